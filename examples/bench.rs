@@ -0,0 +1,83 @@
+//! Bus cost of common drawing operations, from the driver's own counters.
+//!
+//! `cargo run --example bench --features testing,metrics`
+
+use embedded_graphics_core::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use std::time::{Duration, Instant};
+use tftmc043::{
+    metrics::Metrics,
+    testing::{RecordingCs, RecordingSpi},
+    ColorMode, TFTMC043Draw16Bit, TFTMC043,
+};
+
+struct NoDelay;
+
+impl DelayMs<u16> for NoDelay {
+    fn delay_ms(&mut self, _ms: u16) {}
+}
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+type Display = TFTMC043Draw16Bit<RecordingSpi, RecordingCs>;
+
+fn report(name: &str, m: Metrics, elapsed: Duration) {
+    println!(
+        "{:<12} {:>7} transactions {:>8} bytes {:>6} polls {:>8.2?}",
+        name, m.transactions, m.bytes_written, m.busy_polls, elapsed
+    );
+}
+
+fn step(name: &str, rec: &RecordingSpi, display: &mut Display, f: impl FnOnce(&mut Display)) {
+    display.inner_mut().reset_metrics();
+    let t = Instant::now();
+    f(display);
+    report(name, display.inner().metrics(), t.elapsed());
+    rec.clear();
+}
+
+fn main() {
+    let (spi, cs) = RecordingSpi::new_loopback();
+    let rec = spi.clone();
+
+    let mut display = TFTMC043::new(spi, cs, ColorMode::SixteenBit);
+    let t = Instant::now();
+    display.init(&mut NoDelay).unwrap();
+    report("init", display.metrics(), t.elapsed());
+    rec.clear();
+    let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+
+    step("clear", &rec, &mut display, |d| {
+        d.clear(Rgb565::BLACK).unwrap()
+    });
+
+    step("1000 pixels", &rec, &mut display, |d| {
+        //fixed seed LCG, the same pixels every run
+        let mut seed = 1u32;
+        let pixels = (0..1000).map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let x = (seed >> 16) % 480;
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let y = (seed >> 16) % 272;
+            Pixel(Point::new(x as i32, y as i32), Rgb565::WHITE)
+        });
+        d.draw_iter(pixels).unwrap();
+    });
+
+    step("100x100 fill", &rec, &mut display, |d| {
+        d.fill_solid(
+            &Rectangle::new(Point::new(10, 10), Size::new(100, 100)),
+            Rgb565::RED,
+        )
+        .unwrap()
+    });
+
+    step("text", &rec, &mut display, |d| {
+        let inner = d.inner_mut();
+        inner.set_text_cursor(0, 200).unwrap();
+        inner.write_text("The quick brown fox").unwrap();
+        inner.graphic_mode().unwrap();
+    });
+}
@@ -0,0 +1,61 @@
+//! The same scene drawn directly and through a buffer flushed in bands, by bytes on the bus.
+//!
+//! `cargo run --example buffered --features testing,buffered`
+
+use embedded_graphics_core::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use tftmc043::{testing::RecordingSpi, BufferedDisplay, ColorMode, TFTMC043Draw16Bit, TFTMC043};
+
+struct NoDelay;
+
+impl DelayMs<u16> for NoDelay {
+    fn delay_ms(&mut self, _ms: u16) {}
+}
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+const W: usize = 480;
+const BAND: usize = 34;
+
+//a bar graph of single pixel columns, the worst case for per primitive drawing
+fn scene<D: DrawTarget<Color = Rgb565>>(d: &mut D) -> Result<(), D::Error> {
+    d.fill_solid(
+        &Rectangle::new(Point::new(0, 0), Size::new(480, 272)),
+        Rgb565::BLACK,
+    )?;
+    for x in 0..480 {
+        let h = 40 + (x * 7 % 180);
+        let bar = (0..h).map(|y| Pixel(Point::new(x, 250 - y), Rgb565::GREEN));
+        d.draw_iter(bar)?;
+    }
+    Ok(())
+}
+
+fn bytes(rec: &RecordingSpi) -> usize {
+    rec.frames().iter().map(|f| f.len()).sum()
+}
+
+fn main() {
+    let (spi, cs) = RecordingSpi::new_loopback();
+    let rec = spi.clone();
+
+    let mut display = TFTMC043::new(spi, cs, ColorMode::SixteenBit);
+    display.init(&mut NoDelay).unwrap();
+    let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+    rec.clear();
+
+    scene(&mut display).unwrap();
+    println!("direct   {:>8} bytes", bytes(&rec));
+    rec.clear();
+
+    let mut buf = vec![0; BufferedDisplay::<W, BAND>::BUF_LEN];
+    let mut buffered = BufferedDisplay::<W, BAND>::new(&mut buf, 272).unwrap();
+    for n in 0..buffered.bands() {
+        buffered.set_band(n);
+        scene(&mut buffered).unwrap();
+        buffered.flush(display.inner_mut()).unwrap();
+    }
+    println!("buffered {:>8} bytes", bytes(&rec));
+}
@@ -0,0 +1,38 @@
+//! `writeln!` debugging in a strip along the bottom while the rest of the screen is redrawn.
+//!
+//! `cargo run --example debug_strip --features testing,debug-strip`
+
+use core::fmt::Write;
+use embedded_graphics_core::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+use embedded_hal::blocking::delay::DelayUs;
+use tftmc043::{prelude::*, testing::RecordingSpi};
+
+struct NoDelay;
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+fn main() {
+    let (spi, cs) = RecordingSpi::new_loopback();
+
+    let mut display = TFTMC043::builder(spi.clone(), cs)
+        .build_rgb565(&mut NoDelay)
+        .unwrap();
+    //four lines of the internal 8x16 font
+    let mut strip = display
+        .inner_mut()
+        .enable_debug_strip(
+            Rectangle::new(Point::new(0, 272 - 64), Size::new(480, 64)),
+            8,
+            16,
+        )
+        .unwrap();
+
+    for frame in 0..10 {
+        //only clears the area above the strip
+        display.clear(Rgb565::BLUE).unwrap();
+        writeln!(strip.writer(display.inner_mut()), "frame {}", frame).unwrap();
+    }
+    println!("{} frames written", spi.frames().len());
+}
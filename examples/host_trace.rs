@@ -0,0 +1,69 @@
+//! Runs the driver against the recording SPI and prints how much bus traffic each step costs.
+//!
+//! The steps of the former RP2040 RTIC demo, there is no board example, see the README.
+//!
+//! `cargo run --example host_trace --features testing`
+
+use embedded_graphics_core::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use std::time::Instant;
+use tftmc043::{testing::RecordingSpi, ColorMode, TFTMC043Draw16Bit, TFTMC043};
+
+struct NoDelay;
+
+impl DelayMs<u16> for NoDelay {
+    fn delay_ms(&mut self, _ms: u16) {}
+}
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+fn report(step: &str, rec: &RecordingSpi, start: Instant) {
+    let frames = rec.frames();
+    let bytes: usize = frames.iter().map(|f| f.len()).sum();
+    println!(
+        "{:<16} {:>7} frames {:>8} bytes {:>8.2?}",
+        step,
+        frames.len(),
+        bytes,
+        start.elapsed()
+    );
+    rec.clear();
+}
+
+fn main() {
+    let (spi, cs) = RecordingSpi::new_loopback();
+    let rec = spi.clone();
+
+    let mut delay = NoDelay;
+    let mut display = TFTMC043::new(spi, cs, ColorMode::TwentyFourBit);
+
+    let t = Instant::now();
+    display.init(&mut delay).unwrap();
+    report("init", &rec, t);
+
+    let t = Instant::now();
+    display.fade_backlight(100, 10, 500, &mut delay).unwrap();
+    report("backlight fade", &rec, t);
+
+    let t = Instant::now();
+    display.set_color_mode(ColorMode::SixteenBit).unwrap();
+    report("color mode", &rec, t);
+
+    let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+    let t = Instant::now();
+    display
+        .fill_solid(
+            &Rectangle::new(Point::zero(), Size::new(480, 272)),
+            Rgb565::BLUE,
+        )
+        .unwrap();
+    report("fill_solid", &rec, t);
+
+    let t = Instant::now();
+    display
+        .draw_iter((0..64).map(|i| Pixel(Point::new(i, i), Rgb565::WHITE)))
+        .unwrap();
+    report("64 pixels", &rec, t);
+}
@@ -0,0 +1,49 @@
+//! From peripherals to a cleared, 16 bit `DrawTarget` with the builder, the usual way to set
+//! up a display.
+//!
+//! `cargo run --example quickstart --features testing`
+
+use embedded_graphics_core::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+use embedded_hal::{blocking::delay::DelayUs, digital::v2::OutputPin};
+use std::convert::Infallible;
+use tftmc043::{prelude::*, testing::RecordingSpi};
+
+struct NoDelay;
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+//stands in for the GPIO wired to the controller's reset
+struct ResetLine;
+
+impl OutputPin for ResetLine {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn main() {
+    let (spi, cs) = RecordingSpi::new_loopback();
+
+    let mut display = TFTMC043::builder(spi.clone(), cs)
+        .reset_pin(ResetLine)
+        .brightness(80)
+        .orientation(Orientation::Rot180)
+        .build_rgb565(&mut NoDelay)
+        .unwrap();
+
+    display
+        .fill_solid(
+            &Rectangle::new(Point::new(10, 10), Size::new(100, 50)),
+            Rgb565::RED,
+        )
+        .unwrap();
+    println!("{} frames written", spi.frames().len());
+}
@@ -0,0 +1,95 @@
+//! The display sharing one SPI bus with another device, arbitrated per transaction like
+//! `embedded-hal-bus`'s `RefCellDevice`.
+//!
+//! `cargo run --example shared_bus --features eh1`
+
+use core::{cell::RefCell, convert::Infallible};
+use embedded_graphics_core::prelude::*;
+use embedded_hal_1::spi::{ErrorType, Operation, SpiDevice};
+use tftmc043::{ColorMode, TFTMC043Draw16Bit, TFTMC043};
+
+//longest display burst on the bus, in bytes after the prefix
+const MAX_TRANSFER: u16 = 64;
+
+#[derive(Default)]
+struct Bus {
+    //device and length of every transaction, in bus order
+    log: Vec<(&'static str, usize)>,
+}
+
+//one chip select on the shared bus, the bus is only borrowed for a single transaction
+struct Device<'a> {
+    name: &'static str,
+    bus: &'a RefCell<Bus>,
+}
+
+impl ErrorType for Device<'_> {
+    type Error = Infallible;
+}
+
+impl SpiDevice for Device<'_> {
+    fn transaction(&mut self, ops: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        let mut bus = self.bus.borrow_mut();
+        let mut len = 0;
+        for op in ops {
+            match op {
+                Operation::Write(b) => len += b.len(),
+                //the display's status reads see an idle controller with an empty write FIFO
+                Operation::Read(b) => {
+                    b.fill(0x40);
+                    len += b.len();
+                }
+                Operation::Transfer(r, w) => {
+                    r.fill(0x40);
+                    len += r.len().max(w.len());
+                }
+                Operation::TransferInPlace(b) => {
+                    b.fill(0x40);
+                    len += b.len();
+                }
+                Operation::DelayNs(_) => (),
+            }
+        }
+        bus.log.push((self.name, len));
+        Ok(())
+    }
+}
+
+fn main() {
+    let bus = RefCell::new(Bus::default());
+    let mut sd = Device {
+        name: "sd",
+        bus: &bus,
+    };
+
+    let dev = Device {
+        name: "display",
+        bus: &bus,
+    };
+    let mut display = TFTMC043::new_with_device(dev, ColorMode::SixteenBit);
+    display.set_max_transfer_len(MAX_TRANSFER);
+    let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+
+    //a 100 pixel wide Rgb565 image, each row is 200 bytes and split into bursts
+    let row = [0x1f_u8; 200];
+    for y in 0..8 {
+        display.draw_raw_image(Point::new(0, y), 100, &row).unwrap();
+        //the other device gets the bus between display operations
+        sd.write(&[0x51, 0, 0, 0, y as u8, 0xff]).unwrap();
+    }
+
+    let log = &bus.borrow().log;
+    let longest = log
+        .iter()
+        .filter(|(name, _)| *name == "display")
+        .map(|(_, len)| *len)
+        .max()
+        .unwrap_or(0);
+    let sd_count = log.iter().filter(|(name, _)| *name == "sd").count();
+    println!(
+        "{} transactions, {} from the sd card, longest display transaction {} bytes",
+        log.len(),
+        sd_count,
+        longest
+    );
+}
@@ -0,0 +1,32 @@
+//! Logging to the panel with `writeln!` through the hardware text mode.
+//!
+//! `cargo run --example terminal --features testing`
+
+use core::fmt::Write;
+use embedded_hal::blocking::delay::DelayUs;
+use tftmc043::{testing::RecordingSpi, ColorMode, Terminal, TerminalOverflow, TFTMC043};
+
+struct NoDelay;
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+fn main() {
+    let (spi, cs) = RecordingSpi::new_loopback();
+
+    let mut display = TFTMC043::new(spi.clone(), cs, ColorMode::SixteenBit);
+    display.init(&mut NoDelay).unwrap();
+
+    //the internal 8x16 font
+    let mut term = Terminal::new(&mut display, 8, 16, TerminalOverflow::Scroll).unwrap();
+    for i in 0..20 {
+        if i % 5 == 0 {
+            term.set_colors((0xff, 0xff, 0), (0, 0, 0x80));
+        } else {
+            term.set_colors((0xff, 0xff, 0xff), (0, 0, 0));
+        }
+        writeln!(term, "line {} of the log", i).unwrap();
+    }
+    println!("{} frames written", spi.frames().len());
+}
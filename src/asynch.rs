@@ -0,0 +1,347 @@
+//! Async driver built on embedded-hal-async, for executors like Embassy.
+//!
+//! Shares the register programming with the blocking [`ER5517`](crate::ER5517) driver, busy
+//! polls yield to the executor between status reads. embedded-graphics `DrawTarget` is sync only
+//! so this driver offers [`TFTMC043Async::fill_rect`] and [`TFTMC043Async::draw_raw`] instead.
+
+use crate::{common, regs, ColorMode, Error, PanelConfig, Res};
+use core::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use embedded_hal_async::{delay::DelayNs, spi::SpiDevice};
+
+type ARes<T, S> = Res<T, Infallible, S>;
+
+pub struct TFTMC043Async<SPI> {
+    config: PanelConfig,
+    color_mode: ColorMode,
+    busy_timeout: u32,
+    spi: SPI,
+}
+
+//pending once so other tasks get to run between status polls
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+impl<SPI, SPIErr> TFTMC043Async<SPI>
+where
+    SPI: SpiDevice<u8, Error = SPIErr>,
+{
+    pub fn new(spi: SPI, color_mode: ColorMode) -> Self {
+        Self::new_with_config(spi, color_mode, PanelConfig::default())
+    }
+
+    pub fn new_with_config(spi: SPI, color_mode: ColorMode, config: PanelConfig) -> Self {
+        Self {
+            config,
+            color_mode,
+            busy_timeout: u32::MAX,
+            spi,
+        }
+    }
+
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+
+    pub fn config(&self) -> &PanelConfig {
+        &self.config
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    /// Limit the number of status polls in busy waits before giving up with [`Error::Timeout`].
+    pub fn set_busy_timeout(&mut self, iterations: u32) {
+        self.busy_timeout = iterations;
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> ARes<(), SPIErr> {
+        self.spi.write(bytes).await.map_err(Error::SPI)
+    }
+
+    async fn read(&mut self, prefix: u8) -> ARes<u8, SPIErr> {
+        let mut d: [u8; 2] = [prefix, 0x00];
+        self.spi
+            .transfer_in_place(&mut d)
+            .await
+            .map_err(Error::SPI)?;
+        Ok(d[1])
+    }
+
+    async fn cmd_write(&mut self, cmd: u8) -> ARes<(), SPIErr> {
+        self.write(&[common::CMD_WRITE, cmd]).await
+    }
+
+    async fn data_write(&mut self, data: u8) -> ARes<(), SPIErr> {
+        self.write(&[common::DATA_WRITE, data]).await
+    }
+
+    pub async fn status_read(&mut self) -> ARes<u8, SPIErr> {
+        self.read(common::STATUS_READ).await
+    }
+
+    pub async fn data_read(&mut self) -> ARes<u8, SPIErr> {
+        self.read(common::DATA_READ).await
+    }
+
+    pub async fn register_write(&mut self, cmd: u8, data: u8) -> ARes<(), SPIErr> {
+        self.cmd_write(cmd).await?;
+        self.data_write(data).await
+    }
+
+    async fn register_write_all(&mut self, pairs: &[(u8, u8)]) -> ARes<(), SPIErr> {
+        for &(reg, v) in pairs {
+            self.register_write(reg, v).await?;
+        }
+        Ok(())
+    }
+
+    async fn modify(&mut self, reg: u8, clear: u8, set: u8) -> ARes<(), SPIErr> {
+        self.cmd_write(reg).await?;
+        let v = (self.data_read().await? & !clear) | set;
+        self.data_write(v).await
+    }
+
+    async fn wait_status<F: Fn(u8) -> bool>(&mut self, done: F) -> ARes<(), SPIErr> {
+        for _ in 0..self.busy_timeout {
+            if done(self.status_read().await?) {
+                return Ok(());
+            }
+            YieldNow(false).await;
+        }
+        Err(Error::Timeout)
+    }
+
+    pub async fn busy_draw(&mut self) -> ARes<(), SPIErr> {
+        self.wait_status(|s| s & common::STATUS_CORE_BUSY == 0)
+            .await
+    }
+
+    #[deprecated(note = "use wait_wr_fifo_not_full")]
+    pub async fn check_mem_wr_fifo_ready(&mut self) -> ARes<(), SPIErr> {
+        self.wait_wr_fifo_not_full().await
+    }
+
+    pub async fn wait_wr_fifo_not_full(&mut self) -> ARes<(), SPIErr> {
+        self.wait_status(|s| s & common::STATUS_WR_FIFO_FULL == 0)
+            .await
+    }
+
+    pub async fn wait_wr_fifo_empty(&mut self) -> ARes<(), SPIErr> {
+        self.wait_status(|s| s & common::STATUS_WR_FIFO_EMPTY != 0)
+            .await
+    }
+
+    async fn system_check_temp<D: DelayNs>(&mut self, delay: &mut D) -> ARes<(), SPIErr> {
+        for _ in 0..self.busy_timeout {
+            if self.status_read().await? & common::STATUS_INHIBIT == 0 {
+                delay.delay_us(common::CHECK_STEP_US).await;
+                self.cmd_write(regs::CCR).await?;
+                delay.delay_us(common::CHECK_STEP_US).await;
+                if self.data_read().await? & 0x80 == 0x80 {
+                    return Ok(());
+                }
+                delay.delay_us(common::CHECK_STEP_US).await;
+                self.cmd_write(regs::CCR).await?;
+                delay.delay_us(common::CHECK_STEP_US).await;
+                self.data_write(0x80).await?;
+            } else {
+                YieldNow(false).await;
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// See [`ER5517::chip_detect`](crate::ER5517::chip_detect).
+    pub async fn chip_detect(&mut self) -> ARes<bool, SPIErr> {
+        let s = self.status_read().await?;
+        if s & 0b1100_0000 == 0b1100_0000 {
+            return Ok(false);
+        }
+        for v in [0xa5, 0x5a] {
+            self.register_write(regs::FGCR, v).await?;
+            self.cmd_write(regs::FGCR).await?;
+            if self.data_read().await? != v {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    pub async fn init<D: DelayNs>(&mut self, delay: &mut D) -> ARes<(), SPIErr> {
+        //detect writes a scratch register, which is ignored until the inhibit bit clears
+        self.wait_status(|s| s & common::STATUS_INHIBIT == 0)
+            .await?;
+        if !self.chip_detect().await? {
+            return Err(Error::NotDetected);
+        }
+        self.system_check_temp(delay).await?;
+        delay.delay_us(common::SETTLE_US).await;
+        self.wait_status(|s| s & common::STATUS_INHIBIT == 0)
+            .await?;
+
+        self.register_write_all(&common::pll_registers(&self.config))
+            .await?;
+        self.cmd_write(regs::SRR).await?;
+        delay.delay_us(common::PLL_START_US).await;
+        self.data_write(0x80).await?;
+        delay.delay_us(common::PLL_START_US).await;
+        self.register_write_all(&common::PWM_INIT).await?;
+
+        self.register_write_all(&common::SDRAM_INIT).await?;
+        self.wait_status(|s| s & common::STATUS_SDRAM_READY != 0)
+            .await?;
+        delay.delay_us(common::PLL_START_US).await;
+
+        for (reg, clear, set) in common::INIT_MODIFY {
+            self.modify(reg, clear, set).await?;
+        }
+        let timing = common::timing_registers(&self.config).ok_or(Error::InvalidParam)?;
+        self.register_write_all(&timing).await?;
+
+        let mode = self.color_mode;
+        self.modify(regs::MPWCTR, 0b1100, common::main_window_color_bits(mode))
+            .await?;
+        self.modify(regs::AW_COLOR, 0b0100, 0).await?; //canvas block (xy) addressing
+        self.modify(regs::AW_COLOR, 0b0011, common::memory_color_bits(mode))
+            .await?;
+
+        let (w, h) = (self.config.width, self.config.height);
+        self.main_image(0, w).await?;
+        self.canvas_image(0, w).await?;
+        self.active_window(0, 0, w, h).await?;
+        self.on(true).await
+    }
+
+    pub async fn on(&mut self, on: bool) -> ARes<(), SPIErr> {
+        let mask = 0b0100_0000u8;
+        if on {
+            self.modify(regs::DPCR, 0, mask).await
+        } else {
+            self.modify(regs::DPCR, mask, 0).await
+        }
+    }
+
+    pub async fn set_color_mode(&mut self, mode: ColorMode) -> ARes<(), SPIErr> {
+        if mode != self.color_mode {
+            self.modify(regs::AW_COLOR, 0b0011, common::memory_color_bits(mode))
+                .await?;
+            self.modify(regs::MPWCTR, 0b1100, common::main_window_color_bits(mode))
+                .await?;
+            self.color_mode = mode;
+        }
+        Ok(())
+    }
+
+    async fn main_image(&mut self, addr: u32, w: u16) -> ARes<(), SPIErr> {
+        let a = addr.to_le_bytes();
+        self.register_write_all(&[
+            (regs::MISA0, a[0]),
+            (regs::MISA0 + 1, a[1]),
+            (regs::MISA0 + 2, a[2]),
+            (regs::MISA0 + 3, a[3]),
+        ])
+        .await?;
+        self.register_write_all(&common::quad_registers(regs::MIW0, [w, 0, 0, 0])[..6])
+            .await
+    }
+
+    async fn canvas_image(&mut self, addr: u32, w: u16) -> ARes<(), SPIErr> {
+        let a = addr.to_le_bytes();
+        self.register_write_all(&[
+            (regs::CVSSA0, a[0]),
+            (regs::CVSSA0 + 1, a[1]),
+            (regs::CVSSA0 + 2, a[2]),
+            (regs::CVSSA0 + 3, a[3]),
+        ])
+        .await?;
+        self.register_write_all(&common::quad_registers(regs::CVS_IMWTH0, [w, 0, 0, 0])[..2])
+            .await
+    }
+
+    //XXX expects 8-bit colors
+    pub async fn fg_color(&mut self, r: u8, g: u8, b: u8) -> ARes<(), SPIErr> {
+        self.register_write_all(&[(regs::FGCR, r), (regs::FGCG, g), (regs::FGCB, b)])
+            .await
+    }
+
+    pub async fn active_window(&mut self, x: u16, y: u16, w: u16, h: u16) -> ARes<(), SPIErr> {
+        self.register_write_all(&common::quad_registers(regs::AWUL_X0, [x, y, w, h]))
+            .await
+    }
+
+    pub async fn goto_pixel(&mut self, x: u16, y: u16) -> ARes<(), SPIErr> {
+        self.register_write_all(&common::quad_registers(regs::CURH0, [x, y, 0, 0])[..4])
+            .await
+    }
+
+    /// Fill the rectangle from the last line start to line end with the fg color.
+    pub async fn rect_fill(&mut self) -> ARes<(), SPIErr> {
+        self.register_write(regs::DCR1, 0xe0).await?;
+        self.busy_draw().await
+    }
+
+    //XXX expects 8-bit colors
+    pub async fn fill_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        color: (u8, u8, u8),
+    ) -> ARes<(), SPIErr> {
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+        self.fg_color(color.0, color.1, color.2).await?;
+        self.register_write_all(&common::quad_registers(
+            regs::DLHSR0,
+            [x, y, x + (w - 1), y + (h - 1)],
+        ))
+        .await?;
+        self.rect_fill().await
+    }
+
+    /// Write `data`, encoded for the current color mode, into the `w` x `h` rectangle at `x`, `y`.
+    pub async fn draw_raw(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        data: &[u8],
+    ) -> ARes<(), SPIErr> {
+        let len = w as usize * h as usize * self.color_mode.bytes_per_pixel() as usize;
+        if data.len() < len {
+            return Err(Error::InvalidParam);
+        }
+        self.active_window(x, y, w, h).await?;
+        self.goto_pixel(x, y).await?;
+        self.cmd_write(regs::MRWDP).await?;
+        for &b in &data[..len] {
+            self.wait_wr_fifo_not_full().await?;
+            self.data_write(b).await?;
+        }
+        self.wait_wr_fifo_empty().await?;
+        let (w, h) = (self.config.width, self.config.height);
+        self.active_window(0, 0, w, h).await
+    }
+}
@@ -0,0 +1,441 @@
+//! Block transfer engine, REG[90h]-REG[B5h]
+//!
+//! BTE coordinates address the image in memory, they are not affected by the orientation.
+
+use crate::{common, regs, ColorMode, Error, Interface, Res, ER5517};
+
+//REG[91h] bits 3-0
+const OP_MPU_WRITE_ROP: u8 = 0b0000;
+const OP_MEMORY_COPY_ROP: u8 = 0b0010;
+const OP_MPU_WRITE_CHROMA: u8 = 0b0100;
+const OP_MEMORY_COPY_CHROMA: u8 = 0b0101;
+const OP_MEMORY_COPY_ALPHA: u8 = 0b1010;
+const OP_SOLID_FILL: u8 = 0b1100;
+
+//REG[B5h] bits 5-0, source 0 weight in 32nds
+const ALPHA_MAX: u16 = 32;
+
+/// BTE raster operation, how the source `S` combines with the destination `D`
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RopCode {
+    /// all zero
+    Blackness,
+    /// !(S | D)
+    NotOr,
+    /// !S & D
+    NotSourceAnd,
+    /// !S
+    NotSource,
+    /// S & !D
+    SourceAndNot,
+    /// !D
+    NotDest,
+    /// S ^ D
+    Xor,
+    /// !(S & D)
+    NotAnd,
+    /// S & D
+    And,
+    /// !(S ^ D)
+    NotXor,
+    /// D, leaves the destination unchanged
+    Dest,
+    /// !S | D
+    NotSourceOr,
+    /// S
+    Source,
+    /// S | !D
+    SourceOrNot,
+    /// S | D
+    Or,
+    /// all ones
+    Whiteness,
+}
+
+impl RopCode {
+    //REG[91h] bits 7-4, the variants are in code order
+    fn bits(self) -> u8 {
+        self as u8
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    //source 0 image and start position
+    pub(crate) fn bte_source0(
+        &mut self,
+        addr: u32,
+        w: u16,
+        x: u16,
+        y: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u32(regs::S0_STR0, addr)?;
+        self.register_write_u16(regs::S0_WTH0, w)?;
+        self.register_write_u16(regs::S0_X0, x)?;
+        self.register_write_u16(regs::S0_Y0, y)
+    }
+
+    //source 1 image and start position
+    pub(crate) fn bte_source1(
+        &mut self,
+        addr: u32,
+        w: u16,
+        x: u16,
+        y: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u32(regs::S1_STR0, addr)?;
+        self.register_write_u16(regs::S1_WTH0, w)?;
+        self.register_write_u16(regs::S1_X0, x)?;
+        self.register_write_u16(regs::S1_Y0, y)
+    }
+
+    //destination at `x`, `y` on the current canvas, with the `w` x `h` block size
+    pub(crate) fn bte_dest(&mut self, x: u16, y: u16, w: u16, h: u16) -> Res<(), PinErr, SPIErr> {
+        let (addr, image_w) = self.canvas_target();
+        self.bte_dest_at(addr, image_w, x, y, w, h)
+    }
+
+    //destination at `x`, `y` of the image at `addr`, `image_w` pixels wide
+    pub(crate) fn bte_dest_at(
+        &mut self,
+        addr: u32,
+        image_w: u16,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        if w == 0 || h == 0 {
+            return Err(Error::InvalidParam);
+        }
+        self.register_write_u32(regs::DT_STR0, addr)?;
+        self.register_write_u16(regs::DT_WTH0, image_w)?;
+        self.register_write_u16(regs::DT_X0, x)?;
+        self.register_write_u16(regs::DT_Y0, y)?;
+        self.register_write_u16(regs::BTE_WTH0, w)?;
+        self.register_write_u16(regs::BTE_HIG0, h)
+    }
+
+    //all surfaces in the memory color mode, REG[92h]
+    pub(crate) fn bte_color_depths(&mut self) -> Res<(), PinErr, SPIErr> {
+        let b = common::memory_color_bits(self.color_mode());
+        self.register_write(regs::BTE_COLR, (b << 5) | (b << 2) | b)
+    }
+
+    //REG[91h] operation and raster operation, then set REG[90h] bit 4 to start
+    pub(crate) fn bte_start(&mut self, op: u8, rop: u8) -> Res<(), PinErr, SPIErr> {
+        self.register_write(regs::BTE_CTRL1, (rop << 4) | op)?;
+        self.modify(regs::BTE_CTRL0, 0, 0b0001_0000)
+    }
+
+    //clear REG[90h] bit 4, ending an operation still waiting for data
+    fn bte_stop(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::BTE_CTRL0, 0b0001_0000, 0)
+    }
+
+    //fill `w` x `h` at `x`, `y` of the image at `addr` with `c`, the BTE fills in the fg color
+    pub(crate) fn bte_solid_fill_at(
+        &mut self,
+        (addr, image_w): (u32, u16),
+        (x, y): (u16, u16),
+        (w, h): (u16, u16),
+        c: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.bte_dest_at(addr, image_w, x, y, w, h)?;
+        self.bte_color_depths()?;
+        self.write_fg(c)?;
+        self.bte_start(OP_SOLID_FILL, 0)?;
+        self.busy_draw()
+    }
+
+    //copy a block within the canvas
+    pub(crate) fn bte_copy_within(
+        &mut self,
+        src: (u16, u16),
+        dst: (u16, u16),
+        w: u16,
+        h: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        let (addr, image_w) = self.canvas_target();
+        self.bte_dest(dst.0, dst.1, w, h)?;
+        self.bte_source0(addr, image_w, src.0, src.1)?;
+        self.bte_color_depths()?;
+        self.bte_start(OP_MEMORY_COPY_ROP, RopCode::Source.bits())?;
+        self.busy_draw()
+    }
+
+    //start a block write from the host to the canvas, the caller streams the pixel data to the
+    //memory port then waits for busy_draw
+    pub(crate) fn bte_write_start(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        if self.is_text_mode() {
+            self.graphic_mode()?;
+        }
+        self.bte_dest(x, y, w, h)?;
+        self.bte_color_depths()?;
+        self.bte_start(OP_MPU_WRITE_ROP, RopCode::Source.bits())
+    }
+
+    /// Copy the `w` x `h` block at `src_x`, `src_y` of the image at `src_addr` to the canvas,
+    /// skipping source pixels matching `key`.
+    ///
+    /// The source image has the canvas width and color mode. The bg color set before is kept,
+    /// the key only holds the background color registers during the copy.
+    #[allow(clippy::too_many_arguments)]
+    pub fn bte_copy_chroma(
+        &mut self,
+        src_addr: u32,
+        src_x: u16,
+        src_y: u16,
+        dst_x: u16,
+        dst_y: u16,
+        w: u16,
+        h: u16,
+        key: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        let (_, image_w) = self.canvas_target();
+        self.bte_copy_chroma_from(src_addr, image_w, (src_x, src_y), (dst_x, dst_y), w, h, key)
+    }
+
+    //bte_copy_chroma from an image `src_w` pixels wide
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn bte_copy_chroma_from(
+        &mut self,
+        src_addr: u32,
+        src_w: u16,
+        src: (u16, u16),
+        dst: (u16, u16),
+        w: u16,
+        h: u16,
+        key: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.bte_dest(dst.0, dst.1, w, h)?;
+        self.bte_source0(src_addr, src_w, src.0, src.1)?;
+        self.bte_color_depths()?;
+        //the BTE compares against the bg registers
+        self.with_bg(key, |s| {
+            s.bte_start(OP_MEMORY_COPY_CHROMA, 0)?;
+            s.busy_draw()
+        })
+    }
+
+    /// Write the `w` x `h` block of pixels in `data` to the canvas at `x`, `y`, skipping pixels
+    /// matching `key`.
+    ///
+    /// `data` is in the current color mode's memory format, see [`ER5517::read_pixels`]. The bg
+    /// color is put back afterwards, like [`ER5517::bte_copy_chroma`].
+    pub fn bte_write_chroma(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        key: (u8, u8, u8),
+        data: &[u8],
+    ) -> Res<(), PinErr, SPIErr> {
+        let len = (w as usize)
+            .saturating_mul(h as usize)
+            .saturating_mul(self.color_mode().bytes_per_pixel() as usize);
+        if data.len() != len {
+            return Err(Error::InvalidParam);
+        }
+        self.check_awake()?;
+        if self.is_text_mode() {
+            self.graphic_mode()?;
+        }
+        self.bte_dest(x, y, w, h)?;
+        self.bte_color_depths()?;
+        self.with_bg(key, |s| {
+            s.bte_start(OP_MPU_WRITE_CHROMA, 0)?;
+            s.cmd_write(regs::MRWDP)?;
+            s.data_write_buf(data)?;
+            s.busy_draw()
+        })
+    }
+
+    /// Write the `w` x `h` block of pixels in `data` to `x`, `y` of the image at `dst_addr`,
+    /// `dst_image_w` pixels wide, the BTE steps to the next row.
+    ///
+    /// `data` is in the current color mode's memory format. With `key` the pixels matching it
+    /// are skipped, the bg color is put back afterwards. The BTE is stopped if the write
+    /// fails.
+    #[allow(clippy::too_many_arguments)]
+    pub fn bte_mcu_write(
+        &mut self,
+        dst_addr: u32,
+        dst_image_w: u16,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        key: Option<(u8, u8, u8)>,
+        data: &[u8],
+    ) -> Res<(), PinErr, SPIErr> {
+        let len = (w as usize)
+            .saturating_mul(h as usize)
+            .saturating_mul(self.color_mode().bytes_per_pixel() as usize);
+        if w == 0 || h == 0 || data.len() != len {
+            return Err(Error::InvalidParam);
+        }
+        //w and h are at least 1
+        let last = x
+            .checked_add(w.saturating_sub(1))
+            .zip(y.checked_add(h.saturating_sub(1)))
+            .and_then(|(x1, y1)| self.pixel_address(dst_addr, dst_image_w, x1, y1));
+        if x.checked_add(w).is_none_or(|end| end > dst_image_w) || last.is_none() {
+            return Err(Error::OutOfBounds);
+        }
+        self.check_awake()?;
+        if self.is_text_mode() {
+            self.graphic_mode()?;
+        }
+        self.bte_dest_at(dst_addr, dst_image_w, x, y, w, h)?;
+        self.bte_color_depths()?;
+        match key {
+            Some(key) => self.with_bg(key, |s| s.bte_mcu_stream(OP_MPU_WRITE_CHROMA, 0, data)),
+            None => self.bte_mcu_stream(OP_MPU_WRITE_ROP, RopCode::Source.bits(), data),
+        }
+    }
+
+    //start `op` and stream `data` to it, stopping the BTE if that fails
+    fn bte_mcu_stream(&mut self, op: u8, rop: u8, data: &[u8]) -> Res<(), PinErr, SPIErr> {
+        self.bte_start(op, rop)?;
+        let r = self
+            .cmd_write(regs::MRWDP)
+            .and_then(|_| self.data_write_buf(data))
+            .and_then(|_| self.busy_draw());
+        if r.is_err() {
+            let _ = self.bte_stop();
+        }
+        r
+    }
+
+    /// Blend the `w` x `h` blocks at `s0_x`, `s0_y` of `s0_addr` and `s1_x`, `s1_y` of
+    /// `s1_addr` into `dst_x`, `dst_y` of `dst_addr`.
+    ///
+    /// `alpha` weights source 0, 255 copies it and 0 copies source 1, in 32 steps. All images
+    /// have the canvas width and color mode, which has to be 16 or 24 bit.
+    #[allow(clippy::too_many_arguments)]
+    pub fn bte_alpha_blend(
+        &mut self,
+        s0_addr: u32,
+        s0_x: u16,
+        s0_y: u16,
+        s1_addr: u32,
+        s1_x: u16,
+        s1_y: u16,
+        dst_addr: u32,
+        dst_x: u16,
+        dst_y: u16,
+        w: u16,
+        h: u16,
+        alpha: u8,
+    ) -> Res<(), PinErr, SPIErr> {
+        if self.color_mode() == ColorMode::EightBit {
+            return Err(Error::InvalidParam);
+        }
+        self.check_awake()?;
+        let (_, image_w) = self.canvas_target();
+        self.bte_dest_at(dst_addr, image_w, dst_x, dst_y, w, h)?;
+        self.bte_source0(s0_addr, image_w, s0_x, s0_y)?;
+        self.bte_source1(s1_addr, image_w, s1_x, s1_y)?;
+        self.bte_color_depths()?;
+        //at most 255 * ALPHA_MAX + 127 before the division
+        let level = (alpha as u16).saturating_mul(ALPHA_MAX).saturating_add(127) / 255;
+        self.register_write(regs::APB_CTRL, level as u8)?;
+        self.bte_start(OP_MEMORY_COPY_ALPHA, 0)?;
+        self.busy_draw()
+    }
+
+    /// Combine the `w` x `h` block at `src_x`, `src_y` of `src_addr` with the block at `dst_x`,
+    /// `dst_y` of `dst_addr` using `rop`, the result replaces the destination block.
+    ///
+    /// Both images have the canvas width and color mode. [`RopCode::Xor`] applied twice restores
+    /// the destination.
+    #[allow(clippy::too_many_arguments)]
+    pub fn bte_rop(
+        &mut self,
+        src_addr: u32,
+        src_x: u16,
+        src_y: u16,
+        dst_addr: u32,
+        dst_x: u16,
+        dst_y: u16,
+        w: u16,
+        h: u16,
+        rop: RopCode,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        let (_, image_w) = self.canvas_target();
+        self.bte_dest_at(dst_addr, image_w, dst_x, dst_y, w, h)?;
+        self.bte_source0(src_addr, image_w, src_x, src_y)?;
+        //the destination is read through source 1
+        self.bte_source1(dst_addr, image_w, dst_x, dst_y)?;
+        self.bte_color_depths()?;
+        self.bte_start(OP_MEMORY_COPY_ROP, rop.bits())?;
+        self.busy_draw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::support;
+    use crate::ColorMode;
+    use std::vec::Vec;
+
+    const KEY: (u8, u8, u8) = (0x12, 0x34, 0x56);
+
+    //values written to BGCR, in order
+    fn bg_reds(writes: &[(u8, u8)]) -> Vec<u8> {
+        writes
+            .iter()
+            .filter(|(r, _)| *r == regs::BGCR)
+            .map(|&(_, v)| v)
+            .collect()
+    }
+
+    #[test]
+    fn chroma_keeps_bg_color() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.bg_color(9, 8, 7).unwrap();
+        rec.clear();
+        display
+            .bte_copy_chroma(0x10_0000, 0, 0, 4, 4, 8, 8, KEY)
+            .unwrap();
+        assert_eq!(bg_reds(&rec.register_writes()), [KEY.0, 9]);
+        rec.clear();
+        display.bte_write_chroma(4, 4, 1, 1, KEY, &[0, 0]).unwrap();
+        assert_eq!(bg_reds(&rec.register_writes()), [KEY.0, 9]);
+        let bg = [regs::BGCR, regs::BGCG, regs::BGCB].map(|r| rec.register(r));
+        assert_eq!(bg, [9, 8, 7]);
+    }
+
+    #[test]
+    fn mcu_write_keeps_bg_color() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.bg_color(9, 8, 7).unwrap();
+        rec.clear();
+        let data = [0; 8];
+        display
+            .bte_mcu_write(0x10_0000, 64, 0, 0, 2, 2, Some(KEY), &data)
+            .unwrap();
+        assert_eq!(bg_reds(&rec.register_writes()), [KEY.0, 9]);
+        rec.clear();
+        display
+            .bte_mcu_write(0x10_0000, 64, 0, 0, 2, 2, None, &data)
+            .unwrap();
+        assert!(bg_reds(&rec.register_writes()).is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+//! Rgb565 frame buffer in MCU memory, flushing only what changed
+
+use crate::{color, ColorMode, Error, Interface, Res, TFTMC043};
+use core::convert::Infallible;
+use embedded_graphics_core::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+
+/// In memory `DrawTarget` of a `W` x `H` band, pushed to the panel by [`BufferedDisplay::flush`]
+///
+/// The band can be shorter than the drawing area to save memory: draw the scene once per band
+/// with [`BufferedDisplay::set_band`] between the passes, pixels outside the band are dropped.
+/// Equal heights make it a full frame buffer.
+pub struct BufferedDisplay<'a, const W: usize, const H: usize> {
+    buf: &'a mut [u8],
+    height: u16,
+    band_y: u16,
+    //changed pixels in band coordinates, inclusive
+    dirty: Option<(u16, u16, u16, u16)>,
+}
+
+impl<'a, const W: usize, const H: usize> BufferedDisplay<'a, W, H> {
+    /// Bytes `buf` needs, 2 per pixel.
+    pub const BUF_LEN: usize = W * H * 2;
+
+    /// A buffer for drawing `height` rows, starting with the band at the top.
+    ///
+    /// None if `buf` is shorter than [`Self::BUF_LEN`] or the band doesn't fit a `u16`
+    /// coordinate, the contents are kept and count as clean.
+    pub fn new(buf: &'a mut [u8], height: u16) -> Option<Self> {
+        if buf.len() < Self::BUF_LEN
+            || W == 0
+            || H == 0
+            || W > u16::MAX as usize
+            || H > u16::MAX as usize
+        {
+            return None;
+        }
+        Some(Self {
+            buf,
+            height,
+            band_y: 0,
+            dirty: None,
+        })
+    }
+
+    /// Bands needed to cover the drawing height.
+    pub fn bands(&self) -> u16 {
+        self.height.div_ceil(H as u16)
+    }
+
+    /// Move the band to `n` bands from the top, the buffer is kept but counts as clean.
+    pub fn set_band(&mut self, n: u16) {
+        self.band_y = n.saturating_mul(H as u16);
+        self.dirty = None;
+    }
+
+    /// The part of the drawing area the band covers.
+    pub fn band(&self) -> Rectangle {
+        let rows = (H as u16).min(self.height.saturating_sub(self.band_y));
+        Rectangle::new(
+            Point::new(0, self.band_y as i32),
+            Size::new(W as u32, rows as u32),
+        )
+    }
+
+    /// Changed pixels since the last flush, in drawing coordinates.
+    pub fn dirty(&self) -> Option<Rectangle> {
+        self.dirty.map(|(x0, y0, x1, y1)| {
+            Rectangle::new(
+                Point::new(x0 as i32, self.band_y.saturating_add(y0) as i32),
+                Size::new(span(x0, x1), span(y0, y1)),
+            )
+        })
+    }
+
+    /// Write the changed pixels to `display` and mark the band clean.
+    ///
+    /// Needs 16 bit color, the rows are streamed to the active window like
+    /// [`TFTMC043Draw16Bit::draw_raw_image`](crate::TFTMC043Draw16Bit::draw_raw_image).
+    pub fn flush<SPI, CS, P, RST, PinErr, SPIErr>(
+        &mut self,
+        display: &mut TFTMC043<SPI, CS, P, RST>,
+    ) -> Res<(), PinErr, SPIErr>
+    where
+        SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    {
+        if display.color_mode() != ColorMode::SixteenBit {
+            return Err(Error::InvalidParam);
+        }
+        let (x0, y0, x1, y1) = match self.dirty {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+        let top_left =
+            display.to_screen(Point::new(x0 as i32, self.band_y.saturating_add(y0) as i32));
+        let size = Size::new(span(x0, x1), span(y0, y1));
+        let area = Rectangle::new(top_left, size);
+        let visible = match display.begin_window(&area)? {
+            Some(v) => v,
+            None => {
+                self.dirty = None;
+                return Ok(());
+            }
+        };
+        //the visible part is inside `area`
+        let x = x0.saturating_add(visible.top_left.x.saturating_sub(top_left.x) as u16);
+        let y = y0.saturating_add(visible.top_left.y.saturating_sub(top_left.y) as u16);
+        let len = (visible.size.width as usize).saturating_mul(2);
+        display.cmd_write(crate::regs::MRWDP)?;
+        for row in y..y.saturating_add(visible.size.height as u16) {
+            let start = Self::offset(x, row);
+            //always there, `new` checked the buffer holds the whole band
+            if let Some(px) = self.buf.get(start..start.saturating_add(len)) {
+                display.data_write_buf(px)?;
+            }
+        }
+        display.end_window()?;
+        self.dirty = None;
+        Ok(())
+    }
+
+    //band coordinates of `p`, if it falls into the band
+    fn local(&self, p: Point) -> Option<(u16, u16)> {
+        if self.band().contains(p) {
+            Some((p.x as u16, p.y.saturating_sub(self.band_y as i32) as u16))
+        } else {
+            None
+        }
+    }
+
+    //byte offset of the band pixel at `x`, `y`, bands are far from usize::MAX bytes
+    fn offset(x: u16, y: u16) -> usize {
+        (y as usize)
+            .saturating_mul(W)
+            .saturating_add(x as usize)
+            .saturating_mul(2)
+    }
+
+    fn mark(&mut self, x0: u16, y0: u16, x1: u16, y1: u16) {
+        self.dirty = Some(match self.dirty {
+            Some((a, b, c, d)) => (a.min(x0), b.min(y0), c.max(x1), d.max(y1)),
+            None => (x0, y0, x1, y1),
+        });
+    }
+}
+
+impl<const W: usize, const H: usize> OriginDimensions for BufferedDisplay<'_, W, H> {
+    fn size(&self) -> Size {
+        Size::new(W as u32, self.height as u32)
+    }
+}
+
+impl<const W: usize, const H: usize> DrawTarget for BufferedDisplay<'_, W, H> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(p, c) in pixels {
+            if let Some((x, y)) = self.local(p) {
+                let i = Self::offset(x, y);
+                if let Some(px) = self.buf.get_mut(i..i.saturating_add(2)) {
+                    px.copy_from_slice(&color::rgb565_to_bytes(c));
+                    self.mark(x, y, x, y);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.band());
+        let Size { width, height } = area.size;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let (x0, y0) = match self.local(area.top_left) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        //inside the band, so neither saturates
+        let x1 = x0.saturating_add(width.saturating_sub(1) as u16);
+        let y1 = y0.saturating_add(height.saturating_sub(1) as u16);
+        let bytes = color::rgb565_to_bytes(color);
+        for y in y0..=y1 {
+            let (start, end) = (Self::offset(x0, y), Self::offset(x1, y).saturating_add(2));
+            for px in self
+                .buf
+                .get_mut(start..end)
+                .unwrap_or_default()
+                .chunks_exact_mut(2)
+            {
+                px.copy_from_slice(&bytes);
+            }
+        }
+        self.mark(x0, y0, x1, y1);
+        Ok(())
+    }
+}
+
+//pixels from `a` to `b` inclusive, dirty rectangles never have `b` below `a`
+fn span(a: u16, b: u16) -> u32 {
+    (b.saturating_sub(a) as u32).saturating_add(1)
+}
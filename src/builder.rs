@@ -0,0 +1,135 @@
+//! Configure and initialize a display in one expression, from [`TFTMC043::builder`]
+
+use crate::{
+    ColorMode, InitError, InitStage, Interface, NoPin, Orientation, PanelConfig, ResetPin,
+    TFTMC043Draw16Bit, TFTMC043Draw24Bit, Tftmc043Panel, TFTMC043,
+};
+use core::marker::PhantomData;
+use embedded_graphics_core::{
+    pixelcolor::{Rgb565, Rgb888},
+    prelude::*,
+};
+use embedded_hal::blocking::delay::DelayUs;
+
+/// Settings for [`TFTMC043Builder::build`], which resets, initializes and configures the
+/// display in the order the controller needs
+pub struct TFTMC043Builder<SPI, CS, RST = NoPin> {
+    spi: SPI,
+    cs: CS,
+    reset: RST,
+    color_mode: ColorMode,
+    config: PanelConfig,
+    orientation: Orientation,
+    brightness: u8,
+}
+
+impl<SPI, CS, PinErr, SPIErr> TFTMC043<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Start a [`TFTMC043Builder`], 24 bit color, normal orientation and full brightness on the
+    /// TFTMC043 panel unless changed.
+    pub fn builder(spi: SPI, cs: CS) -> TFTMC043Builder<SPI, CS> {
+        TFTMC043Builder {
+            spi,
+            cs,
+            reset: NoPin,
+            color_mode: ColorMode::TwentyFourBit,
+            config: PanelConfig::default(),
+            orientation: Orientation::Normal,
+            brightness: 100,
+        }
+    }
+}
+
+impl<SPI, CS, RST, PinErr, SPIErr> TFTMC043Builder<SPI, CS, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    RST: ResetPin<PinErr>,
+{
+    pub fn color_mode(mut self, mode: ColorMode) -> Self {
+        self.color_mode = mode;
+        self
+    }
+
+    /// Timing for another panel, checked by `build`.
+    pub fn config(mut self, config: PanelConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Pulse `reset` before init.
+    pub fn reset_pin<R: ResetPin<PinErr>>(self, reset: R) -> TFTMC043Builder<SPI, CS, R> {
+        TFTMC043Builder {
+            spi: self.spi,
+            cs: self.cs,
+            reset,
+            color_mode: self.color_mode,
+            config: self.config,
+            orientation: self.orientation,
+            brightness: self.brightness,
+        }
+    }
+
+    pub fn orientation(mut self, o: Orientation) -> Self {
+        self.orientation = o;
+        self
+    }
+
+    /// Backlight percentage once initialized, 0 leaves it off.
+    pub fn brightness(mut self, pct: u8) -> Self {
+        self.brightness = pct;
+        self
+    }
+
+    /// Reset, initialize and set the orientation and backlight, failing with the stage that
+    /// went wrong.
+    pub fn build<D: DelayUs<u32> + ?Sized>(
+        self,
+        delay: &mut D,
+    ) -> Result<TFTMC043<SPI, CS, Tftmc043Panel, RST>, InitError<PinErr, SPIErr>> {
+        let mut display = TFTMC043 {
+            controller: crate::ER5517::new(self.spi, self.cs, self.color_mode, self.config),
+            clip: None,
+            viewport: None,
+            surface: None,
+            reset: self.reset,
+            panel: PhantomData,
+        };
+        //init applies the orientation along with the scan directions
+        display.state.orientation = self.orientation;
+        display.init(delay)?;
+        display
+            .set_backlight_percent(self.brightness)
+            .map_err(InitError::at(InitStage::DisplayOn))?;
+        Ok(display)
+    }
+
+    /// [`TFTMC043Builder::build`] in 16 bit color, cleared to black.
+    pub fn build_rgb565<D: DelayUs<u32> + ?Sized>(
+        self,
+        delay: &mut D,
+    ) -> Result<TFTMC043Draw16Bit<SPI, CS, Tftmc043Panel, RST>, InitError<PinErr, SPIErr>> {
+        let display = self.color_mode(ColorMode::SixteenBit).build(delay)?;
+        let mut display =
+            TFTMC043Draw16Bit::new(display).map_err(InitError::at(InitStage::ColorMode))?;
+        display
+            .clear(Rgb565::BLACK)
+            .map_err(InitError::at(InitStage::DisplayOn))?;
+        Ok(display)
+    }
+
+    /// [`TFTMC043Builder::build`] in 24 bit color, cleared to black.
+    pub fn build_rgb888<D: DelayUs<u32> + ?Sized>(
+        self,
+        delay: &mut D,
+    ) -> Result<TFTMC043Draw24Bit<SPI, CS, Tftmc043Panel, RST>, InitError<PinErr, SPIErr>> {
+        let display = self.color_mode(ColorMode::TwentyFourBit).build(delay)?;
+        let mut display =
+            TFTMC043Draw24Bit::new(display).map_err(InitError::at(InitStage::ColorMode))?;
+        display
+            .clear(Rgb888::BLACK)
+            .map_err(InitError::at(InitStage::DisplayOn))?;
+        Ok(display)
+    }
+}
@@ -0,0 +1,102 @@
+//! What this build of the crate includes, for checking at compile time
+//!
+//! ```ignore
+//! const _: () = assert!(tftmc043::capabilities().buffered);
+//! ```
+
+/// Optional parts the enabled features compiled in, from [`capabilities`]
+///
+/// The BTE, every color depth and the hardware text and shape engines are always built.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Capabilities {
+    /// `TFTMC043::new_with_device` over an embedded-hal 1.0 `SpiDevice`, `eh1`
+    pub eh1: bool,
+    /// `asynch::TFTMC043Async`, `async`
+    pub async_driver: bool,
+    /// `BufferedDisplay`, `buffered`
+    pub buffered: bool,
+    /// transaction counters, `metrics`
+    pub metrics: bool,
+    /// `set_trace_sink`, `trace`
+    pub trace: bool,
+    /// `enable_debug_strip`, `debug-strip`
+    pub debug_strip: bool,
+    /// `testing::RecordingSpi`, `testing`
+    pub testing: bool,
+}
+
+pub const fn capabilities() -> Capabilities {
+    Capabilities {
+        eh1: cfg!(feature = "eh1"),
+        async_driver: cfg!(feature = "async"),
+        buffered: cfg!(feature = "buffered"),
+        metrics: cfg!(feature = "metrics"),
+        trace: cfg!(feature = "trace"),
+        debug_strip: cfg!(feature = "debug-strip"),
+        testing: cfg!(feature = "testing"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //checked when the tier's build compiles, like an application would
+    #[cfg(feature = "tier-standard")]
+    const _: () = assert!(capabilities().buffered);
+    #[cfg(feature = "tier-full")]
+    const _: () = assert!(capabilities().eh1 && capabilities().async_driver);
+
+    #[test]
+    fn tiers() {
+        //a tier's features, or each picked on its own
+        let full = cfg!(feature = "tier-full");
+        let standard = cfg!(feature = "tier-standard");
+        assert_eq!(
+            capabilities(),
+            Capabilities {
+                eh1: full || cfg!(feature = "eh1"),
+                async_driver: full || cfg!(feature = "async"),
+                buffered: standard || cfg!(feature = "buffered"),
+                metrics: cfg!(feature = "metrics"),
+                trace: cfg!(feature = "trace"),
+                debug_strip: cfg!(feature = "debug-strip"),
+                testing: cfg!(feature = "testing"),
+            }
+        );
+        let c = capabilities();
+        if full {
+            assert!(c.buffered && c.eh1 && c.async_driver);
+        } else if standard {
+            assert!(c.buffered);
+        }
+    }
+
+    #[test]
+    fn default_build() {
+        if cfg!(any(
+            feature = "tier-basic",
+            feature = "eh1",
+            feature = "async",
+            feature = "buffered",
+            feature = "metrics",
+            feature = "trace",
+            feature = "debug-strip",
+            feature = "testing"
+        )) {
+            return;
+        }
+        assert_eq!(
+            capabilities(),
+            Capabilities {
+                eh1: false,
+                async_driver: false,
+                buffered: false,
+                metrics: false,
+                trace: false,
+                debug_strip: false,
+                testing: false,
+            }
+        );
+    }
+}
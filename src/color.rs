@@ -0,0 +1,220 @@
+//! Color types missing from embedded-graphics-core, and the controller's memory formats
+
+use embedded_graphics_core::pixelcolor::{
+    raw::{RawData, RawU16, RawU8},
+    PixelColor, Rgb565, Rgb888, RgbColor,
+};
+
+/// 8-bit color with 3 bits red, 3 bits green and 2 bits blue, the controller's 8bpp format
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Hash)]
+pub struct Rgb332(u8);
+
+impl Rgb332 {
+    /// Components above the maximum for their channel are masked.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self(((r & 0b111) << 5) | ((g & 0b111) << 2) | (b & 0b11))
+    }
+
+    /// The memory byte, red in the top bits.
+    pub const fn into_byte(self) -> u8 {
+        self.0
+    }
+}
+
+impl PixelColor for Rgb332 {
+    type Raw = RawU8;
+}
+
+impl From<RawU8> for Rgb332 {
+    fn from(raw: RawU8) -> Self {
+        Self(raw.into_inner())
+    }
+}
+
+impl From<Rgb332> for RawU8 {
+    fn from(c: Rgb332) -> Self {
+        RawU8::new(c.0)
+    }
+}
+
+impl RgbColor for Rgb332 {
+    fn r(&self) -> u8 {
+        self.0 >> 5
+    }
+
+    fn g(&self) -> u8 {
+        (self.0 >> 2) & 0b111
+    }
+
+    fn b(&self) -> u8 {
+        self.0 & 0b11
+    }
+
+    const MAX_R: u8 = 7;
+    const MAX_G: u8 = 7;
+    const MAX_B: u8 = 3;
+
+    const BLACK: Self = Self::new(0, 0, 0);
+    const RED: Self = Self::new(7, 0, 0);
+    const GREEN: Self = Self::new(0, 7, 0);
+    const BLUE: Self = Self::new(0, 0, 3);
+    const YELLOW: Self = Self::new(7, 7, 0);
+    const MAGENTA: Self = Self::new(7, 0, 3);
+    const CYAN: Self = Self::new(0, 7, 3);
+    const WHITE: Self = Self::new(7, 7, 3);
+}
+
+//scale an 8-bit component to `max`, rounding to the nearest step
+const fn round(v: u8, max: u8) -> u8 {
+    //at most 255 * 255 + 127 before the division
+    ((v as u16).saturating_mul(max as u16).saturating_add(127) / 255) as u8
+}
+
+impl From<Rgb888> for Rgb332 {
+    fn from(c: Rgb888) -> Self {
+        Self::new(round(c.r(), 7), round(c.g(), 7), round(c.b(), 3))
+    }
+}
+
+/// Rgb888 to Rgb565 rounding to the nearest value, embedded-graphics' `From` truncates.
+pub fn rgb888_to_565(c: Rgb888) -> Rgb565 {
+    Rgb565::new(round(c.r(), 31), round(c.g(), 63), round(c.b(), 31))
+}
+
+/// The 8bpp memory byte.
+pub fn rgb332_to_byte(c: Rgb332) -> u8 {
+    c.into_byte()
+}
+
+/// The 16bpp memory bytes, low byte first.
+pub fn rgb565_to_bytes(c: Rgb565) -> [u8; 2] {
+    RawU16::from(c).into_inner().to_le_bytes()
+}
+
+/// The 24bpp memory bytes, blue first.
+pub fn rgb888_to_bytes(c: Rgb888) -> [u8; 3] {
+    [c.b(), c.g(), c.r()]
+}
+
+/// Scale to 8-bit components for the color registers, replicating the top bits so full scale
+/// maps to 0xFF.
+pub fn expand565_to_888(c: Rgb565) -> Rgb888 {
+    let (r, g, b) = (c.r(), c.g(), c.b());
+    Rgb888::new(
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    )
+}
+
+/// Like [`expand565_to_888`], the two blue bits are repeated four times.
+pub fn expand332_to_888(c: Rgb332) -> Rgb888 {
+    let (r, g, b) = (c.r(), c.g(), c.b());
+    Rgb888::new(
+        (r << 5) | (r << 2) | (r >> 1),
+        (g << 5) | (g << 2) | (g >> 1),
+        //two bits, at most 0xff
+        b.saturating_mul(0x55),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAY: Rgb888 = Rgb888::new(128, 128, 128);
+
+    #[test]
+    fn rgb565_bytes() {
+        for (c, bytes) in [
+            (Rgb565::BLACK, [0x00, 0x00]),
+            (Rgb565::WHITE, [0xff, 0xff]),
+            (Rgb565::RED, [0x00, 0xf8]),
+            (Rgb565::GREEN, [0xe0, 0x07]),
+            (Rgb565::BLUE, [0x1f, 0x00]),
+            (Rgb565::new(16, 32, 16), [0x10, 0x84]),
+            //all green bits set, the top three in the high byte and the rest in the low one
+            (Rgb565::new(0, 63, 0), [0xe0, 0x07]),
+            (Rgb565::new(0, 63, 31), [0xff, 0x07]),
+        ] {
+            assert_eq!(rgb565_to_bytes(c), bytes, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn rgb888_bytes() {
+        assert_eq!(rgb888_to_bytes(Rgb888::BLACK), [0, 0, 0]);
+        assert_eq!(rgb888_to_bytes(Rgb888::WHITE), [0xff, 0xff, 0xff]);
+        assert_eq!(rgb888_to_bytes(Rgb888::RED), [0, 0, 0xff]);
+        assert_eq!(rgb888_to_bytes(Rgb888::GREEN), [0, 0xff, 0]);
+        assert_eq!(rgb888_to_bytes(Rgb888::BLUE), [0xff, 0, 0]);
+        assert_eq!(rgb888_to_bytes(GRAY), [128, 128, 128]);
+    }
+
+    #[test]
+    fn rgb332_bytes() {
+        for (c, byte) in [
+            (Rgb888::BLACK, 0x00),
+            (Rgb888::WHITE, 0xff),
+            (Rgb888::RED, 0xe0),
+            (Rgb888::GREEN, 0x1c),
+            (Rgb888::BLUE, 0x03),
+            (GRAY, 0x92),
+        ] {
+            assert_eq!(rgb332_to_byte(c.into()), byte, "{:?}", c);
+        }
+        assert_eq!(Rgb332::new(8, 9, 4), Rgb332::new(0, 1, 0));
+    }
+
+    #[test]
+    fn rounding_to_565() {
+        assert_eq!(rgb888_to_565(Rgb888::WHITE), Rgb565::WHITE);
+        assert_eq!(rgb888_to_565(Rgb888::GREEN), Rgb565::new(0, 63, 0));
+        assert_eq!(rgb888_to_565(GRAY), Rgb565::new(16, 32, 16));
+        //truncating would give 0
+        assert_eq!(rgb888_to_565(Rgb888::new(6, 3, 6)), Rgb565::new(1, 1, 1));
+    }
+
+    #[test]
+    fn expansion() {
+        assert_eq!(expand565_to_888(Rgb565::BLACK), Rgb888::BLACK);
+        assert_eq!(expand565_to_888(Rgb565::WHITE), Rgb888::WHITE);
+        assert_eq!(expand565_to_888(Rgb565::RED), Rgb888::RED);
+        assert_eq!(expand565_to_888(Rgb565::new(0, 63, 0)), Rgb888::GREEN);
+        assert_eq!(expand565_to_888(Rgb565::BLUE), Rgb888::BLUE);
+        assert_eq!(
+            expand565_to_888(Rgb565::new(16, 32, 16)),
+            Rgb888::new(132, 130, 132)
+        );
+
+        assert_eq!(expand332_to_888(Rgb332::BLACK), Rgb888::BLACK);
+        assert_eq!(expand332_to_888(Rgb332::WHITE), Rgb888::WHITE);
+        assert_eq!(expand332_to_888(Rgb332::RED), Rgb888::RED);
+        assert_eq!(expand332_to_888(Rgb332::GREEN), Rgb888::GREEN);
+        assert_eq!(expand332_to_888(Rgb332::BLUE), Rgb888::BLUE);
+        assert_eq!(
+            expand332_to_888(Rgb332::new(4, 4, 2)),
+            Rgb888::new(146, 146, 0xaa)
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        for c in [
+            Rgb565::BLACK,
+            Rgb565::WHITE,
+            Rgb565::new(16, 32, 16),
+            Rgb565::new(1, 63, 30),
+        ] {
+            assert_eq!(rgb888_to_565(expand565_to_888(c)), c);
+        }
+        for c in [
+            Rgb332::BLACK,
+            Rgb332::WHITE,
+            Rgb332::new(4, 4, 2),
+            Rgb332::new(1, 6, 1),
+        ] {
+            assert_eq!(Rgb332::from(expand332_to_888(c)), c);
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! Register programming shared by the blocking and async drivers
+
+use crate::{regs, ColorMode, PanelConfig};
+
+//SPI cycle prefixes
+pub(crate) const CMD_WRITE: u8 = 0x00;
+pub(crate) const STATUS_READ: u8 = 0x40;
+pub(crate) const DATA_WRITE: u8 = 0x80;
+pub(crate) const DATA_READ: u8 = 0xc0;
+
+/// registers the driver read-modify-writes, with their reset values as listed in the datasheet,
+/// kept in a shadow copy for host interfaces that can't read
+pub(crate) const SHADOWED: [(u8, u8); 34] = [
+    (regs::CCR, 0x48),
+    (regs::MACR, 0),
+    (regs::ICR, 0),
+    (regs::INTEN, 0),
+    (regs::MINTFR, 0),
+    (regs::MPWCTR, 0),
+    (regs::PIPCDEP, 0),
+    (regs::DPCR, 0),
+    (regs::PCSR, 0x03),
+    (regs::GTCCR, 0),
+    (regs::AW_COLOR, 0),
+    (regs::PMUXR, 0),
+    (regs::PCFGR, 0),
+    (regs::BTE_CTRL0, 0),
+    (regs::DMA_CTRL, 0),
+    (regs::SFL_CTRL, 0),
+    (regs::CCR0, 0),
+    (regs::CCR1, 0),
+    (regs::FLDR, 0),
+    (regs::F2FSSR, 0),
+    (regs::PMU, 0x03),
+    (regs::GPIOAD, 0xff),
+    (regs::GPIOA, 0),
+    (regs::GPIOB, 0),
+    (regs::GPIOCD, 0xff),
+    (regs::GPIOC, 0),
+    (regs::GPIODD, 0xff),
+    (regs::GPIOD, 0),
+    (regs::GPIOED, 0xff),
+    (regs::GPIOE, 0),
+    (regs::GPIOFD, 0xff),
+    (regs::GPIOF, 0),
+    (regs::KSCR1, 0),
+    (regs::KSCR2, 0),
+];
+
+pub(crate) fn shadow_index(reg: u8) -> Option<usize> {
+    SHADOWED.iter().position(|&(r, _)| r == reg)
+}
+
+pub(crate) fn shadow_defaults() -> [u8; SHADOWED.len()] {
+    let mut v = [0; SHADOWED.len()];
+    for (v, &(_, d)) in v.iter_mut().zip(SHADOWED.iter()) {
+        *v = d;
+    }
+    v
+}
+
+//status a host interface without reads reports, every wait passes after one poll pause
+pub(crate) const STATUS_IDLE: u8 = STATUS_WR_FIFO_EMPTY | STATUS_RD_FIFO_EMPTY | STATUS_SDRAM_READY;
+
+//status register bits
+pub(crate) const STATUS_WR_FIFO_FULL: u8 = 0x80;
+pub(crate) const STATUS_WR_FIFO_EMPTY: u8 = 0x40;
+pub(crate) const STATUS_RD_FIFO_FULL: u8 = 0x20;
+pub(crate) const STATUS_RD_FIFO_EMPTY: u8 = 0x10;
+pub(crate) const STATUS_CORE_BUSY: u8 = 0x08;
+pub(crate) const STATUS_SDRAM_READY: u8 = 0x04;
+pub(crate) const STATUS_INHIBIT: u8 = 0x02;
+pub(crate) const STATUS_INTERRUPT: u8 = 0x01;
+
+//init waits in microseconds. The vendor sequence waits 2 ms between the steps of the PLL check
+//loop, but each pass already starts by polling for host access, a short pause is enough.
+pub(crate) const CHECK_STEP_US: u32 = 100;
+pub(crate) const SETTLE_US: u32 = 100_000;
+pub(crate) const PLL_START_US: u32 = 1_000;
+//the PLL check loop without reads, one write to REG[01h] and this long for the lock
+pub(crate) const PLL_LOCK_US: u32 = 10_000;
+//init status polls when the poll config doesn't limit them
+pub(crate) const INIT_MAX_POLLS: u32 = 100_000;
+//polled and written bytes between progress callbacks, and the longest init delay without one
+pub(crate) const PROGRESS_WORK: u32 = 1024;
+pub(crate) const PROGRESS_WAIT_US: u32 = 10_000;
+
+/// (register, clear mask, set mask) read-modify-write steps run after the PLL and SDRAM are up
+pub(crate) const INIT_MODIFY: [(u8, u8, u8); 13] = [
+    (regs::CCR, 0b0000_1000, 0b0001_0000),  //TFT output 16bit
+    (regs::CCR, 0, 0b0000_0001),            //host bus 16bit
+    (regs::MACR, 0b1000_0000, 0b0100_0000), //host data 16bit 16bpp
+    (regs::MACR, 0b0000_0110, 0),           //memory write left-right top-down
+    (regs::ICR, 0b0000_0100, 0),            //graphic mode
+    (regs::ICR, 0b0000_0011, 0),            //memory port to SDRAM
+    (regs::DPCR, 0b0001_0000, 0),           //hscan left to right
+    (regs::DPCR, 0b0000_1000, 0),           //vscan top to bottom
+    (regs::DPCR, 0b0000_0111, 0),           //RGB output
+    (regs::DPCR, 0, 0b1000_0000),           //PCLK falling edge
+    (regs::PCSR, 0b1000_0000, 0),           //HSYNC low active
+    (regs::PCSR, 0b0100_0000, 0),           //VSYNC low active
+    (regs::PCSR, 0b0010_0000, 0),           //DE high active
+];
+
+/// pwm0 pwm1 100%
+pub(crate) const PWM_INIT: [(u8, u8); 6] = [
+    (regs::PMUXR, 0x0a),
+    (regs::TCMPB0L, 0x64),
+    (regs::TCNTB0L, 0x64),
+    (regs::TCMPB1L, 0x64),
+    (regs::TCNTB1L, 0x64),
+    (regs::PCFGR, 0x33),
+];
+
+const SDRAM_ITV: u16 = 476; //(64000000 / 8192) / (1000/60) - 12
+
+pub(crate) const SDRAM_INIT: [(u8, u8); 5] = [
+    (regs::SDRAR, 0x29),
+    (regs::SDRMD, 0x03), //CAS:2=0x02, CAS:3=0x03
+    (regs::SDR_REF_ITVL0, SDRAM_ITV as u8),
+    (regs::SDR_REF_ITVL1, (SDRAM_ITV >> 8) as u8),
+    (regs::SDRCR, 0x01),
+];
+
+pub(crate) fn pll_registers(config: &PanelConfig) -> [(u8, u8); 6] {
+    let lpll_od_sclk = 2u8;
+    let lpll_od_cclk = 2u8;
+    let lpll_od_mclk = 2u8;
+    let lpll_r_sclk = 5u8;
+    let lpll_r_cclk = 5u8;
+    let lpll_r_mclk = 5u8;
+    let lpll_n_sclk = config.sclk_n(); // TFT PCLK output frequency
+    let lpll_n_cclk = 100u8; // Core CLK:100
+    let lpll_n_mclk = 100u8; // SRAM CLK:100
+    [
+        (regs::PPLLC1, (lpll_od_sclk << 6) | (lpll_r_sclk << 1)),
+        (regs::MPLLC1, (lpll_od_mclk << 6) | (lpll_r_mclk << 1)),
+        (regs::SPLLC1, (lpll_od_cclk << 6) | (lpll_r_cclk << 1)),
+        (regs::PPLLC2, lpll_n_sclk),
+        (regs::MPLLC2, lpll_n_mclk),
+        (regs::SPLLC2, lpll_n_cclk),
+    ]
+}
+
+/// REG[14h]/[15h] and [16h]/[17h] encoding, `v = (coarse + 1) * 8 + fine`
+///
+/// None below 8 pixels, the coarse register can't express less, or past its 8 bits.
+pub(crate) fn horizontal_units(v: u16) -> Option<(u8, u8)> {
+    let coarse = (v / 8).checked_sub(1)?;
+    Some((u8::try_from(coarse).ok()?, (v % 8) as u8))
+}
+
+/// None if the horizontal width or back porch can't be encoded
+pub(crate) fn timing_registers(c: &PanelConfig) -> Option<[(u8, u8); 12]> {
+    let (w, h) = (c.width, c.height);
+    let (hdw, hdw_fine) = horizontal_units(w)?;
+    let (hnd, hnd_fine) = horizontal_units(c.hbpd)?;
+    let vnd = c.vbpd.checked_sub(1)?;
+    let last_row = h.checked_sub(1)?;
+    Some([
+        (regs::HDWR, hdw),
+        (regs::HDWFTR, hdw_fine),
+        (regs::VDHR0, last_row as _),
+        (regs::VDHR1, (last_row >> 8) as _),
+        (regs::HNDR, hnd),
+        (regs::HNDFTR, hnd_fine),
+        (regs::HSTR, (c.hfpd / 8).saturating_sub(1) as _),
+        (regs::HPWR, (c.hspw / 8).saturating_sub(1) as _),
+        (regs::VNDR0, vnd as _),
+        (regs::VNDR1, (vnd >> 8) as _),
+        (regs::VSTR, c.vfpd.saturating_sub(1) as _),
+        (regs::VPWR, c.vspw.saturating_sub(1) as _),
+    ])
+}
+
+/// little endian register pairs for the 4 16-bit values starting at `reg`
+pub(crate) fn quad_registers(reg: u8, v: [u16; 4]) -> [(u8, u8); 8] {
+    let mut r = [(0, 0); 8];
+    //register addresses wrap at 8 bits like the controller's, the callers stay below that
+    let mut a = reg;
+    for (pair, v) in r.chunks_exact_mut(2).zip(v) {
+        if let [lo, hi] = pair {
+            *lo = (a, v as u8);
+            *hi = (a.wrapping_add(1), (v >> 8) as u8);
+        }
+        a = a.wrapping_add(2);
+    }
+    r
+}
+
+//little endian 32 bit value across reg..=reg + 3
+pub(crate) fn u32_registers(reg: u8, v: u32) -> [(u8, u8); 4] {
+    let [b0, b1, b2, b3] = v.to_le_bytes();
+    [
+        (reg, b0),
+        (reg.wrapping_add(1), b1),
+        (reg.wrapping_add(2), b2),
+        (reg.wrapping_add(3), b3),
+    ]
+}
+
+/// REG[10h] bits 3-2
+pub(crate) fn main_window_color_bits(mode: ColorMode) -> u8 {
+    match mode {
+        ColorMode::EightBit => 0b0000,
+        ColorMode::SixteenBit => 0b0100,
+        ColorMode::TwentyFourBit => 0b1000,
+    }
+}
+
+/// REG[5Eh] bits 1-0
+pub(crate) fn memory_color_bits(mode: ColorMode) -> u8 {
+    match mode {
+        ColorMode::EightBit => 0b00,
+        ColorMode::SixteenBit => 0b01,
+        ColorMode::TwentyFourBit => 0b10,
+    }
+}
+
+/// Whether a written value reads back unchanged, false for ports, status and self clearing
+/// start bits
+pub(crate) fn verifiable(reg: u8) -> bool {
+    !matches!(
+        reg,
+        regs::SRR
+            | regs::CCR
+            | regs::MRWDP
+            | regs::INTF
+            | regs::DCR0
+            | regs::DCR1
+            | regs::BTE_CTRL0
+            | regs::DMA_CTRL
+            | regs::PMU
+            | regs::SDRCR
+            | regs::I2CMTXR
+            | regs::I2CMRXR
+            | regs::I2CMCMDR
+            | regs::I2CMSTUR
+            | regs::GPIOA
+            | regs::GPIOB
+            | regs::GPIOC
+            | regs::GPIOD
+            | regs::GPIOE
+            | regs::GPIOF
+            | regs::KSCR2
+            | regs::KSDR0..=0xff
+    )
+}
+
+//sine at 5 degree steps up to 90, in 1/32767ths
+const SIN_5DEG: [i32; 19] = [
+    0, 2856, 5690, 8481, 11207, 13848, 16383, 18794, 21062, 23170, 25101, 26841, 28377, 29697,
+    30791, 31650, 32269, 32642, 32767,
+];
+
+/// sine of `a` tenths of a degree in 1/32767ths, interpolated from [`SIN_5DEG`], off by at
+/// most 1/1000
+pub(crate) fn sin_deci_deg(a: u32) -> i32 {
+    //folded into 0..=900, the table steps keep the interpolation far from overflowing
+    let a = a % 3600;
+    let (negative, a) = match a.checked_sub(1800) {
+        Some(a) => (true, a),
+        None => (false, a),
+    };
+    let a = if a > 900 {
+        1800u32.saturating_sub(a)
+    } else {
+        a
+    };
+    let (i, f) = ((a / 50) as usize, (a % 50) as i32);
+    let v = match (SIN_5DEG.get(i), SIN_5DEG.get(i.saturating_add(1))) {
+        (Some(&v), Some(&next)) => v.wrapping_add(next.wrapping_sub(v).wrapping_mul(f) / 50),
+        (Some(&v), None) => v,
+        _ => 32767,
+    };
+    if negative {
+        v.wrapping_neg()
+    } else {
+        v
+    }
+}
+
+pub(crate) fn cos_deci_deg(a: u32) -> i32 {
+    sin_deci_deg((a % 3600).wrapping_add(900))
+}
+
+//`r` times a 1/32767ths factor, rounded half away from zero
+pub(crate) fn scale_q15(r: u16, v: i32) -> i32 {
+    //at most 2 * 65535 * 32767 before the division, nowhere near wrapping in i64
+    let (r, v) = (i64::from(r), i64::from(v.clamp(-32767, 32767)));
+    let n = r.wrapping_mul(v).wrapping_mul(2);
+    (n.wrapping_add(v.signum().wrapping_mul(32767)) / 65534) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::support::{self, NoDelay};
+    use crate::ColorMode;
+
+    #[test]
+    fn horizontal_units_widths() {
+        assert_eq!(horizontal_units(480), Some((59, 0)));
+        assert_eq!(horizontal_units(800), Some((99, 0)));
+        assert_eq!(horizontal_units(466), Some((57, 2)));
+        assert_eq!(horizontal_units(8), Some((0, 0)));
+        assert_eq!(horizontal_units(7), None);
+        assert_eq!(horizontal_units(0), None);
+        assert_eq!(horizontal_units(2048), Some((255, 0)));
+        assert_eq!(horizontal_units(2056), None);
+    }
+
+    #[test]
+    fn init_writes_horizontal_timing() {
+        for (width, hdw, fine) in [(480, 59, 0), (800, 99, 0), (466, 57, 2), (8, 0, 0)] {
+            let config = PanelConfig {
+                width,
+                ..PanelConfig::ER_TFTMC043
+            };
+            let (mut display, rec) = support::display_with_config(ColorMode::SixteenBit, config);
+            display.init(&mut NoDelay).unwrap();
+            let w = rec.register_writes();
+            let last = |reg| w.iter().rev().find(|(r, _)| *r == reg).map(|(_, v)| *v);
+            assert_eq!(last(regs::HDWR), Some(hdw), "width {width}");
+            assert_eq!(last(regs::HDWFTR), Some(fine), "width {width}");
+            //the 140 pixel back porch, (16 + 1) * 8 + 4
+            assert_eq!(last(regs::HNDR), Some(16));
+            assert_eq!(last(regs::HNDFTR), Some(4));
+        }
+    }
+
+    #[test]
+    fn trig_at_quadrant_boundaries() {
+        for (a, sin, cos) in [
+            (0, 0, 32767),
+            (900, 32767, 0),
+            (1800, 0, -32767),
+            (2700, -32767, 0),
+            (3600, 0, 32767),
+            (4500, 32767, 0),
+        ] {
+            assert_eq!((sin_deci_deg(a), cos_deci_deg(a)), (sin, cos), "{a}");
+        }
+        assert_eq!(scale_q15(50, 32767), 50);
+        assert_eq!(scale_q15(50, -32767), -50);
+        assert_eq!(scale_q15(50, sin_deci_deg(450)), 35);
+    }
+}
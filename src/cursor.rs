@@ -0,0 +1,92 @@
+//! Hardware graphic and text cursors
+
+use crate::{regs, Error, Interface, Res, ER5517};
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    //REG[3Ch] bits 3-2 select one of the four cursor images
+    fn graphic_cursor_select(&mut self, which: u8) -> Res<(), PinErr, SPIErr> {
+        if which > 3 {
+            return Err(Error::InvalidParam);
+        }
+        self.modify(regs::GTCCR, 0b0000_1100, which << 2)
+    }
+
+    /// Upload 32x32 2bpp cursor image `which`, 0..=3, and select it.
+    pub fn set_graphic_cursor_image(
+        &mut self,
+        which: u8,
+        data: &[u8; 256],
+    ) -> Res<(), PinErr, SPIErr> {
+        self.graphic_cursor_select(which)?;
+        if self.is_text_mode() {
+            self.graphic_mode()?;
+        }
+        //point the memory port at the graphic cursor RAM
+        self.modify(regs::ICR, 0b0000_0011, 0b0000_0010)?;
+        let r = self.graphic_cursor_write(data);
+        //always restore the memory port to SDRAM so normal drawing keeps working
+        self.modify(regs::ICR, 0b0000_0011, 0)?;
+        r
+    }
+
+    fn graphic_cursor_write(&mut self, data: &[u8; 256]) -> Res<(), PinErr, SPIErr> {
+        self.cmd_write(regs::MRWDP)?;
+        for &b in data {
+            self.wait_wr_fifo_not_full()?;
+            self.data_write(b)?;
+        }
+        Ok(())
+    }
+
+    /// Colors for the cursor's 00 and 01 pixel values, 8bpp RGB332.
+    pub fn set_graphic_cursor_color(&mut self, c0: u8, c1: u8) -> Res<(), PinErr, SPIErr> {
+        self.register_write(regs::GCC0, c0)?;
+        self.register_write(regs::GCC1, c1)
+    }
+
+    pub fn graphic_cursor_position(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u16(regs::GCHP0, x)?;
+        self.register_write_u16(regs::GCVP0, y)
+    }
+
+    pub fn graphic_cursor_enable(&mut self, on: bool) -> Res<(), PinErr, SPIErr> {
+        self.modify_bits(regs::GTCCR, 0b0001_0000, on)
+    }
+
+    /// Show the text cursor at the text write position, it follows [`write_text`](Self::write_text).
+    ///
+    /// The text cursor only exists in text mode, see [`text_mode`](Self::text_mode).
+    pub fn text_cursor_enable(&mut self, on: bool, blink: bool) -> Res<(), PinErr, SPIErr> {
+        if on && !self.is_text_mode() {
+            return Err(Error::TextModeRequired);
+        }
+        let mut v = 0;
+        if on {
+            v |= 0b0000_0010;
+        }
+        if blink {
+            v |= 0b0000_0001;
+        }
+        self.modify(regs::GTCCR, 0b0000_0011, v)
+    }
+
+    /// Text cursor size in pixels, 1..=32 for each dimension.
+    pub fn set_text_cursor_size(&mut self, h: u8, v: u8) -> Res<(), PinErr, SPIErr> {
+        if !(1..=32).contains(&h) || !(1..=32).contains(&v) {
+            return Err(Error::InvalidParam);
+        }
+        self.register_write(regs::CURHS, h.saturating_sub(1))?;
+        self.register_write(regs::CURVS, v.saturating_sub(1))
+    }
+
+    /// Text cursor blink period in frames, at least 1.
+    pub fn set_blink_rate(&mut self, frames: u8) -> Res<(), PinErr, SPIErr> {
+        if frames == 0 {
+            return Err(Error::InvalidParam);
+        }
+        self.register_write(regs::BTCR, frames.saturating_sub(1))
+    }
+}
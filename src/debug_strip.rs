@@ -0,0 +1,160 @@
+//! On screen log for development, kept out of the way of the main drawing
+//!
+//! Only built with the `debug-strip` feature, leave it off for release builds.
+
+use crate::{Error, Interface, Orientation, Res, Terminal, TerminalOverflow, TFTMC043};
+use core::fmt;
+use embedded_graphics_core::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+
+/// A strip along one screen edge for text, from [`TFTMC043::enable_debug_strip`]
+///
+/// Holds no borrow, write to it with [`DebugStrip::writer`] between the main drawing.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct DebugStrip {
+    area: Rectangle,
+    cell: (u16, u16),
+    pos: (u16, u16),
+    fg: (u8, u8, u8),
+    bg: (u8, u8, u8),
+}
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Reserve `rect` for a [`DebugStrip`] of `cell_w` x `cell_h` text cells, matching the
+    /// selected font.
+    ///
+    /// `rect` has to span the screen along the top, bottom, left or right edge. The rest of the
+    /// screen becomes the wrappers' viewport, replacing any other, so drawing and `clear` leave
+    /// the strip alone. Needs the normal orientation, the text engine uses memory coordinates.
+    pub fn enable_debug_strip(
+        &mut self,
+        rect: Rectangle,
+        cell_w: u16,
+        cell_h: u16,
+    ) -> Res<DebugStrip, PinErr, SPIErr> {
+        if self.orientation() != Orientation::Normal {
+            return Err(Error::InvalidParam);
+        }
+        let screen = self.screen();
+        if rect.size == Size::zero() || rect.intersection(&screen) != rect {
+            return Err(Error::OutOfBounds);
+        }
+        let (Size { width, height }, s) = (screen.size, rect.size);
+        let rest = if s.width == width && rect.top_left.y == 0 {
+            Rectangle::new(
+                Point::new(0, s.height as i32),
+                Size::new(width, height - s.height),
+            )
+        } else if s.width == width && rect.top_left.y as u32 + s.height == height {
+            Rectangle::new(Point::zero(), Size::new(width, height - s.height))
+        } else if s.height == height && rect.top_left.x == 0 {
+            Rectangle::new(
+                Point::new(s.width as i32, 0),
+                Size::new(width - s.width, height),
+            )
+        } else if s.height == height && rect.top_left.x as u32 + s.width == width {
+            Rectangle::new(Point::zero(), Size::new(width - s.width, height))
+        } else {
+            return Err(Error::InvalidParam);
+        };
+        if cell_w == 0 || cell_h == 0 || cell_w as u32 > s.width || cell_h as u32 > s.height {
+            return Err(Error::InvalidParam);
+        }
+        //`rect` is in screen coordinates, not the old viewport's
+        self.viewport = None;
+        self.clip = None;
+        self.fill_area(&rect, Rgb888::BLACK)?;
+        self.set_viewport(rest)?;
+        Ok(DebugStrip {
+            area: rect,
+            cell: (cell_w, cell_h),
+            pos: (0, 0),
+            fg: (0xff, 0xff, 0xff),
+            bg: (0, 0, 0),
+        })
+    }
+
+    /// Give the strip's area back to the main drawing, its last contents stay on screen.
+    pub fn disable_debug_strip(&mut self, _strip: DebugStrip) -> Res<(), PinErr, SPIErr> {
+        self.clear_viewport()
+    }
+}
+
+impl DebugStrip {
+    /// Colors for the following writes.
+    pub fn set_colors(&mut self, fg: (u8, u8, u8), bg: (u8, u8, u8)) {
+        self.fg = fg;
+        self.bg = bg;
+    }
+
+    /// A `core::fmt::Write` into the strip on `display`, scrolling with the BTE once full.
+    ///
+    /// Each new line costs a BTE copy, a fill and the text write.
+    pub fn writer<'a, SPI, CS, P, RST>(
+        &'a mut self,
+        display: &'a mut TFTMC043<SPI, CS, P, RST>,
+    ) -> DebugWriter<'a, SPI, CS, P, RST>
+    where
+        SPI: Interface<CS>,
+    {
+        DebugWriter {
+            strip: self,
+            display,
+        }
+    }
+}
+
+/// `core::fmt::Write` for a [`DebugStrip`], from [`DebugStrip::writer`]
+pub struct DebugWriter<'a, SPI, CS, P, RST>
+where
+    SPI: Interface<CS>,
+{
+    strip: &'a mut DebugStrip,
+    display: &'a mut TFTMC043<SPI, CS, P, RST>,
+}
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> DebugWriter<'_, SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Write `s` like [`Terminal::write`].
+    pub fn write(&mut self, s: &str) -> Res<(), PinErr, SPIErr> {
+        let strip = &mut *self.strip;
+        let display = &mut *self.display;
+        //the strip is outside of the viewport, lift it while writing
+        let (viewport, clip) = (display.viewport.take(), display.clip.take());
+        let (cw, ch) = strip.cell;
+        let size = strip.area.size;
+        let r = display.end_window().and_then(|_| {
+            let mut term = Terminal::resume(
+                &mut *display,
+                (strip.area.top_left.x as u16, strip.area.top_left.y as u16),
+                strip.cell,
+                (size.width as u16 / cw, size.height as u16 / ch),
+                strip.pos,
+                TerminalOverflow::Scroll,
+                strip.fg,
+                strip.bg,
+            );
+            let r = term.write(s);
+            strip.pos = term.position();
+            r
+        });
+        display.viewport = viewport;
+        display.clip = clip;
+        let restored = display.end_window();
+        r?;
+        restored
+    }
+}
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> fmt::Write for DebugWriter<'_, SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s).map_err(|_| fmt::Error)
+    }
+}
@@ -0,0 +1,17 @@
+//! Delay adapters for the init and reset functions, which take microsecond delays
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+/// Microsecond delays from a millisecond only provider, rounding up to whole milliseconds
+pub struct DelayMsShim<'a>(pub &'a mut dyn DelayMs<u16>);
+
+impl DelayUs<u32> for DelayMsShim<'_> {
+    fn delay_us(&mut self, us: u32) {
+        let mut ms = us.div_ceil(1_000);
+        while ms > 0 {
+            let n = ms.min(u16::MAX as u32);
+            self.0.delay_ms(n as u16);
+            ms -= n;
+        }
+    }
+}
@@ -0,0 +1,2473 @@
+use crate::{
+    common, regs, Error, InitError, InitProgress, InitStage, Interface, InterruptMask, PanelConfig,
+    PipWindow, PowerMode, PwmClockDivider, Res,
+};
+use embedded_graphics_core::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+use embedded_hal::blocking::delay::DelayUs;
+
+/// SDRAM size as configured by init, 4 banks of 4096 rows of 512 16-bit columns
+pub const SDRAM_BYTES: u32 = 0x100_0000;
+
+const INT_VSYNC: u8 = InterruptMask::VSYNC.bits();
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ColorMode {
+    EightBit,
+    SixteenBit,
+    TwentyFourBit,
+}
+
+impl ColorMode {
+    pub(crate) fn bytes_per_pixel(self) -> u32 {
+        match self {
+            ColorMode::EightBit => 1,
+            ColorMode::SixteenBit => 2,
+            ColorMode::TwentyFourBit => 3,
+        }
+    }
+}
+
+/// Order memory writes advance in, REG[02h] bits 2-1
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum WriteDirection {
+    LeftRightTopBottom,
+    RightLeftTopBottom,
+    TopBottomLeftRight,
+    BottomTopLeftRight,
+}
+
+impl WriteDirection {
+    fn bits(self) -> u8 {
+        match self {
+            WriteDirection::LeftRightTopBottom => 0b0000_0000,
+            WriteDirection::RightLeftTopBottom => 0b0000_0010,
+            WriteDirection::TopBottomLeftRight => 0b0000_0100,
+            WriteDirection::BottomTopLeftRight => 0b0000_0110,
+        }
+    }
+}
+
+/// Internal character generator font sizes
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FontSize {
+    Font8x16,
+    Font12x24,
+    Font16x32,
+}
+
+/// Panel orientation, rotations are clockwise
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Orientation {
+    Normal,
+    Rot90,
+    Rot180,
+    Rot270,
+}
+
+impl Orientation {
+    /// 90 and 270 degree rotations swap x and y, the remaining flips are done by the scan direction
+    fn transposed(self) -> bool {
+        matches!(self, Orientation::Rot90 | Orientation::Rot270)
+    }
+}
+
+/// Quarter of an ellipse for [`ER5517::arc_quadrant`], as drawn on screen
+///
+/// The values are the REG[76h] bits 1-0 part select from the datasheet, not verified on a panel.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Quadrant {
+    BottomLeft = 0b00,
+    UpperLeft = 0b01,
+    UpperRight = 0b10,
+    BottomRight = 0b11,
+}
+
+impl Quadrant {
+    //swapping x and y mirrors the quarters across the diagonal
+    fn transposed(self) -> Self {
+        match self {
+            Quadrant::BottomLeft => Quadrant::UpperRight,
+            Quadrant::UpperRight => Quadrant::BottomLeft,
+            q => q,
+        }
+    }
+}
+
+/// Decoded status register
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct StatusFlags {
+    pub write_fifo_full: bool,
+    pub write_fifo_empty: bool,
+    pub read_fifo_full: bool,
+    pub read_fifo_empty: bool,
+    pub core_busy: bool,
+    pub sdram_ready: bool,
+    /// host access inhibited, during reset or power saving
+    pub inhibit: bool,
+    pub interrupt_pending: bool,
+}
+
+impl StatusFlags {
+    pub fn from_bits(s: u8) -> Self {
+        Self {
+            write_fifo_full: s & common::STATUS_WR_FIFO_FULL != 0,
+            write_fifo_empty: s & common::STATUS_WR_FIFO_EMPTY != 0,
+            read_fifo_full: s & common::STATUS_RD_FIFO_FULL != 0,
+            read_fifo_empty: s & common::STATUS_RD_FIFO_EMPTY != 0,
+            core_busy: s & common::STATUS_CORE_BUSY != 0,
+            sdram_ready: s & common::STATUS_SDRAM_READY != 0,
+            inhibit: s & common::STATUS_INHIBIT != 0,
+            interrupt_pending: s & common::STATUS_INTERRUPT != 0,
+        }
+    }
+}
+
+/// Busy wait behaviour
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PollConfig {
+    /// pause between status polls, only used once a delay is set with [`ER5517::set_poll_delay`]
+    pub poll_delay_us: u32,
+    /// polls before giving up with [`Error::Timeout`]
+    pub max_polls: u32,
+    /// bytes written to the memory port between write FIFO checks
+    pub fifo_check_interval: u16,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            poll_delay_us: 0,
+            max_polls: u32::MAX,
+            fifo_check_interval: 16,
+        }
+    }
+}
+
+/// Register read timing, for long or fast SPI lines that return stale data right after the
+/// command, see [`ER5517::set_spi_timing`]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct SpiTiming {
+    /// pause between the command and the data read, through the
+    /// [`ER5517::set_poll_delay`] function
+    pub post_cmd_delay_us: u8,
+    /// read twice and keep the second value
+    pub double_read: bool,
+    /// send the register command before every access, even while the register is still
+    /// selected from the previous one
+    pub always_select: bool,
+}
+
+/// SPI framing of the host interface the controller is strapped for, see
+/// [`ER5517::set_host_interface`]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct HostInterface {
+    /// prefix bytes of the four cycle types
+    pub cmd_write: u8,
+    pub data_write: u8,
+    pub status_read: u8,
+    pub data_read: u8,
+    /// one dummy byte between a read prefix and the response
+    pub read_dummy: bool,
+    /// whether the controller can be read at all
+    pub readable: bool,
+}
+
+impl HostInterface {
+    /// 4-wire SPI, the TFTMC043 module strapping
+    pub const SPI_4WIRE: Self = Self {
+        cmd_write: common::CMD_WRITE,
+        data_write: common::DATA_WRITE,
+        status_read: common::STATUS_READ,
+        data_read: common::DATA_READ,
+        read_dummy: false,
+        readable: true,
+    };
+
+    /// 3-wire SPI with the shared data line only driven by the host.
+    ///
+    /// Register reads return the driver's shadow copy of the read-modify-written registers,
+    /// other reads fail with [`Error::ReadUnavailable`]. Status polls always see an idle
+    /// controller after pausing for [`PollConfig::poll_delay_us`], which has to cover the
+    /// longest wait, and writes can't be verified.
+    pub const SPI_3WIRE_WRITE_ONLY: Self = Self {
+        readable: false,
+        ..Self::SPI_4WIRE
+    };
+}
+
+impl Default for HostInterface {
+    fn default() -> Self {
+        Self::SPI_4WIRE
+    }
+}
+
+/// Settings [`ER5517::reinit`] restores after the controller lost its registers
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct DriverState {
+    pub config: PanelConfig,
+    /// memory port depth, what drawing writes and page addresses use
+    pub color_mode: ColorMode,
+    /// depth the main window is shown in
+    pub main_color_mode: ColorMode,
+    /// PIP window depths, once set with [`ER5517::pip_color_mode`]
+    pub pip_color_modes: [Option<ColorMode>; 2],
+    pub orientation: Orientation,
+    /// main image address, window origin and image width
+    pub main_image: Option<(u32, u16, u16, u16)>,
+    /// canvas address and image width
+    pub canvas_image: Option<(u32, u16)>,
+    /// active window x, y, width and height, before orientation
+    pub active_window: Option<(u16, u16, u16, u16)>,
+    /// backlight percentage, kept while it is off
+    pub backlight: u8,
+    pub backlight_on: bool,
+}
+
+/// Register level driver for the ER-5517 controller, independent of the panel attached to it
+pub struct ER5517<SPI, CS> {
+    pub(crate) state: DriverState,
+    display_page: u8,
+    draw_page: u8,
+    text_mode: bool,
+    write_direction: WriteDirection,
+    poll: PollConfig,
+    poll_delay: Option<fn(u32)>,
+    spi_timing: SpiTiming,
+    host: HostInterface,
+    //register the last command selected, for the shadow copies
+    selected: Option<u8>,
+    shadow: [u8; common::SHADOWED.len()],
+    shadow_registers: bool,
+    progress: Option<fn()>,
+    sector_segments: u8,
+    //longest data burst in one chip select assertion, 0 for no limit
+    max_transfer_len: u16,
+    //polls and bytes since the last progress call
+    progress_work: u32,
+    //bytes written since the last write FIFO check
+    fifo_written: u16,
+    scroll_area: (u16, u16),
+    scroll: (u16, u16),
+    //address of a custom canvas from set_canvas, replayed by soft_reset
+    canvas_addr: Option<u32>,
+    //where the next memory port write lands, while MRWDP is still selected
+    pub(crate) mem_cursor: Option<(u16, u16)>,
+    //last fg color set with fg_color, fills write their own and leave this to be restored
+    pub(crate) fg: Option<(u8, u8, u8)>,
+    //what the color registers hold, None when unknown
+    fg_reg: Option<(u8, u8, u8)>,
+    bg_reg: Option<(u8, u8, u8)>,
+    //line start x, y and end x, y registers in memory coordinates, None when unknown
+    line_reg: [Option<u16>; 4],
+    verify_writes: bool,
+    write_retries: u8,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: crate::metrics::Metrics,
+    #[cfg(feature = "trace")]
+    pub(crate) trace: Option<&'static dyn crate::trace::TraceSink>,
+    pub(crate) pwm_prescaler: u16,
+    pub(crate) pwm1_div: PwmClockDivider,
+    pub(crate) power_mode: PowerMode,
+    //set while a fixed depth draw wrapper owns the driver
+    pub(crate) color_mode_locked: bool,
+    //panel state to restore when waking from suspend or sleep
+    pub(crate) resume_display: bool,
+    spi: SPI,
+    cs: CS, //chip select
+}
+
+impl<SPI, CS> ER5517<SPI, CS> {
+    pub fn config(&self) -> &PanelConfig {
+        &self.state.config
+    }
+
+    /// Give back the bus and chip select, the controller keeps running as it is.
+    pub fn release(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+
+    /// Limit the number of status polls in busy waits before giving up with [`Error::Timeout`].
+    ///
+    /// Defaults to `u32::MAX`, effectively unbounded.
+    pub fn set_busy_timeout(&mut self, iterations: u32) {
+        self.poll.max_polls = iterations;
+    }
+
+    pub fn busy_timeout(&self) -> u32 {
+        self.poll.max_polls
+    }
+
+    /// Read registers back after writing them, failing with [`Error::VerifyFailed`] on a
+    /// mismatch.
+    ///
+    /// Off by default. Covers register writes and read-modify-writes, memory port data and
+    /// registers that don't read back what was written are skipped.
+    pub fn set_verify_writes(&mut self, on: bool) {
+        self.verify_writes = on;
+    }
+
+    /// Times a write that failed verification is repeated before giving up.
+    pub fn set_write_retries(&mut self, n: u8) {
+        self.write_retries = n;
+    }
+
+    pub fn set_poll_config(&mut self, poll: PollConfig) {
+        self.poll = poll;
+    }
+
+    pub fn poll_config(&self) -> PollConfig {
+        self.poll
+    }
+
+    /// Sleep for the given number of microseconds between status polls, freeing a shared bus.
+    pub fn set_poll_delay(&mut self, delay: Option<fn(u32)>) {
+        self.poll_delay = delay;
+    }
+
+    /// How registers are read, used by every read and read-modify-write.
+    pub fn set_spi_timing(&mut self, timing: SpiTiming) {
+        self.spi_timing = timing;
+    }
+
+    pub fn spi_timing(&self) -> SpiTiming {
+        self.spi_timing
+    }
+
+    /// Frame transfers for `host` instead of the module's 4-wire SPI.
+    pub fn set_host_interface(&mut self, host: HostInterface) {
+        self.host = host;
+    }
+
+    pub fn host_interface(&self) -> HostInterface {
+        self.host
+    }
+
+    /// Read-modify-write from local copies of the registers instead of reading them back,
+    /// for buses where reads aren't reliable.
+    ///
+    /// Shadowed are CCR, MACR, ICR, INTEN, MINTFR, MPWCTR, PIPCDEP, DPCR, PCSR, GTCCR,
+    /// AW_COLOR, PMUXR, PCFGR, BTE_CTRL0, DMA_CTRL, SFL_CTRL, CCR0, CCR1, FLDR, F2FSSR, PMU,
+    /// KSCR1, KSCR2 and the GPIO direction and data registers. The copies start at the reset
+    /// values and follow every write, status bits and GPIO inputs are still read from the bus.
+    /// Enable it before init or call [`ER5517::resync_shadows`] afterwards.
+    pub fn enable_shadow_registers(&mut self) {
+        self.shadow_registers = true;
+    }
+
+    pub fn disable_shadow_registers(&mut self) {
+        self.shadow_registers = false;
+    }
+
+    pub fn shadow_registers_enabled(&self) -> bool {
+        self.shadow_registers
+    }
+
+    /// Split data bursts after `len` bytes, releasing a shared bus in between, 0 for no limit.
+    ///
+    /// Other transactions are a few bytes, the longest is `len` plus the prefix byte.
+    pub fn set_max_transfer_len(&mut self, len: u16) {
+        self.max_transfer_len = len;
+    }
+
+    /// Call `cb` regularly during long operations, to feed a watchdog.
+    ///
+    /// Called between init steps and at least every 10 ms of init delays, and about every
+    /// kilobyte of status polls and memory writes. `cb` must not use the driver.
+    pub fn set_progress_callback(&mut self, cb: Option<fn()>) {
+        self.progress = cb;
+        self.progress_work = 0;
+    }
+
+    //count `work` polled or written bytes towards the next progress call
+    pub(crate) fn progress_tick(&mut self, work: u32) {
+        if let Some(cb) = self.progress {
+            self.progress_work = self.progress_work.saturating_add(work);
+            if self.progress_work >= common::PROGRESS_WORK {
+                self.progress_work = 0;
+                cb();
+            }
+        }
+    }
+
+    pub(crate) fn progress_now(&mut self) {
+        if let Some(cb) = self.progress {
+            self.progress_work = 0;
+            cb();
+        }
+    }
+
+    //called after every unsuccessful poll
+    pub(crate) fn poll_pause(&mut self) {
+        //a status read is a prefix and a data byte
+        self.progress_tick(2);
+        if let Some(delay) = self.poll_delay {
+            if self.poll.poll_delay_us > 0 {
+                delay(self.poll.poll_delay_us);
+            }
+        }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.state.orientation
+    }
+
+    pub fn bytes_per_pixel(&self) -> u8 {
+        self.state.color_mode.bytes_per_pixel() as u8
+    }
+
+    /// SDRAM address of `x`, `y` in the image at `canvas_addr`, `canvas_w` pixels wide, in the
+    /// current color mode.
+    ///
+    /// None if any byte of the pixel is past the end of the SDRAM.
+    pub fn pixel_address(&self, canvas_addr: u32, canvas_w: u16, x: u16, y: u16) -> Option<u32> {
+        let bpp = self.state.color_mode.bytes_per_pixel();
+        let offset = (y as u32)
+            .checked_mul(canvas_w as u32)?
+            .checked_add(x as u32)?
+            .checked_mul(bpp)?;
+        let addr = canvas_addr.checked_add(offset)?;
+        if addr.checked_add(bpp)? > SDRAM_BYTES {
+            return None;
+        }
+        Some(addr)
+    }
+
+    /// [`ER5517::pixel_address`] in the canvas last set up, None before init.
+    pub fn current_pixel_address(&self, x: u16, y: u16) -> Option<u32> {
+        let (addr, w) = self.state.canvas_image?;
+        self.pixel_address(addr, w, x, y)
+    }
+
+    /// Width and height in the current orientation
+    pub fn dimensions(&self) -> (u16, u16) {
+        if self.state.orientation.transposed() {
+            (self.state.config.height, self.state.config.width)
+        } else {
+            (self.state.config.width, self.state.config.height)
+        }
+    }
+
+    //map oriented coordinates to panel coordinates
+    fn transpose(&self, x: u16, y: u16) -> (u16, u16) {
+        if self.state.orientation.transposed() {
+            (y, x)
+        } else {
+            (x, y)
+        }
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    fn write(&mut self, bytes: &[u8]) -> Res<(), PinErr, SPIErr> {
+        #[cfg(feature = "metrics")]
+        self.count_transaction(bytes.len());
+        #[cfg(feature = "trace")]
+        if let Some((&prefix, rest)) = bytes.split_first() {
+            self.trace(crate::trace::Dir::Write, prefix, rest);
+        }
+        self.spi.write(&mut self.cs, bytes)
+    }
+
+    /// Read `buf.len()` bytes after `prefix` in one chip select assertion, the
+    /// [`HostInterface`] prefixes read the status register or the data port.
+    ///
+    /// Consecutive memory port reads still need the read FIFO checks of
+    /// [`ER5517::read_pixels`].
+    pub fn read_burst(&mut self, prefix: u8, buf: &mut [u8]) -> Res<(), PinErr, SPIErr> {
+        if !self.host.readable {
+            return Err(Error::ReadUnavailable);
+        }
+        if prefix == self.host.data_read {
+            self.mem_cursor = None;
+        }
+        let dummy = self.host.read_dummy as usize;
+        //dummy bytes are read into a stack buffer and dropped
+        let mut tmp = [0; 32];
+        let mut chunk = self.burst_len(buf.len());
+        if dummy > 0 {
+            chunk = chunk.min(tmp.len().saturating_sub(dummy));
+        }
+        for buf in buf.chunks_mut(chunk) {
+            #[cfg(feature = "metrics")]
+            self.count_transaction(buf.len().saturating_add(1).saturating_add(dummy));
+            if dummy == 0 {
+                self.spi.read_burst(&mut self.cs, prefix, buf)?;
+                #[cfg(feature = "trace")]
+                self.trace(crate::trace::Dir::Read, prefix, buf);
+            } else {
+                //`chunk` leaves room for the dummy bytes
+                let t = tmp
+                    .get_mut(..buf.len().saturating_add(dummy))
+                    .ok_or(Error::InvalidParam)?;
+                self.spi.read_burst(&mut self.cs, prefix, t)?;
+                #[cfg(feature = "trace")]
+                self.trace(crate::trace::Dir::Read, prefix, t);
+                for (b, v) in buf.iter_mut().zip(t.iter().skip(dummy)) {
+                    *b = *v;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //bytes per chip select assertion for a `len` byte burst
+    fn burst_len(&self, len: usize) -> usize {
+        let mut chunk = len.max(1);
+        //split so the progress callback runs during long bursts
+        if self.progress.is_some() {
+            chunk = chunk.min(common::PROGRESS_WORK as usize);
+        }
+        if self.max_transfer_len > 0 {
+            chunk = chunk.min(self.max_transfer_len as usize);
+        }
+        chunk
+    }
+
+    //any other access may move the memory write position or select another register, the
+    //draw targets set mem_cursor again after their own pixel writes
+    pub(crate) fn cmd_write(&mut self, cmd: u8) -> Res<(), PinErr, SPIErr> {
+        self.mem_cursor = None;
+        //unknown until the controller has it
+        self.selected = None;
+        self.write(&[self.host.cmd_write, cmd])?;
+        self.selected = Some(cmd);
+        Ok(())
+    }
+
+    //command write unless `reg` is still selected, true if the command was sent. The memory
+    //port is always selected again, its command also ends text and BTE writes
+    pub(crate) fn select(&mut self, reg: u8) -> Res<bool, PinErr, SPIErr> {
+        if self.still_selected(reg) {
+            self.mem_cursor = None;
+            return Ok(false);
+        }
+        self.cmd_write(reg)?;
+        Ok(true)
+    }
+
+    //whether `select` would skip the command for `reg`
+    pub(crate) fn still_selected(&self, reg: u8) -> bool {
+        self.selected == Some(reg) && reg != regs::MRWDP && !self.spi_timing.always_select
+    }
+
+    //whether data writes go to a register rather than the memory port
+    pub(crate) fn register_selected(&self) -> bool {
+        self.selected.is_some_and(|r| r != regs::MRWDP)
+    }
+
+    pub(crate) fn data_write(&mut self, data: u8) -> Res<(), PinErr, SPIErr> {
+        self.mem_cursor = None;
+        self.shadow_write(data);
+        self.write(&[self.host.data_write, data])
+    }
+
+    //`data` written to the selected register, kept if it is one of the shadowed ones
+    fn shadow_write(&mut self, data: u8) {
+        if let Some(s) = self
+            .selected
+            .filter(|&r| r != regs::MRWDP)
+            .and_then(common::shadow_index)
+            .and_then(|i| self.shadow.get_mut(i))
+        {
+            *s = data;
+        }
+    }
+
+    //consecutive data writes in a single chip select assertion
+    pub(crate) fn data_write_burst(&mut self, data: &[u8]) -> Res<(), PinErr, SPIErr> {
+        self.mem_cursor = None;
+        //a register keeps the last of the bytes
+        if let Some(&last) = data.last() {
+            self.shadow_write(last);
+        }
+        let chunk = self.burst_len(data.len());
+        for data in data.chunks(chunk) {
+            #[cfg(feature = "metrics")]
+            self.count_transaction(data.len().saturating_add(1));
+            #[cfg(feature = "trace")]
+            self.trace(crate::trace::Dir::Write, self.host.data_write, data);
+            self.spi
+                .write_burst(&mut self.cs, self.host.data_write, data)?;
+            self.progress_tick(data.len() as u32);
+        }
+        Ok(())
+    }
+
+    /// Write `data` to the memory port with one prefix byte and chip select assertion.
+    ///
+    /// Starts from an empty write FIFO, graphic mode writes drain faster than SPI can fill it.
+    /// Don't use it in text mode, each character has to finish before the next is written.
+    pub fn data_write_buf(&mut self, data: &[u8]) -> Res<(), PinErr, SPIErr> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.wait_wr_fifo_empty()?;
+        self.data_write_burst(data)?;
+        //make the next batched write check again
+        self.fifo_written = u16::MAX;
+        Ok(())
+    }
+
+    pub fn new(spi: SPI, cs: CS, color_mode: ColorMode, config: PanelConfig) -> Self {
+        Self {
+            state: DriverState {
+                config,
+                color_mode,
+                main_color_mode: color_mode,
+                pip_color_modes: [None; 2],
+                orientation: Orientation::Normal,
+                main_image: None,
+                canvas_image: None,
+                active_window: None,
+                backlight: 100,
+                backlight_on: false,
+            },
+            spi,
+            cs,
+            display_page: 0,
+            draw_page: 0,
+            text_mode: false,
+            write_direction: WriteDirection::LeftRightTopBottom,
+            poll: PollConfig::default(),
+            poll_delay: None,
+            spi_timing: SpiTiming::default(),
+            host: HostInterface::SPI_4WIRE,
+            selected: None,
+            shadow: common::shadow_defaults(),
+            shadow_registers: false,
+            progress: None,
+            sector_segments: 3,
+            max_transfer_len: 0,
+            progress_work: 0,
+            fifo_written: u16::MAX,
+            scroll_area: (config.width, config.height),
+            scroll: (0, 0),
+            canvas_addr: None,
+            mem_cursor: None,
+            fg: None,
+            fg_reg: None,
+            bg_reg: None,
+            line_reg: [None; 4],
+            verify_writes: false,
+            write_retries: 0,
+            #[cfg(feature = "metrics")]
+            metrics: Default::default(),
+            #[cfg(feature = "trace")]
+            trace: None,
+            pwm_prescaler: 20,
+            pwm1_div: PwmClockDivider::Div1,
+            power_mode: PowerMode::Normal,
+            color_mode_locked: false,
+            resume_display: true,
+        }
+    }
+
+    pub fn status_read(&mut self) -> Res<u8, PinErr, SPIErr> {
+        if !self.host.readable {
+            if let Some(delay) = self.poll_delay {
+                delay(self.poll.poll_delay_us);
+            }
+            return Ok(common::STATUS_IDLE);
+        }
+        let mut v = [0];
+        self.read_burst(self.host.status_read, &mut v)?;
+        Ok(v[0])
+    }
+
+    pub fn read_status_flags(&mut self) -> Res<StatusFlags, PinErr, SPIErr> {
+        Ok(StatusFlags::from_bits(self.status_read()?))
+    }
+
+    pub fn data_read(&mut self) -> Res<u8, PinErr, SPIErr> {
+        let mut v = [0];
+        self.read_burst(self.host.data_read, &mut v)?;
+        Ok(v[0])
+    }
+
+    /// Select `reg` and read it, a command and a data read transaction, no command while it is
+    /// still selected.
+    ///
+    /// Every chip select assertion carries a single access type, so the two can't share one.
+    /// With [`ER5517::enable_shadow_registers`] or a write-only [`HostInterface`], the
+    /// registers the driver keeps a copy of are answered from it without the read.
+    pub fn register_read(&mut self, reg: u8) -> Res<u8, PinErr, SPIErr> {
+        if !self.host.readable || self.shadow_registers {
+            if let Some(v) = common::shadow_index(reg).and_then(|i| self.shadow.get(i).copied()) {
+                self.select(reg)?;
+                return Ok(v);
+            }
+        }
+        self.register_read_live(reg)
+    }
+
+    //select `reg` and read it from the bus with the configured timing, for status bits the
+    //controller changes itself
+    pub(crate) fn register_read_live(&mut self, reg: u8) -> Res<u8, PinErr, SPIErr> {
+        if !self.host.readable {
+            return Err(Error::ReadUnavailable);
+        }
+        let sent = self.select(reg)?;
+        let SpiTiming {
+            post_cmd_delay_us,
+            double_read,
+            ..
+        } = self.spi_timing;
+        if sent && post_cmd_delay_us > 0 {
+            if let Some(delay) = self.poll_delay {
+                delay(post_cmd_delay_us as u32);
+            }
+        }
+        if double_read {
+            self.data_read()?;
+        }
+        self.data_read()
+    }
+
+    pub fn register_write(&mut self, cmd: u8, data: u8) -> Res<(), PinErr, SPIErr> {
+        self.select(cmd)?;
+        self.data_write(data)?;
+        self.verify_write(cmd, data)
+    }
+
+    /// Write each `(reg, value)` pair in order, verified like [`ER5517::register_write`].
+    ///
+    /// Every chip select assertion is one prefix byte's access type followed by its bytes, so
+    /// a command and its data can't share one. The pairs go through a [`Transaction`], values
+    /// for the register that is still selected join the pending data burst instead of taking a
+    /// transaction each. With verification on each pair is written and read back on its own.
+    ///
+    /// [`Transaction`]: crate::Transaction
+    pub fn register_write_all(&mut self, pairs: &[(u8, u8)]) -> Res<(), PinErr, SPIErr> {
+        let verify = self.verify_writes;
+        let mut t = self.begin();
+        for &(reg, v) in pairs {
+            if verify {
+                t.register_write(reg, v)?;
+            } else {
+                t.cmd(reg)?;
+                t.data(v)?;
+            }
+        }
+        t.commit()
+    }
+
+    //read `reg` back if verification is on, rewriting it up to write_retries times
+    fn verify_write(&mut self, reg: u8, v: u8) -> Res<(), PinErr, SPIErr> {
+        if !self.verify_writes || !self.host.readable || !common::verifiable(reg) {
+            return Ok(());
+        }
+        let mut retries = self.write_retries;
+        loop {
+            let read = self.register_read_live(reg)?;
+            if read == v {
+                return Ok(());
+            }
+            if retries == 0 {
+                return Err(Error::VerifyFailed {
+                    reg,
+                    wrote: v,
+                    read,
+                });
+            }
+            retries = retries.saturating_sub(1);
+            self.data_write(v)?;
+        }
+    }
+
+    //little endian 16-bit value across reg, reg + 1, addresses wrap at 8 bits like the
+    //controller's
+    pub(crate) fn register_write_u16(&mut self, reg: u8, v: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write(reg, v as u8)?;
+        self.register_write(reg.wrapping_add(1), (v >> 8) as u8)
+    }
+
+    //little endian 32-bit value across reg..=reg + 3
+    pub(crate) fn register_write_u32(&mut self, reg: u8, v: u32) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u16(reg, v as u16)?;
+        self.register_write_u16(reg.wrapping_add(2), (v >> 16) as u16)
+    }
+
+    //read-modify-write, clearing then setting bits
+    pub(crate) fn modify(&mut self, reg: u8, clear: u8, set: u8) -> Res<(), PinErr, SPIErr> {
+        self.modify_register(reg, |v| (v & !clear) | set)?;
+        Ok(())
+    }
+
+    //set or clear `mask` in `reg`
+    pub(crate) fn modify_bits(&mut self, reg: u8, mask: u8, on: bool) -> Res<(), PinErr, SPIErr> {
+        if on {
+            self.modify(reg, 0, mask)
+        } else {
+            self.modify(reg, mask, 0)
+        }
+    }
+
+    /// Read `reg`, write back `f` of the value and return what was written.
+    ///
+    /// Three transactions, or two while `reg` is still selected: the write can't follow the
+    /// read in the same chip select assertion.
+    pub fn modify_register(
+        &mut self,
+        reg: u8,
+        f: impl FnOnce(u8) -> u8,
+    ) -> Res<u8, PinErr, SPIErr> {
+        let v = f(self.register_read(reg)?);
+        self.data_write(v)?;
+        self.verify_write(reg, v)?;
+        Ok(v)
+    }
+
+    pub fn color_bars(&mut self, on: bool) -> Res<(), PinErr, SPIErr> {
+        self.modify_bits(regs::DPCR, 0b0010_0000, on)
+    }
+
+    pub fn on(&mut self, on: bool) -> Res<(), PinErr, SPIErr> {
+        self.modify_bits(regs::DPCR, 0b0100_0000, on)
+    }
+
+    /// Show the main window in `mode`, independent of the memory port depth.
+    pub fn select_main_window_color_mode(&mut self, mode: ColorMode) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::MPWCTR, 0b1100, common::main_window_color_bits(mode))?;
+        self.state.main_color_mode = mode;
+        Ok(())
+    }
+
+    /// Check that an ER-5517 is answering, by the status register pattern and a scratch register readback.
+    ///
+    /// A floating or shorted MISO line reads back constant bytes, which neither check accepts.
+    /// A write-only [`HostInterface`] can't check anything, the controller is assumed present.
+    pub fn chip_detect(&mut self) -> Res<bool, PinErr, SPIErr> {
+        if !self.host.readable {
+            return Ok(true);
+        }
+        let s = self.status_read()?;
+        //the write FIFO can't be full and empty at once
+        if s & 0b1100_0000 == 0b1100_0000 {
+            return Ok(false);
+        }
+        //REG[D2h], foreground red, is overwritten by any drawing anyway
+        for v in [0xa5, 0x5a] {
+            self.register_write(regs::FGCR, v)?;
+            if self.register_read(regs::FGCR)? != v {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Blocking init, drives [`ER5517::start_init`] to completion.
+    ///
+    /// `delay` can be a `dyn DelayUs<u32>`, or a [`DelayMsShim`](crate::DelayMsShim) around a
+    /// millisecond only `dyn DelayMs<u16>`.
+    pub fn init<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(), InitError<PinErr, SPIErr>> {
+        let mut seq = self.start_init();
+        let mut elapsed = 0;
+        loop {
+            let r = seq.poll(self, elapsed)?;
+            self.progress_now();
+            match r {
+                InitProgress::WaitUs(us) => {
+                    //the rest is asked for again on the next poll
+                    let us = match self.progress {
+                        Some(_) => us.min(common::PROGRESS_WAIT_US),
+                        None => us,
+                    };
+                    delay.delay_us(us);
+                    elapsed = us;
+                }
+                InitProgress::Pending => {
+                    self.poll_pause();
+                    elapsed = 0;
+                }
+                InitProgress::Done => return Ok(()),
+            }
+        }
+    }
+
+    //the rest of init once the clocks and SDRAM are up, nothing here waits
+    pub(crate) fn init_configure(&mut self, stage: &mut InitStage) -> Res<(), PinErr, SPIErr> {
+        *stage = InitStage::TimingConfig;
+        for (reg, clear, set) in common::INIT_MODIFY {
+            self.modify(reg, clear, set)?;
+        }
+        self.text_mode = false;
+        self.force_color_resync();
+        self.set_orientation(self.state.orientation)?;
+
+        let timing = common::timing_registers(&self.state.config).ok_or(Error::InvalidParam)?;
+        for (reg, v) in timing {
+            self.register_write(reg, v)?;
+        }
+
+        *stage = InitStage::ColorMode;
+        self.select_main_window_color_mode(self.state.main_color_mode)?;
+        self.memory_xy_mode()?;
+        self.write_memory_color_mode(self.state.color_mode)?;
+        self.select_main_window_color_mode(self.state.main_color_mode)?;
+        for (which, mode) in [PipWindow::Pip1, PipWindow::Pip2]
+            .into_iter()
+            .zip(self.state.pip_color_modes)
+        {
+            if let Some(mode) = mode {
+                self.pip_color_mode(which, mode)?;
+            }
+        }
+        *stage = InitStage::WindowSetup;
+        self.set_display_page(self.display_page)?;
+        self.set_draw_page(self.draw_page)?;
+        let (w, h) = self.dimensions();
+        self.active_window_raw(0, 0, w, h)?;
+
+        *stage = InitStage::DisplayOn;
+        self.on(true)
+    }
+
+    /// Reset the controller through REG[00h] and run `init` again, keeping the color mode,
+    /// orientation, pages and canvas.
+    ///
+    /// Returns REG[01h] read back after the reset, bit 7 is set once the PLL is running again.
+    /// None with a write-only [`HostInterface`], the reset and init are still done.
+    pub fn soft_reset<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        delay: &mut D,
+    ) -> Res<Option<u8>, PinErr, SPIErr> {
+        let canvas = self
+            .canvas_addr
+            .map(|addr| (addr, self.scroll_area, self.scroll));
+        self.register_write(regs::SRR, 0x01)?;
+        self.force_color_resync();
+        self.reset_shadows();
+        delay.delay_us(1_000);
+        self.power_mode = PowerMode::Normal;
+        self.init(delay)?;
+        if let Some((addr, (w, h), (x, y))) = canvas {
+            self.set_canvas(addr, w, h)?;
+            self.scroll_to(x, y)?;
+        }
+        if !self.host.readable {
+            return Ok(None);
+        }
+        self.register_read_live(regs::CCR).map(Some)
+    }
+
+    /// What [`ER5517::reinit`] would restore.
+    pub fn state(&self) -> &DriverState {
+        &self.state
+    }
+
+    /// Run `init` again after the controller lost power and restore the [`DriverState`].
+    ///
+    /// Text settings and interrupt enables are not restored.
+    pub fn reinit<D: DelayUs<u32> + ?Sized>(&mut self, delay: &mut D) -> Res<(), PinErr, SPIErr> {
+        let saved = self.state;
+        let canvas = self
+            .canvas_addr
+            .map(|addr| (addr, self.scroll_area, self.scroll));
+        //the powered up controller holds its reset values and nothing selected
+        self.force_color_resync();
+        self.reset_shadows();
+        self.power_mode = PowerMode::Normal;
+        self.init(delay)?;
+        if let Some((addr, (w, h), (x, y))) = canvas {
+            self.set_canvas(addr, w, h)?;
+            self.scroll_to(x, y)?;
+        }
+        if let Some((addr, x, y, w)) = saved.main_image {
+            self.main_image_raw(addr, x, y, w)?;
+        }
+        if let Some((addr, w)) = saved.canvas_image {
+            self.canvas_image(addr, w)?;
+        }
+        if let Some((x, y, w, h)) = saved.active_window {
+            self.active_window_raw(x, y, w, h)?;
+        }
+        self.state.backlight = saved.backlight;
+        self.backlight_on(saved.backlight_on)
+    }
+
+    /// Whether the status register reads like a powered controller.
+    ///
+    /// A dead or unpowered bus reads all ones or all zeros, neither is a valid status.
+    pub fn is_responsive(&mut self) -> Res<bool, PinErr, SPIErr> {
+        let s = self.status_read()?;
+        Ok(s != 0x00 && s != 0xff)
+    }
+
+    /// Draw and show in `mode`, the memory port and the main window. PIP window depths are left
+    /// as they are.
+    pub fn set_color_mode(&mut self, mode: ColorMode) -> Res<(), PinErr, SPIErr> {
+        self.memory_color_mode(mode)?;
+        if mode != self.state.main_color_mode {
+            self.select_main_window_color_mode(mode)?;
+        }
+        Ok(())
+    }
+
+    /// The depth drawing writes, without changing what the windows show.
+    ///
+    /// Page addresses follow it, fails with [`Error::ColorModeLocked`] in a fixed depth draw
+    /// target.
+    pub fn memory_color_mode(&mut self, mode: ColorMode) -> Res<(), PinErr, SPIErr> {
+        if mode != self.state.color_mode {
+            if self.color_mode_locked {
+                return Err(Error::ColorModeLocked);
+            }
+            self.write_memory_color_mode(mode)?;
+            self.state.color_mode = mode;
+            //page addresses depend on the bytes per pixel
+            self.set_display_page(self.display_page)?;
+            self.set_draw_page(self.draw_page)?;
+        }
+        Ok(())
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.state.color_mode
+    }
+
+    /// Set the foreground color, skipped if the registers already hold it.
+    //XXX expects 8-bit colors
+    pub fn fg_color(&mut self, r: u8, g: u8, b: u8) -> Res<(), PinErr, SPIErr> {
+        self.fg = Some((r, g, b));
+        self.write_fg((r, g, b))
+    }
+
+    /// The last color set with [`ER5517::fg_color`], without reading the registers.
+    pub fn cached_fg_color(&self) -> Option<(u8, u8, u8)> {
+        self.fg
+    }
+
+    /// Forget the cached color and line coordinate register contents, so the next use writes
+    /// them again.
+    ///
+    /// Needed after writing those registers directly with [`ER5517::register_write`].
+    pub fn force_color_resync(&mut self) {
+        self.fg_reg = None;
+        self.bg_reg = None;
+        self.line_reg = [None; 4];
+    }
+
+    //back to the reset values, after the controller was reset
+    pub(crate) fn reset_shadows(&mut self) {
+        self.shadow = common::shadow_defaults();
+        //the reset also drops the register selection
+        self.selected = None;
+    }
+
+    /// Read the shadowed registers back into their copies, once the bus is known to be quiet.
+    pub fn resync_shadows(&mut self) -> Res<(), PinErr, SPIErr> {
+        for (i, &(reg, _)) in common::SHADOWED.iter().enumerate() {
+            let v = self.register_read_live(reg)?;
+            if let Some(s) = self.shadow.get_mut(i) {
+                *s = v;
+            }
+        }
+        Ok(())
+    }
+
+    //write the fg registers without changing the color restore_fg goes back to
+    pub(crate) fn write_fg(&mut self, c: (u8, u8, u8)) -> Res<(), PinErr, SPIErr> {
+        if self.fg_reg != Some(c) {
+            self.fg_reg = None;
+            self.register_write(regs::FGCR, c.0)?;
+            self.register_write(regs::FGCG, c.1)?;
+            self.register_write(regs::FGCB, c.2)?;
+            self.fg_reg = Some(c);
+        }
+        Ok(())
+    }
+
+    //put back the fg_color color after fills, before anything drawing with it
+    pub(crate) fn restore_fg(&mut self) -> Res<(), PinErr, SPIErr> {
+        match self.fg {
+            Some(c) => self.write_fg(c),
+            None => Ok(()),
+        }
+    }
+
+    /// The foreground color registers, for restoring them after a temporary change.
+    pub fn read_fg_color(&mut self) -> Res<(u8, u8, u8), PinErr, SPIErr> {
+        let mut c = [0; 3];
+        for (reg, v) in [regs::FGCR, regs::FGCG, regs::FGCB]
+            .into_iter()
+            .zip(c.iter_mut())
+        {
+            *v = self.register_read(reg)?;
+        }
+        self.fg_reg = Some((c[0], c[1], c[2]));
+        Ok((c[0], c[1], c[2]))
+    }
+
+    /// Set the background color, skipped if the registers already hold it.
+    pub fn bg_color(&mut self, r: u8, g: u8, b: u8) -> Res<(), PinErr, SPIErr> {
+        if self.bg_reg != Some((r, g, b)) {
+            self.bg_reg = None;
+            self.register_write(regs::BGCR, r)?;
+            self.register_write(regs::BGCG, g)?;
+            self.register_write(regs::BGCB, b)?;
+            self.bg_reg = Some((r, g, b));
+        }
+        Ok(())
+    }
+
+    //run `f` with the bg registers holding `c`, then put back the color they held before
+    pub(crate) fn with_bg<T>(
+        &mut self,
+        c: (u8, u8, u8),
+        f: impl FnOnce(&mut Self) -> Res<T, PinErr, SPIErr>,
+    ) -> Res<T, PinErr, SPIErr> {
+        let prev = self.bg_reg;
+        self.bg_color(c.0, c.1, c.2)?;
+        let r = f(self);
+        let restored = match prev {
+            Some((r, g, b)) => self.bg_color(r, g, b),
+            None => Ok(()),
+        };
+        let v = r?;
+        restored?;
+        Ok(v)
+    }
+
+    pub fn set_orientation(&mut self, o: Orientation) -> Res<(), PinErr, SPIErr> {
+        let (hflip, vflip) = match o {
+            Orientation::Normal => (false, false),
+            Orientation::Rot90 => (true, false),
+            Orientation::Rot180 => (true, true),
+            Orientation::Rot270 => (false, true),
+        };
+        let mut v = 0;
+        if hflip {
+            v |= 0b0001_0000;
+        }
+        if vflip {
+            v |= 0b0000_1000;
+        }
+        self.modify(regs::DPCR, 0b0001_1000, v)?;
+
+        self.state.orientation = o;
+        //memory writes should advance along the oriented x axis
+        self.set_memwrite_direction(self.row_direction())
+    }
+
+    //memory order of a row, or a column, in the current orientation
+    pub(crate) fn row_direction(&self) -> WriteDirection {
+        if self.state.orientation.transposed() {
+            WriteDirection::TopBottomLeftRight
+        } else {
+            WriteDirection::LeftRightTopBottom
+        }
+    }
+
+    fn column_direction(&self) -> WriteDirection {
+        if self.state.orientation.transposed() {
+            WriteDirection::LeftRightTopBottom
+        } else {
+            WriteDirection::TopBottomLeftRight
+        }
+    }
+
+    /// The order in memory, drawing expects the one set by [`ER5517::set_orientation`], put it
+    /// back when done.
+    pub fn set_memwrite_direction(&mut self, dir: WriteDirection) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::MACR, 0b0000_0110, dir.bits())?;
+        self.write_direction = dir;
+        Ok(())
+    }
+
+    pub fn memwrite_direction(&self) -> WriteDirection {
+        self.write_direction
+    }
+
+    /// Write columns of `h` pixels from `x`, `y` down, as many as `colors` holds, then step right.
+    ///
+    /// `colors` is in the current color mode's memory format and a whole number of columns.
+    pub fn fill_column(
+        &mut self,
+        x: u16,
+        y: u16,
+        h: u16,
+        colors: &[u8],
+    ) -> Res<(), PinErr, SPIErr> {
+        let col_len = (h as usize).saturating_mul(self.state.color_mode.bytes_per_pixel() as usize);
+        if col_len == 0 || !colors.len().is_multiple_of(col_len) {
+            return Err(Error::InvalidParam);
+        }
+        let w = colors
+            .len()
+            .checked_div(col_len)
+            .and_then(|w| u16::try_from(w).ok())
+            .ok_or(Error::InvalidParam)?;
+        if w == 0 {
+            return Ok(());
+        }
+        self.check_awake()?;
+        if self.text_mode {
+            self.graphic_mode()?;
+        }
+        let prev = self.write_direction;
+        self.active_window_raw(x, y, w, h)?;
+        self.set_memwrite_direction(self.column_direction())?;
+        self.goto_pixel(x, y)?;
+        self.cmd_write(regs::MRWDP)?;
+        let r = self
+            .data_write_buf(colors)
+            .and_then(|_| self.wait_wr_fifo_empty());
+        //restore even if the write failed, later drawing relies on it
+        self.set_memwrite_direction(prev)?;
+        let (dw, dh) = self.dimensions();
+        self.active_window_raw(0, 0, dw, dh)?;
+        r
+    }
+
+    /// Stream a `w` x `h` block at `x`, `y` from `rows`, called with each row's index and
+    /// `w` pixels of `scratch` to fill in the current color mode's memory format.
+    ///
+    /// An error from `rows` stops the write with [`Error::RowSource`], the active window is
+    /// restored either way.
+    pub fn blit_rows<F>(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        scratch: &mut [u8],
+        mut rows: F,
+    ) -> Res<(), PinErr, SPIErr>
+    where
+        F: FnMut(u16, &mut [u8]) -> Result<(), ()>,
+    {
+        let row_len = (w as usize).saturating_mul(self.state.color_mode.bytes_per_pixel() as usize);
+        let scratch = scratch.get_mut(..row_len).ok_or(Error::InvalidParam)?;
+        let (dw, dh) = self.dimensions();
+        if (x as u32).saturating_add(w as u32) > dw as u32
+            || (y as u32).saturating_add(h as u32) > dh as u32
+        {
+            return Err(Error::OutOfBounds);
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+        self.check_awake()?;
+        if self.text_mode {
+            self.graphic_mode()?;
+        }
+        self.active_window_raw(x, y, w, h)?;
+        self.goto_pixel(x, y)?;
+        self.cmd_write(regs::MRWDP)?;
+        let r = (0..h)
+            .try_for_each(|i| {
+                rows(i, scratch).map_err(|_| Error::RowSource)?;
+                self.data_write_buf(scratch)
+            })
+            .and_then(|_| self.wait_wr_fifo_empty());
+        //restore even if the write failed, later drawing relies on it
+        self.active_window_raw(0, 0, dw, dh)?;
+        r
+    }
+
+    /// Limit drawing to `area`, which has to be on screen and not empty.
+    pub fn active_window(&mut self, area: Rectangle) -> Res<(), PinErr, SPIErr> {
+        let (w, h) = self.dimensions();
+        let screen = Rectangle::new(Point::zero(), Size::new(w as _, h as _));
+        if area.size == Size::zero() || screen.intersection(&area) != area {
+            return Err(Error::OutOfBounds);
+        }
+        let Point { x, y } = area.top_left;
+        self.active_window_raw(x as _, y as _, area.size.width as _, area.size.height as _)
+    }
+
+    /// [`ER5517::active_window`] without the checks.
+    pub fn active_window_raw(&mut self, x: u16, y: u16, w: u16, h: u16) -> Res<(), PinErr, SPIErr> {
+        self.state.active_window = Some((x, y, w, h));
+        let (x, y) = self.transpose(x, y);
+        let (w, h) = self.transpose(w, h);
+        self.register_write_all(&common::quad_registers(regs::AWUL_X0, [x, y, w, h]))
+    }
+
+    pub fn line_start(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
+        let (x, y) = self.transpose(x, y);
+        self.write_line_reg(0, x)?;
+        self.write_line_reg(1, y)
+    }
+
+    pub fn line_end(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
+        let (x, y) = self.transpose(x, y);
+        self.write_line_reg(2, x)?;
+        self.write_line_reg(3, y)
+    }
+
+    //`i` picks the start x, start y, end x or end y register
+    fn write_line_reg(&mut self, i: usize, v: u16) -> Res<(), PinErr, SPIErr> {
+        let addrs = [regs::DLHSR0, regs::DLVSR0, regs::DLHER0, regs::DLVER0];
+        let (reg, cached) = match (addrs.get(i), self.line_reg.get(i)) {
+            (Some(&reg), Some(&cached)) => (reg, cached),
+            _ => return Err(Error::InvalidParam),
+        };
+        if cached != Some(v) {
+            self.set_line_reg(i, None);
+            self.register_write_u16(reg, v)?;
+            self.set_line_reg(i, Some(v));
+        }
+        Ok(())
+    }
+
+    fn set_line_reg(&mut self, i: usize, v: Option<u16>) {
+        if let Some(c) = self.line_reg.get_mut(i) {
+            *c = v;
+        }
+    }
+
+    /// Draw a `len` pixel horizontal line from `x`, `y` to the right in the fg color.
+    pub fn hline(&mut self, x: u16, y: u16, len: u16) -> Res<(), PinErr, SPIErr> {
+        self.hlines([((x, y), len)])
+    }
+
+    /// Draw a `len` pixel vertical line from `x`, `y` down in the fg color.
+    pub fn vline(&mut self, x: u16, y: u16, len: u16) -> Res<(), PinErr, SPIErr> {
+        self.vlines([((x, y), len)])
+    }
+
+    /// Draw horizontal lines from (start, length) pairs, like [`ER5517::hline`].
+    ///
+    /// Only the coordinates that differ from the previous line are written.
+    pub fn hlines(
+        &mut self,
+        lines: impl IntoIterator<Item = ((u16, u16), u16)>,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.axis_lines(lines, |(x, y), len| {
+            (x.saturating_add(len.saturating_sub(1)), y)
+        })
+    }
+
+    /// Draw vertical lines from (start, length) pairs, like [`ER5517::vline`].
+    pub fn vlines(
+        &mut self,
+        lines: impl IntoIterator<Item = ((u16, u16), u16)>,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.axis_lines(lines, |(x, y), len| {
+            (x, y.saturating_add(len.saturating_sub(1)))
+        })
+    }
+
+    fn axis_lines(
+        &mut self,
+        lines: impl IntoIterator<Item = ((u16, u16), u16)>,
+        end: impl Fn((u16, u16), u16) -> (u16, u16),
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.restore_fg()?;
+        for (start, len) in lines {
+            if len == 0 {
+                continue;
+            }
+            let (x1, y1) = end(start, len);
+            self.line_start(start.0, start.1)?;
+            self.line_end(x1, y1)?;
+            //bit 7 starts a line, the coordinates can't change until it is done
+            self.register_write(regs::DCR0, 0x80)?;
+            self.busy_draw()?;
+        }
+        Ok(())
+    }
+
+    pub fn rect_fill(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.rect_fill_start()?;
+        self.busy_draw()
+    }
+
+    /// Start filling the rectangle from line start to line end without waiting, poll
+    /// [`ER5517::draw_busy`] before the next drawing operation.
+    pub fn rect_fill_start(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.restore_fg()?;
+        self.rect_fill_raw()
+    }
+
+    //fill with whatever the fg registers hold
+    pub(crate) fn rect_fill_raw(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.register_write(regs::DCR1, 0xE0)
+    }
+
+    /// Draw a rectangle from `x0`, `y0` to `x1`, `y1` with `rx` by `ry` rounded corners in the fg
+    /// color, filled or as an outline.
+    ///
+    /// Radii are clamped to half the rectangle's size.
+    #[allow(clippy::too_many_arguments)]
+    pub fn rounded_rect(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        rx: u16,
+        ry: u16,
+        fill: bool,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.restore_fg()?;
+        let (x0, x1) = (x0.min(x1), x0.max(x1));
+        let (y0, y1) = (y0.min(y1), y0.max(y1));
+        let rx = rx.min(x1.abs_diff(x0) / 2);
+        let ry = ry.min(y1.abs_diff(y0) / 2);
+        self.line_start(x0, y0)?;
+        self.line_end(x1, y1)?;
+        let (rx, ry) = self.transpose(rx, ry);
+        self.register_write_u16(regs::ELL_A0, rx)?;
+        self.register_write_u16(regs::ELL_B0, ry)?;
+        //bits 5-4 = 11 selects the circle square, bit 6 fills it
+        self.register_write(regs::DCR1, if fill { 0xF0 } else { 0xB0 })?;
+        self.busy_draw()
+    }
+
+    /// Draw the `quadrant` of the `rx` by `ry` ellipse around `cx`, `cy` in the fg color, as an
+    /// arc or filled to the center.
+    pub fn arc_quadrant(
+        &mut self,
+        cx: u16,
+        cy: u16,
+        rx: u16,
+        ry: u16,
+        quadrant: Quadrant,
+        fill: bool,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.restore_fg()?;
+        let (cx, cy) = self.transpose(cx, cy);
+        let (rx, ry) = self.transpose(rx, ry);
+        let quadrant = if self.state.orientation.transposed() {
+            quadrant.transposed()
+        } else {
+            quadrant
+        };
+        self.register_write_all(&common::quad_registers(regs::ELL_A0, [rx, ry, cx, cy]))?;
+        //bits 5-4 = 01 selects the curve, bit 6 fills it
+        let fill = if fill { 0b0100_0000 } else { 0 };
+        self.register_write(regs::DCR1, 0x90 | fill | quadrant as u8)?;
+        self.busy_draw()
+    }
+
+    /// Fill the `quadrants` of the radius `r` circle around `cx`, `cy`, for gauge sectors.
+    pub fn gauge_sector(
+        &mut self,
+        cx: u16,
+        cy: u16,
+        r: u16,
+        quadrants: &[Quadrant],
+    ) -> Res<(), PinErr, SPIErr> {
+        for q in quadrants {
+            self.arc_quadrant(cx, cy, r, r, *q, true)?;
+        }
+        Ok(())
+    }
+
+    /// Draw the triangle between `p0`, `p1` and `p2` in the fg color, filled or as an outline.
+    pub fn triangle(
+        &mut self,
+        p0: (u16, u16),
+        p1: (u16, u16),
+        p2: (u16, u16),
+        fill: bool,
+    ) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.restore_fg()?;
+        self.line_start(p0.0, p0.1)?;
+        self.line_end(p1.0, p1.1)?;
+        let (x, y) = self.transpose(p2.0, p2.1);
+        self.register_write_u16(regs::DTPH0, x)?;
+        self.register_write_u16(regs::DTPV0, y)?;
+        //bit 1 selects the triangle, bit 5 fills it
+        self.register_write(regs::DCR0, if fill { 0xA2 } else { 0x82 })?;
+        self.busy_draw()
+    }
+
+    /// Triangles per partial quadrant in [`ER5517::sector`], clamped to 1..=16, 3 by default.
+    pub fn set_sector_segments(&mut self, n: u8) {
+        self.sector_segments = n.clamp(1, 16);
+    }
+
+    /// Draw the part of the radius `r` circle around `center` from `start` over `sweep`, both
+    /// in tenths of a degree, in the fg color, filled to the center or as an outline.
+    ///
+    /// Zero points along +x and positive angles turn counterclockwise on screen, `start` wraps
+    /// at 3600 and a `sweep` of 3600 or more is the whole circle. Whole quadrants are drawn by
+    /// the curve engine, the rest as [`ER5517::set_sector_segments`] triangles (or chords)
+    /// each, which fall inside the arc by at most `r * (1 - cos(span / 2))` for a span of up to
+    /// 90 degrees over the segment count, 3.4% of `r` for 3 segments. The circle has to be on the
+    /// coordinate range, `center` at least `r` from the top and left.
+    pub fn sector(
+        &mut self,
+        center: (u16, u16),
+        r: u16,
+        start_deci_deg: u16,
+        sweep_deci_deg: u16,
+        fill: bool,
+    ) -> Res<(), PinErr, SPIErr> {
+        let (cx, cy) = center;
+        if cx < r || cy < r || cx.checked_add(r).is_none() || cy.checked_add(r).is_none() {
+            return Err(Error::OutOfBounds);
+        }
+        let sweep = sweep_deci_deg.min(3600) as u32;
+        if sweep == 0 || r == 0 {
+            return Ok(());
+        }
+        let start = start_deci_deg as u32 % 3600;
+        //all angles stay below 2 * 3600
+        let end = start.saturating_add(sweep);
+        let mut a = start;
+        while a < end {
+            let q_end = (a / 900).saturating_add(1).saturating_mul(900);
+            let b = end.min(q_end);
+            let span = b.saturating_sub(a);
+            if span == 900 {
+                let quadrant = match (a / 900) % 4 {
+                    0 => Quadrant::UpperRight,
+                    1 => Quadrant::UpperLeft,
+                    2 => Quadrant::BottomLeft,
+                    _ => Quadrant::BottomRight,
+                };
+                self.arc_quadrant(cx, cy, r, r, quadrant, fill)?;
+            } else {
+                let n = self.sector_segments as u32;
+                let at =
+                    |i: u32| a.saturating_add(span.saturating_mul(i).checked_div(n).unwrap_or(0));
+                for i in 0..n {
+                    let p0 = Self::circle_point(center, r, at(i));
+                    let p1 = Self::circle_point(center, r, at(i.saturating_add(1)));
+                    if fill {
+                        self.triangle(center, p0, p1, true)?;
+                    } else {
+                        self.segment(p0, p1)?;
+                    }
+                }
+            }
+            a = b;
+        }
+        if !fill && sweep < 3600 {
+            self.segment(center, Self::circle_point(center, r, start))?;
+            self.segment(center, Self::circle_point(center, r, end))?;
+        }
+        Ok(())
+    }
+
+    //the point `a` tenths of a degree around the circle, sector checks it is in range
+    fn circle_point((cx, cy): (u16, u16), r: u16, a: u32) -> (u16, u16) {
+        let x = (cx as i32).saturating_add(common::scale_q15(r, common::cos_deci_deg(a)));
+        let y = (cy as i32).saturating_sub(common::scale_q15(r, common::sin_deci_deg(a)));
+        (x as u16, y as u16)
+    }
+
+    //line from `p0` to `p1` in the fg color
+    fn segment(&mut self, p0: (u16, u16), p1: (u16, u16)) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.restore_fg()?;
+        self.line_start(p0.0, p0.1)?;
+        self.line_end(p1.0, p1.1)?;
+        self.register_write(regs::DCR0, 0x80)?;
+        self.busy_draw()
+    }
+
+    /// Whether the drawing engine is still busy, non-blocking.
+    pub fn draw_busy(&mut self) -> Res<bool, PinErr, SPIErr> {
+        Ok(self.status_read()? & common::STATUS_CORE_BUSY != 0)
+    }
+
+    /// Show the image at `addr`, `image_w` pixels wide, from `origin` in memory coordinates.
+    ///
+    /// The panel sized window has to fit the image width and the address the SDRAM.
+    pub fn main_image(
+        &mut self,
+        addr: u32,
+        origin: Point,
+        image_w: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        let c = &self.state.config;
+        if origin.x < 0
+            || origin.y < 0
+            || origin.x.saturating_add(c.width as i32) > image_w as i32
+            || addr >= SDRAM_BYTES
+        {
+            return Err(Error::OutOfBounds);
+        }
+        self.main_image_raw(addr, origin.x as _, origin.y as _, image_w)
+    }
+
+    /// [`ER5517::main_image`] without the checks, `x`, `y` is the window origin and `w` the
+    /// image width.
+    pub fn main_image_raw(&mut self, addr: u32, x: u16, y: u16, w: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_all(&common::u32_registers(regs::MISA0, addr))?;
+        //MIW, MWULX and MWULY are consecutive
+        self.register_write_all(&common::quad_registers(regs::MIW0, [w, x, y, 0])[..6])?;
+        self.state.main_image = Some((addr, x, y, w));
+        self.scroll = (x, y);
+        Ok(())
+    }
+
+    pub fn canvas_image(&mut self, addr: u32, w: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_all(&common::u32_registers(regs::CVSSA0, addr))?;
+        self.register_write_all(&common::quad_registers(regs::CVS_IMWTH0, [w, 0, 0, 0])[..2])?;
+        self.state.canvas_image = Some((addr, w));
+        Ok(())
+    }
+
+    //address and image width drawing currently goes to
+    pub(crate) fn canvas_target(&self) -> (u32, u16) {
+        match self.canvas_addr {
+            Some(addr) => (addr, self.scroll_area.0),
+            None => (self.page_address(self.draw_page), self.state.config.width),
+        }
+    }
+
+    //rows of the image canvas_target points at
+    pub(crate) fn canvas_height(&self) -> u16 {
+        match self.canvas_addr {
+            Some(_) => self.scroll_area.1,
+            None => self.state.config.height,
+        }
+    }
+
+    fn page_address(&self, n: u8) -> u32 {
+        let c = &self.state.config;
+        (n as u32)
+            .saturating_mul(c.width as u32)
+            .saturating_mul(c.height as u32)
+            .saturating_mul(self.state.color_mode.bytes_per_pixel())
+    }
+
+    /// Show frame buffer `n` on the panel.
+    pub fn set_display_page(&mut self, n: u8) -> Res<(), PinErr, SPIErr> {
+        self.main_image_raw(self.page_address(n), 0, 0, self.state.config.width)?;
+        self.display_page = n;
+        self.scroll_area = (self.state.config.width, self.state.config.height);
+        self.scroll = (0, 0);
+        self.canvas_addr = None;
+        Ok(())
+    }
+
+    /// Direct drawing operations at frame buffer `n`.
+    pub fn set_draw_page(&mut self, n: u8) -> Res<(), PinErr, SPIErr> {
+        self.canvas_image(self.page_address(n), self.state.config.width)?;
+        self.draw_page = n;
+        Ok(())
+    }
+
+    pub fn display_page(&self) -> u8 {
+        self.display_page
+    }
+
+    pub fn draw_page(&self) -> u8 {
+        self.draw_page
+    }
+
+    /// Draw to and display a `w` x `h` image at `addr`, which can be larger than the panel for scrolling.
+    pub fn set_canvas(&mut self, addr: u32, w: u16, h: u16) -> Res<(), PinErr, SPIErr> {
+        if w < self.state.config.width || h < self.state.config.height {
+            return Err(Error::InvalidParam);
+        }
+        self.canvas_image(addr, w)?;
+        self.main_image_raw(addr, 0, 0, w)?;
+        self.scroll_area = (w, h);
+        self.scroll = (0, 0);
+        self.canvas_addr = Some(addr);
+        Ok(())
+    }
+
+    /// Show the canvas starting at `x`, `y`, the visible window must stay inside the canvas.
+    ///
+    /// The panel has no scroll offset separate from the main window origin, this moves the
+    /// origin set by [`ER5517::main_image`]. Page flips and `set_canvas` start again at 0, 0.
+    pub fn scroll_to(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
+        let (w, h) = self.scroll_area;
+        if (x as u32).saturating_add(self.state.config.width as u32) > w as u32
+            || (y as u32).saturating_add(self.state.config.height as u32) > h as u32
+        {
+            return Err(Error::InvalidParam);
+        }
+        self.register_write_u16(regs::MWULX0, x)?;
+        self.register_write_u16(regs::MWULY0, y)?;
+        self.scroll = (x, y);
+        //reinit replays the main image after the canvas, it has to show the same position
+        if let Some((_, mx, my, _)) = self.state.main_image.as_mut() {
+            (*mx, *my) = (x, y);
+        }
+        Ok(())
+    }
+
+    /// Scroll relative to the current position, wrapping around at the canvas edges.
+    ///
+    /// The window can't show the canvas end and start at once, a seamless ticker repeats the
+    /// first screen at the end of the canvas.
+    pub fn scroll_by(&mut self, dx: i16, dy: i16) -> Res<(), PinErr, SPIErr> {
+        let wrap = |pos: u16, d: i16, range: i32| {
+            (pos as i32).saturating_add(d as i32).rem_euclid(range) as u16
+        };
+        //number of valid start positions along each axis, the scroll area covers the panel
+        let xr =
+            (self.scroll_area.0.saturating_sub(self.state.config.width) as i32).saturating_add(1);
+        let yr =
+            (self.scroll_area.1.saturating_sub(self.state.config.height) as i32).saturating_add(1);
+        let x = wrap(self.scroll.0, dx, xr);
+        let y = wrap(self.scroll.1, dy, yr);
+        self.scroll_to(x, y)
+    }
+
+    pub fn scroll_position(&self) -> (u16, u16) {
+        self.scroll
+    }
+
+    /// Exchange the display and draw pages, flipping at the start of the next vertical blank.
+    pub fn swap_pages(&mut self) -> Res<(), PinErr, SPIErr> {
+        let (display, draw) = (self.display_page, self.draw_page);
+        self.wait_vsync()?;
+        self.set_display_page(draw)?;
+        self.set_draw_page(display)
+    }
+
+    /// Block until the next vertical blanking interval starts.
+    pub fn wait_vsync(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.interrupt_clear(INT_VSYNC)?;
+        for _ in 0..self.poll.max_polls {
+            if self.interrupt_flags()? & INT_VSYNC != 0 {
+                return Ok(());
+            }
+            self.poll_pause();
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Non-blocking check for a vertical blank since the last call, clears the flag if set.
+    pub fn vsync_occurred(&mut self) -> Res<bool, PinErr, SPIErr> {
+        let set = self.interrupt_flags()? & INT_VSYNC != 0;
+        if set {
+            self.interrupt_clear(INT_VSYNC)?;
+        }
+        Ok(set)
+    }
+
+    //REG[0Bh], enable/disable the given interrupts driving the XnINTR pin
+    pub(crate) fn interrupt_enable(&mut self, mask: u8, on: bool) -> Res<(), PinErr, SPIErr> {
+        self.modify_bits(regs::INTEN, mask, on)
+    }
+
+    //REG[0Ch], event flags are set regardless of the enable register
+    pub(crate) fn interrupt_flags(&mut self) -> Res<u8, PinErr, SPIErr> {
+        self.register_read(regs::INTF)
+    }
+
+    //REG[0Ch], flags are cleared by writing 1
+    pub(crate) fn interrupt_clear(&mut self, mask: u8) -> Res<(), PinErr, SPIErr> {
+        self.register_write(regs::INTF, mask)
+    }
+
+    //REG[0Dh], masked flags don't assert the XnINTR pin
+    #[allow(dead_code)]
+    fn interrupt_mask(&mut self, mask: u8, masked: bool) -> Res<(), PinErr, SPIErr> {
+        self.modify_bits(regs::MINTFR, mask, masked)
+    }
+
+    pub fn set_text_cursor(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u16(regs::F_CURX0, x)?;
+        self.register_write_u16(regs::F_CURY0, y)
+    }
+
+    /// Select an internal CGROM font.
+    pub fn set_font_size(&mut self, size: FontSize) -> Res<(), PinErr, SPIErr> {
+        //bits 7-6 = 00 selects the internal CGROM
+        let v = match size {
+            FontSize::Font8x16 => 0b0000_0000,
+            FontSize::Font12x24 => 0b0001_0000,
+            FontSize::Font16x32 => 0b0010_0000,
+        };
+        self.modify(regs::CCR0, 0b1111_0000, v)
+    }
+
+    //XXX expects 8-bit colors
+    pub fn set_font_colors(
+        &mut self,
+        fg: (u8, u8, u8),
+        bg: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        self.fg_color(fg.0, fg.1, fg.2)?;
+        self.bg_color(bg.0, bg.1, bg.2)
+    }
+
+    /// Write `s` at the text cursor with the internal font, switching to text mode if needed.
+    ///
+    /// Characters outside of printable ASCII are written as `?`.
+    pub fn write_text(&mut self, s: &str) -> Res<(), PinErr, SPIErr> {
+        if !self.text_mode {
+            self.text_mode()?;
+        }
+        self.restore_fg()?;
+        self.cmd_write(regs::MRWDP)?;
+        for c in s.chars() {
+            let b = if c.is_ascii() && !c.is_ascii_control() {
+                c as u8
+            } else {
+                b'?'
+            };
+            self.wait_wr_fifo_not_full()?;
+            self.data_write(b)?;
+            self.busy_draw()?;
+        }
+        Ok(())
+    }
+
+    /// Read back the `w` x `h` rectangle at `x`, `y` from the canvas into `buf`.
+    ///
+    /// The data is in the current color mode's memory format, `bytes_per_pixel` bytes per pixel.
+    pub fn read_pixels(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        buf: &mut [u8],
+    ) -> Res<(), PinErr, SPIErr> {
+        let len = (w as usize)
+            .saturating_mul(h as usize)
+            .saturating_mul(self.state.color_mode.bytes_per_pixel() as usize);
+        if buf.len() < len {
+            return Err(Error::InvalidParam);
+        }
+        self.check_awake()?;
+        if self.text_mode {
+            self.graphic_mode()?;
+        }
+        self.active_window_raw(x, y, w, h)?;
+        //the graphic read/write position is shared by the memory read and write paths
+        self.goto_pixel(x, y)?;
+        self.cmd_write(regs::MRWDP)?;
+        //the first read after the command only primes the read FIFO
+        self.data_read()?;
+        for b in buf.iter_mut().take(len) {
+            self.wait_rd_fifo_not_empty()?;
+            *b = self.data_read()?;
+        }
+        let (w, h) = self.dimensions();
+        self.active_window_raw(0, 0, w, h)
+    }
+
+    pub fn busy_draw(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.wait_status(|s| s & common::STATUS_CORE_BUSY == 0)
+    }
+
+    #[deprecated(note = "use wait_wr_fifo_not_full")]
+    pub fn check_mem_wr_fifo_ready(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.wait_wr_fifo_not_full()
+    }
+
+    /// Wait for room in the write FIFO, before pushing more data.
+    pub fn wait_wr_fifo_not_full(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.wait_status(|s| s & common::STATUS_WR_FIFO_FULL == 0)
+    }
+
+    /// Wait for all written data to reach memory, before changing the window or write position.
+    pub fn wait_wr_fifo_empty(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.wait_status(|s| s & common::STATUS_WR_FIFO_EMPTY != 0)
+    }
+
+    /// Wait for read data, before each data read of a memory read back.
+    pub fn wait_rd_fifo_not_empty(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        self.wait_status(|s| s & common::STATUS_RD_FIFO_EMPTY == 0)
+    }
+
+    //account for `n` bytes about to be written to the memory port, waiting for the write FIFO
+    //to drain once fifo_check_interval bytes went out without a check
+    pub(crate) fn fifo_reserve(&mut self, n: u16) -> Res<(), PinErr, SPIErr> {
+        self.check_awake()?;
+        if self.fifo_written.saturating_add(n) > self.poll.fifo_check_interval {
+            self.wait_wr_fifo_empty()?;
+            self.fifo_written = 0;
+        }
+        self.fifo_written = self.fifo_written.saturating_add(n);
+        Ok(())
+    }
+
+    //poll the status register until `done` returns true, giving up after max_polls reads
+    pub(crate) fn wait_status<F: Fn(u8) -> bool>(&mut self, done: F) -> Res<(), PinErr, SPIErr> {
+        for _ in 0..self.poll.max_polls {
+            #[cfg(feature = "metrics")]
+            self.count_poll();
+            if done(self.status_read()?) {
+                return Ok(());
+            }
+            self.poll_pause();
+        }
+        Err(Error::Timeout)
+    }
+
+    pub(crate) fn system_check_temp<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        delay: &mut D,
+    ) -> Res<(), PinErr, SPIErr> {
+        if !self.host.readable {
+            //the lock bit can't be read, start the PLL check once and give it the time
+            self.register_write(regs::CCR, 0x80)?;
+            delay.delay_us(common::PLL_LOCK_US);
+            return Ok(());
+        }
+        for _ in 0..self.poll.max_polls {
+            if self.status_read()? & common::STATUS_INHIBIT == 0 {
+                delay.delay_us(common::CHECK_STEP_US);
+                self.cmd_write(regs::CCR)?;
+                delay.delay_us(common::CHECK_STEP_US);
+                if self.data_read()? & 0x80 == 0x80 {
+                    return Ok(());
+                }
+                delay.delay_us(common::CHECK_STEP_US);
+                self.cmd_write(regs::CCR)?;
+                delay.delay_us(common::CHECK_STEP_US);
+                self.data_write(0x80)?;
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    pub(crate) fn sdram_check_ready(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.wait_status(|s| s & common::STATUS_SDRAM_READY != 0)
+    }
+
+    pub fn graphic_mode(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::ICR, 0b0000_0100, 0)?;
+        self.text_mode = false;
+        Ok(())
+    }
+
+    /// Memory writes are interpreted as characters for the internal font engine while in text mode.
+    pub fn text_mode(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::ICR, 0, 0b0000_0100)?;
+        self.text_mode = true;
+        Ok(())
+    }
+
+    pub fn is_text_mode(&self) -> bool {
+        self.text_mode
+    }
+
+    fn memory_xy_mode(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::AW_COLOR, 0b0000_0100, 0)
+    }
+
+    fn write_memory_color_mode(&mut self, mode: ColorMode) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::AW_COLOR, 0b0011, common::memory_color_bits(mode))
+    }
+
+    pub fn goto_pixel(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
+        let (x, y) = self.transpose(x, y);
+        self.register_write_u16(regs::CURH0, x)?;
+        self.register_write_u16(regs::CURV0, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::support::{self, NoDelay};
+
+    //register writes of a 16-bit init on the default panel, detect through the window setup
+    const INIT_WRITES: [(u8, u8); 77] = [
+        (0xd2, 0xa5),
+        (0xd2, 0x5a),
+        (0x01, 0x80),
+        (0x05, 0x8a),
+        (0x07, 0x8a),
+        (0x09, 0x8a),
+        (0x06, 0x0f),
+        (0x08, 0x64),
+        (0x0a, 0x64),
+        (0x00, 0x80),
+        (0x85, 0x0a),
+        (0x88, 0x64),
+        (0x8a, 0x64),
+        (0x8c, 0x64),
+        (0x8e, 0x64),
+        (0x86, 0x33),
+        (0xe0, 0x29),
+        (0xe1, 0x03),
+        (0xe2, 0xdc),
+        (0xe3, 0x01),
+        (0xe4, 0x01),
+        (0x01, 0x90),
+        (0x01, 0x91),
+        (0x02, 0x40),
+        (0x02, 0x40),
+        (0x03, 0x00),
+        (0x03, 0x00),
+        (0x12, 0x00),
+        (0x12, 0x00),
+        (0x12, 0x00),
+        (0x12, 0x80),
+        (0x13, 0x03),
+        (0x13, 0x03),
+        (0x13, 0x03),
+        (0x12, 0x80),
+        (0x02, 0x40),
+        (0x14, 0x3b),
+        (0x15, 0x00),
+        (0x1a, 0x0f),
+        (0x1b, 0x01),
+        (0x16, 0x10),
+        (0x17, 0x04),
+        (0x18, 0x13),
+        (0x19, 0x01),
+        (0x1c, 0x13),
+        (0x1d, 0x00),
+        (0x1e, 0x0b),
+        (0x1f, 0x02),
+        (0x10, 0x04),
+        (0x5e, 0x00),
+        (0x5e, 0x01),
+        (0x10, 0x04),
+        (0x20, 0x00),
+        (0x21, 0x00),
+        (0x22, 0x00),
+        (0x23, 0x00),
+        (0x24, 0xe0),
+        (0x25, 0x01),
+        (0x26, 0x00),
+        (0x27, 0x00),
+        (0x28, 0x00),
+        (0x29, 0x00),
+        (0x50, 0x00),
+        (0x51, 0x00),
+        (0x52, 0x00),
+        (0x53, 0x00),
+        (0x54, 0xe0),
+        (0x55, 0x01),
+        (0x56, 0x00),
+        (0x57, 0x00),
+        (0x58, 0x00),
+        (0x59, 0x00),
+        (0x5a, 0xe0),
+        (0x5b, 0x01),
+        (0x5c, 0x10),
+        (0x5d, 0x01),
+        (0x12, 0xc0),
+    ];
+
+    #[test]
+    fn init_sequence() {
+        let (mut display, rec) = support::display(ColorMode::SixteenBit);
+        display.init(&mut NoDelay).unwrap();
+        assert_eq!(rec.register_writes(), INIT_WRITES);
+        //one access type per chip select assertion
+        for f in rec.frames() {
+            assert_eq!(f.len(), 2, "{:02x?}", f);
+        }
+    }
+
+    #[test]
+    fn write_only_init() {
+        let (mut display, rec) = support::display(ColorMode::SixteenBit);
+        display.set_host_interface(HostInterface::SPI_3WIRE_WRITE_ONLY);
+        display.init(&mut NoDelay).unwrap();
+        let frames = rec.frames();
+        assert!(
+            frames
+                .iter()
+                .all(|f| f[0] == common::CMD_WRITE || f[0] == common::DATA_WRITE),
+            "read on a write-only bus"
+        );
+        let writes = rec.register_writes();
+        assert!(writes.contains(&(regs::CCR, 0x80)));
+        assert!(!writes.iter().any(|&(r, _)| r == regs::FGCR));
+        assert_eq!(writes.last(), Some(&(regs::DPCR, 0xc0)));
+        assert_eq!(display.soft_reset(&mut NoDelay), Ok(None));
+    }
+
+    #[test]
+    fn reinit_selects_before_writing() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.register_write(regs::FGCR, 1).unwrap();
+        rec.clear();
+        display.reinit(&mut NoDelay).unwrap();
+        let frames = rec.frames();
+        let first = frames
+            .iter()
+            .position(|f| f[0] == common::DATA_WRITE)
+            .unwrap();
+        assert!(first > 0);
+        assert_eq!(frames[first - 1][0], common::CMD_WRITE);
+    }
+
+    #[test]
+    fn detect_waits_for_host_access() {
+        let (mut display, rec) = support::display(ColorMode::SixteenBit);
+        rec.set_status(common::STATUS_WR_FIFO_EMPTY | common::STATUS_INHIBIT);
+        let mut seq = display.start_init();
+        for _ in 0..3 {
+            assert_eq!(seq.poll(&mut display, 0), Ok(InitProgress::Pending));
+        }
+        assert!(rec.register_writes().is_empty());
+        rec.set_status(common::STATUS_WR_FIFO_EMPTY);
+        seq.poll(&mut display, 0).unwrap();
+        assert_eq!(rec.register_writes().first(), Some(&(regs::FGCR, 0xa5)));
+    }
+
+    #[test]
+    fn set_color_mode_idempotent() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.set_color_mode(ColorMode::SixteenBit).unwrap();
+        assert!(rec.frames().is_empty());
+
+        display.set_color_mode(ColorMode::TwentyFourBit).unwrap();
+        let (mpwctr, aw_color) = (rec.register(regs::MPWCTR), rec.register(regs::AW_COLOR));
+        assert_eq!(mpwctr & 0x0c, 0x08);
+        assert_eq!(aw_color & 0x03, 0x02);
+        rec.clear();
+        display.set_color_mode(ColorMode::TwentyFourBit).unwrap();
+        assert!(rec.frames().is_empty());
+        assert_eq!(display.color_mode(), ColorMode::TwentyFourBit);
+    }
+
+    #[test]
+    fn orientations() {
+        use crate::TFTMC043Draw16Bit;
+        use embedded_graphics_core::{pixelcolor::Rgb565, prelude::*};
+
+        let reg16 = |rec: &crate::testing::RecordingSpi, r: u8| {
+            u16::from_le_bytes([rec.register(r), rec.register(r + 1)])
+        };
+        //orientation, DPCR flip bits, MACR write direction, whether x and y swap
+        for (o, flips, dir, swapped) in [
+            (Orientation::Normal, 0b0_0000, 0b000, false),
+            (Orientation::Rot90, 0b1_0000, 0b100, true),
+            (Orientation::Rot180, 0b1_1000, 0b000, false),
+            (Orientation::Rot270, 0b0_1000, 0b100, true),
+        ] {
+            let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+            display.set_orientation(o).unwrap();
+            assert_eq!(rec.register(regs::DPCR) & 0b1_1000, flips, "{o:?}");
+            assert_eq!(rec.register(regs::MACR) & 0b110, dir, "{o:?}");
+            let swap = |(x, y): (u16, u16)| if swapped { (y, x) } else { (x, y) };
+            let dims = swap((480, 272));
+            assert_eq!(display.dimensions(), dims, "{o:?}");
+
+            let mut draw = TFTMC043Draw16Bit::new(display).unwrap();
+            let size = Size::new(dims.0 as u32, dims.1 as u32);
+            assert_eq!(draw.size(), size, "{o:?}");
+            let area = Rectangle::new(Point::new(10, 20), Size::new(30, 5));
+            draw.fill_solid(&area, Rgb565::RED).unwrap();
+            let start = (reg16(&rec, regs::DLHSR0), reg16(&rec, regs::DLVSR0));
+            let end = (reg16(&rec, regs::DLHER0), reg16(&rec, regs::DLVER0));
+            assert_eq!((start, end), (swap((10, 20)), swap((39, 24))), "{o:?}");
+
+            let display = draw.inner_mut();
+            display.active_window_raw(1, 2, 30, 40).unwrap();
+            let origin = (reg16(&rec, regs::AWUL_X0), reg16(&rec, regs::AWUL_Y0));
+            let size = (reg16(&rec, regs::AW_WTH0), reg16(&rec, regs::AW_HT0));
+            assert_eq!((origin, size), (swap((1, 2)), swap((30, 40))), "{o:?}");
+            display.goto_pixel(5, 7).unwrap();
+            let cursor = (reg16(&rec, regs::CURH0), reg16(&rec, regs::CURV0));
+            assert_eq!(cursor, swap((5, 7)), "{o:?}");
+        }
+    }
+
+    #[test]
+    fn active_window_registers() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        let area = Rectangle::new(Point::new(300, 20), Size::new(180, 252));
+        display.active_window(area).unwrap();
+        assert_eq!(
+            rec.register_writes(),
+            [
+                (regs::AWUL_X0, 0x2c),
+                (regs::AWUL_X0 + 1, 0x01),
+                (regs::AWUL_Y0, 20),
+                (regs::AWUL_Y0 + 1, 0),
+                (regs::AW_WTH0, 180),
+                (regs::AW_WTH0 + 1, 0),
+                (regs::AW_HT0, 252),
+                (regs::AW_HT0 + 1, 0),
+            ]
+        );
+        let outside = Rectangle::new(Point::new(300, 20), Size::new(181, 10));
+        assert!(display.active_window(outside).is_err());
+    }
+
+    #[test]
+    fn register_write_framing() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.register_write(regs::FGCR, 0x12).unwrap();
+        display.register_write(regs::FGCG, 0x34).unwrap();
+        assert_eq!(
+            rec.frames(),
+            [
+                [common::CMD_WRITE, regs::FGCR],
+                [common::DATA_WRITE, 0x12],
+                [common::CMD_WRITE, regs::FGCG],
+                [common::DATA_WRITE, 0x34],
+            ]
+        );
+    }
+
+    #[test]
+    fn status_bits() {
+        assert_eq!(StatusFlags::from_bits(0), StatusFlags::default());
+        let bits = [
+            (
+                0x80,
+                StatusFlags {
+                    write_fifo_full: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                0x40,
+                StatusFlags {
+                    write_fifo_empty: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                0x20,
+                StatusFlags {
+                    read_fifo_full: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                0x10,
+                StatusFlags {
+                    read_fifo_empty: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                0x08,
+                StatusFlags {
+                    core_busy: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                0x04,
+                StatusFlags {
+                    sdram_ready: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                0x02,
+                StatusFlags {
+                    inhibit: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                0x01,
+                StatusFlags {
+                    interrupt_pending: true,
+                    ..Default::default()
+                },
+            ),
+        ];
+        for (bit, flags) in bits {
+            assert_eq!(StatusFlags::from_bits(bit), flags, "{bit:#04x}");
+        }
+    }
+
+    #[test]
+    fn fifo_waits() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        let poll = display.poll_config();
+        display.set_poll_config(PollConfig {
+            max_polls: 3,
+            ..poll
+        });
+        //full and not yet drained
+        rec.set_status(common::STATUS_WR_FIFO_FULL | common::STATUS_SDRAM_READY);
+        assert_eq!(display.wait_wr_fifo_not_full(), Err(Error::Timeout));
+        assert_eq!(display.wait_wr_fifo_empty(), Err(Error::Timeout));
+        //some room, still draining
+        rec.set_status(common::STATUS_SDRAM_READY);
+        assert_eq!(display.wait_wr_fifo_not_full(), Ok(()));
+        assert_eq!(display.wait_wr_fifo_empty(), Err(Error::Timeout));
+        rec.set_status(common::STATUS_WR_FIFO_EMPTY | common::STATUS_SDRAM_READY);
+        assert_eq!(display.wait_wr_fifo_empty(), Ok(()));
+        assert_eq!(display.wait_rd_fifo_not_empty(), Ok(()));
+        rec.set_status(common::STATUS_RD_FIFO_EMPTY | common::STATUS_SDRAM_READY);
+        assert_eq!(display.wait_rd_fifo_not_empty(), Err(Error::Timeout));
+    }
+
+    #[test]
+    fn register_write_all_batches_runs() {
+        let pairs = [
+            (regs::FGCR, 1),
+            (regs::FGCR, 2),
+            (regs::FGCR, 3),
+            (regs::FGCG, 4),
+            (regs::FGCB, 5),
+            (regs::FGCB, 6),
+        ];
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        for &(reg, v) in &pairs {
+            display.register_write(reg, v).unwrap();
+        }
+        let single = rec.frames();
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.register_write_all(&pairs).unwrap();
+        let batched = rec.frames();
+        assert_eq!(
+            batched,
+            [
+                std::vec![common::CMD_WRITE, regs::FGCR],
+                std::vec![common::DATA_WRITE, 1, 2, 3],
+                std::vec![common::CMD_WRITE, regs::FGCG],
+                std::vec![common::DATA_WRITE, 4],
+                std::vec![common::CMD_WRITE, regs::FGCB],
+                std::vec![common::DATA_WRITE, 5, 6],
+            ]
+        );
+        let bytes = |f: &[std::vec::Vec<u8>]| f.iter().map(|f| f.len()).sum::<usize>();
+        assert_eq!((single.len(), bytes(&single)), (9, 18));
+        assert_eq!((batched.len(), bytes(&batched)), (6, 15));
+        assert_eq!(rec.register(regs::FGCR), 3);
+
+        //read back one at a time
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.set_verify_writes(true);
+        display.register_write_all(&pairs).unwrap();
+        assert_eq!(rec.register_writes(), pairs);
+    }
+
+    #[test]
+    fn shadowed_init_matches_read_back() {
+        let (mut display, read_back) = support::display(ColorMode::SixteenBit);
+        display.init(&mut NoDelay).unwrap();
+        let (mut display, shadowed) = support::display(ColorMode::SixteenBit);
+        display.enable_shadow_registers();
+        display.init(&mut NoDelay).unwrap();
+        for reg in 0..=u8::MAX {
+            assert_eq!(
+                shadowed.register(reg),
+                read_back.register(reg),
+                "{reg:#04x}"
+            );
+        }
+        let reads = |rec: &crate::testing::RecordingSpi| {
+            rec.frames()
+                .iter()
+                .filter(|f| f.first() == Some(&common::DATA_READ))
+                .count()
+        };
+        assert!(reads(&shadowed) < reads(&read_back));
+        //the shadows agree with the registers too
+        for (reg, _) in common::SHADOWED {
+            assert_eq!(
+                display.register_read(reg),
+                Ok(shadowed.register(reg)),
+                "{reg:#04x}"
+            );
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Quadrant(u8),
+        Triangle([(u16, u16); 3]),
+        Line([(u16, u16); 2]),
+    }
+
+    //what a sector call drew, from its register writes
+    fn sector_shapes(start: u16, sweep: u16, fill: bool) -> std::vec::Vec<Shape> {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.sector((100, 100), 50, start, sweep, fill).unwrap();
+        let mut regs = [0u8; 256];
+        let mut shapes = std::vec::Vec::new();
+        for (reg, v) in rec.register_writes() {
+            regs[reg as usize] = v;
+            let at = |r: u8| {
+                (
+                    u16::from_le_bytes([regs[r as usize], regs[r as usize + 1]]),
+                    u16::from_le_bytes([regs[r as usize + 2], regs[r as usize + 3]]),
+                )
+            };
+            match (reg, v) {
+                (regs::DCR1, v) if v & 0xb0 == 0x90 => shapes.push(Shape::Quadrant(v & 0x03)),
+                (regs::DCR0, 0xa2) => {
+                    shapes.push(Shape::Triangle([
+                        at(regs::DLHSR0),
+                        at(regs::DLHER0),
+                        at(regs::DTPH0),
+                    ]));
+                }
+                (regs::DCR0, 0x80) => {
+                    shapes.push(Shape::Line([at(regs::DLHSR0), at(regs::DLHER0)]))
+                }
+                _ => (),
+            }
+        }
+        shapes
+    }
+
+    #[test]
+    fn sector_quadrant_boundaries() {
+        use Shape::Quadrant as Q;
+        let (ur, ul, bl, br) = (
+            Quadrant::UpperRight as u8,
+            Quadrant::UpperLeft as u8,
+            Quadrant::BottomLeft as u8,
+            Quadrant::BottomRight as u8,
+        );
+        //0 along +x, counterclockwise on screen
+        assert_eq!(sector_shapes(0, 900, true), [Q(ur)]);
+        assert_eq!(sector_shapes(900, 900, true), [Q(ul)]);
+        assert_eq!(sector_shapes(1800, 900, true), [Q(bl)]);
+        assert_eq!(sector_shapes(2700, 900, true), [Q(br)]);
+        assert_eq!(sector_shapes(0, 3600, true), [Q(ur), Q(ul), Q(bl), Q(br)]);
+        //360 wraps to 0 and sweeps past a full turn are the whole circle
+        assert_eq!(sector_shapes(3600, 900, true), [Q(ur)]);
+        assert_eq!(
+            sector_shapes(900, u16::MAX, true),
+            [Q(ul), Q(bl), Q(br), Q(ur)]
+        );
+        assert_eq!(sector_shapes(0, 0, true), []);
+    }
+
+    #[test]
+    fn sector_partial_edges() {
+        //three triangles each side of 0, meeting on the +x axis
+        let shapes = sector_shapes(3500, 200, true);
+        assert_eq!(shapes.len(), 6);
+        let Shape::Triangle([c, _, p]) = &shapes[2] else {
+            panic!("{:?}", shapes)
+        };
+        assert_eq!((*c, *p), ((100, 100), (150, 100)));
+        let Shape::Triangle([_, p0, _]) = &shapes[3] else {
+            panic!("{:?}", shapes)
+        };
+        assert_eq!(*p0, (150, 100));
+
+        //an outline up to 90 is closed by radii to (150, 100) and (100, 50)
+        let shapes = sector_shapes(450, 450, false);
+        assert_eq!(
+            shapes[shapes.len() - 2..],
+            [
+                Shape::Line([(100, 100), (135, 65)]),
+                Shape::Line([(100, 100), (100, 50)]),
+            ]
+        );
+        assert_eq!(
+            sector_shapes(0, 900, false)[1..],
+            [
+                Shape::Line([(100, 100), (150, 100)]),
+                Shape::Line([(100, 100), (100, 50)]),
+            ]
+        );
+    }
+}
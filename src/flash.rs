@@ -0,0 +1,199 @@
+//! External serial flash and the DMA engine copying from it to SDRAM, REG[B6h]-REG[CBh]
+
+use crate::{common, er5517::SDRAM_BYTES, regs, Error, Interface, Res, ER5517};
+
+/// bytes per image table entry
+pub const IMAGE_ENTRY_LEN: u32 = 12;
+
+//the table entry is copied here to read it back, the last bytes of the SDRAM
+const HEADER_SCRATCH: u32 = SDRAM_BYTES - IMAGE_ENTRY_LEN;
+
+/// An image from the flash image table, see [`ER5517::load_image_from_flash`]
+///
+/// Table entries are [`IMAGE_ENTRY_LEN`] bytes, little endian: the 32-bit flash address of the
+/// pixel data, 16-bit width and height, bits per pixel and three reserved bytes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ImageInfo {
+    pub flash_addr: u32,
+    pub width: u16,
+    pub height: u16,
+    pub bpp: u8,
+}
+
+impl ImageInfo {
+    pub fn from_entry(e: &[u8; IMAGE_ENTRY_LEN as usize]) -> Self {
+        Self {
+            flash_addr: u32::from_le_bytes([e[0], e[1], e[2], e[3]]),
+            width: u16::from_le_bytes([e[4], e[5]]),
+            height: u16::from_le_bytes([e[6], e[7]]),
+            bpp: e[8],
+        }
+    }
+}
+
+/// Serial flash chip select used by the controller's SPI master
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FlashSelect {
+    Flash0,
+    Flash1,
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Select the flash for DMA, read with the 03h command and 24-bit addresses.
+    pub fn select_flash(&mut self, which: FlashSelect) -> Res<(), PinErr, SPIErr> {
+        //bit 7 chip select, bit 6 DMA mode, bit 5 24-bit address, bits 3-0 03h read
+        let v = match which {
+            FlashSelect::Flash0 => 0b0100_0000,
+            FlashSelect::Flash1 => 0b1100_0000,
+        };
+        self.modify(regs::SFL_CTRL, 0b1110_1111, v)
+    }
+
+    /// Flash SPI clock, core clock / ((div + 1) * 2).
+    pub fn set_flash_clock_div(&mut self, div: u8) -> Res<(), PinErr, SPIErr> {
+        self.register_write(regs::SPI_DIVSOR, div)
+    }
+
+    /// Copy a `w` x `h` block from the picture at `flash_addr`, `picture_w` pixels wide, to
+    /// `x`, `y` of the image at `sdram_addr`.
+    ///
+    /// The SDRAM image has the canvas width, the picture is in the current color mode.
+    #[allow(clippy::too_many_arguments)]
+    pub fn flash_dma_to_sdram(
+        &mut self,
+        flash_addr: u32,
+        sdram_addr: u32,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        picture_w: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        let (canvas_addr, image_w) = self.canvas_target();
+        let picture_len = picture_w as u32 * h as u32 * self.color_mode().bytes_per_pixel();
+        //24-bit flash addresses
+        if w == 0
+            || h == 0
+            || w > picture_w
+            || x as u32 + w as u32 > image_w as u32
+            || flash_addr as u64 + picture_len as u64 > 1 << 24
+        {
+            return Err(Error::InvalidParam);
+        }
+        self.check_awake()?;
+
+        //the DMA writes through the canvas registers
+        self.canvas_image(sdram_addr, image_w)?;
+        self.register_write_u32(regs::DMA_SSTR0, flash_addr)?;
+        self.register_write_u16(regs::DMA_DX0, x)?;
+        self.register_write_u16(regs::DMA_DY0, y)?;
+        self.register_write_u16(regs::DMAW_WTH0, w)?;
+        self.register_write_u16(regs::DMAW_HIGH0, h)?;
+        self.register_write_u16(regs::DMA_SWTH0, picture_w)?;
+        self.modify(regs::DMA_CTRL, 0, 0b0000_0001)?;
+        let r = self.flash_dma_wait();
+        self.canvas_image(canvas_addr, image_w)?;
+        r
+    }
+
+    //REG[B6h] bit 0 reads 1 while the DMA is running
+    fn flash_dma_wait(&mut self) -> Res<(), PinErr, SPIErr> {
+        for _ in 0..self.busy_timeout() {
+            if self.register_read_live(regs::DMA_CTRL)? & 0b0000_0001 == 0 {
+                return Ok(());
+            }
+            self.poll_pause();
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Copy image `index` of the table at `table_base` in flash to `x`, `y` of the canvas, in
+    /// memory coordinates.
+    ///
+    /// The entry is read through the DMA into the last bytes of the SDRAM. Entries with a zero
+    /// size, a depth other than the color mode's or an image that doesn't fit the flash or the
+    /// canvas are rejected with [`Error::BadImageHeader`] before anything is copied.
+    pub fn load_image_from_flash(
+        &mut self,
+        index: u16,
+        table_base: u32,
+        x: u16,
+        y: u16,
+    ) -> Res<ImageInfo, PinErr, SPIErr> {
+        let entry_addr = table_base as u64 + index as u64 * IMAGE_ENTRY_LEN as u64;
+        if entry_addr + IMAGE_ENTRY_LEN as u64 > 1 << 24 {
+            return Err(Error::InvalidParam);
+        }
+        let mut entry = [0; IMAGE_ENTRY_LEN as usize];
+        self.read_flash_entry(entry_addr as u32, &mut entry)?;
+        let info = ImageInfo::from_entry(&entry);
+
+        let bpp = self.color_mode().bytes_per_pixel();
+        let (canvas_addr, image_w) = self.canvas_target();
+        let image_h = self.canvas_height();
+        let len = info.width as u64 * info.height as u64 * bpp as u64;
+        if info.width == 0
+            || info.height == 0
+            || info.bpp as u32 != bpp * 8
+            || info.flash_addr as u64 + len > 1 << 24
+            || x as u32 + info.width as u32 > image_w as u32
+            || y as u32 + info.height as u32 > image_h as u32
+            || self
+                .pixel_address(
+                    canvas_addr,
+                    image_w,
+                    x + info.width - 1,
+                    y + info.height - 1,
+                )
+                .is_none()
+        {
+            return Err(Error::BadImageHeader);
+        }
+        self.flash_dma_to_sdram(
+            info.flash_addr,
+            canvas_addr,
+            x,
+            y,
+            info.width,
+            info.height,
+            info.width,
+        )?;
+        Ok(info)
+    }
+
+    //DMA a table entry to the scratch bytes and read it back through the memory port, in
+    //memory coordinates so the orientation doesn't matter
+    fn read_flash_entry(&mut self, addr: u32, buf: &mut [u8]) -> Res<(), PinErr, SPIErr> {
+        let bpp = self.color_mode().bytes_per_pixel();
+        let px = (buf.len() as u32 / bpp) as u16;
+        let (canvas_addr, image_w) = self.canvas_target();
+        self.check_awake()?;
+        if self.is_text_mode() {
+            self.graphic_mode()?;
+        }
+        self.canvas_image(HEADER_SCRATCH, px)?;
+        self.register_write_u32(regs::DMA_SSTR0, addr)?;
+        self.register_write_all(&common::quad_registers(regs::DMA_DX0, [0, 0, 0, 0])[..4])?;
+        self.register_write_all(&common::quad_registers(regs::DMAW_WTH0, [px, 1, px, 0])[..6])?;
+        self.modify(regs::DMA_CTRL, 0, 0b0000_0001)?;
+        let r = self.flash_dma_wait().and_then(|_| {
+            self.register_write_all(&common::quad_registers(regs::AWUL_X0, [0, 0, px, 1]))?;
+            self.register_write_all(&common::quad_registers(regs::CURH0, [0, 0, 0, 0])[..4])?;
+            self.cmd_write(regs::MRWDP)?;
+            //the first read after the command only primes the read FIFO
+            self.data_read()?;
+            for b in buf.iter_mut() {
+                self.wait_rd_fifo_not_empty()?;
+                *b = self.data_read()?;
+            }
+            Ok(())
+        });
+        self.canvas_image(canvas_addr, image_w)?;
+        let (w, h) = self.dimensions();
+        self.active_window_raw(0, 0, w, h)?;
+        r
+    }
+}
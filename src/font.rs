@@ -0,0 +1,166 @@
+//! Text attributes and external Genitop font ROMs on the serial flash interface,
+//! REG[CCh]-REG[D1h]
+
+use crate::{regs, Error, Interface, Res, ER5517};
+
+/// Genitop font ROM part, REG[CEh] bits 7-5
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FontRom {
+    GT21L16T1W,
+    GT30L16U2W,
+    GT30L24T3Y,
+    GT30L24M1Z,
+    GT30L32S4W,
+    GT20L24F6Y,
+    GT21L24S1W,
+}
+
+/// Half width (ASCII) glyph style, REG[CFh] bits 1-0
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FontWidth {
+    Fixed,
+    Arial,
+    Roman,
+    Bold,
+}
+
+/// Encoding of the text written in external font mode, REG[CFh] bits 7-3
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FontEncoding {
+    Gb2312,
+    Gb12345,
+    Big5,
+    /// UCS-2, two bytes per character, high byte first
+    Unicode,
+    Ascii,
+    UniJapanese,
+    Jis0208,
+    /// Latin, Greek, Cyrillic, Arabic, Thai and Hebrew
+    Latin,
+}
+
+impl FontRom {
+    fn bits(self) -> u8 {
+        (self as u8) << 5
+    }
+}
+
+impl FontWidth {
+    fn bits(self) -> u8 {
+        self as u8
+    }
+}
+
+impl FontEncoding {
+    fn bits(self) -> u8 {
+        (self as u8) << 3
+    }
+}
+
+/// UCS-2 encoder for [`ER5517::write_text_with`] in [`FontEncoding::Unicode`], characters past
+/// the basic multilingual plane are written as `?`.
+pub fn encode_ucs2(c: char, buf: &mut [u8; 4]) -> usize {
+    let v = u16::try_from(c as u32).unwrap_or(b'?' as u16);
+    buf[..2].copy_from_slice(&v.to_be_bytes());
+    2
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Draw text from the external font ROM on the flash chip select chosen by
+    /// [`ER5517::select_flash`], the size still comes from [`ER5517::set_font_size`].
+    ///
+    /// The flash interface is shared with DMA, `select_flash` switches it back to DMA mode.
+    /// Half width characters take half the glyph width so ASCII mixed into CJK text isn't
+    /// spaced out.
+    pub fn select_external_font(
+        &mut self,
+        rom: FontRom,
+        width: FontWidth,
+        encoding: FontEncoding,
+    ) -> Res<(), PinErr, SPIErr> {
+        //REG[B7h] bit 6 = 0, font mode
+        self.modify(regs::SFL_CTRL, 0b0100_0000, 0)?;
+        self.register_write(regs::GTFNT_SEL, rom.bits())?;
+        self.register_write(regs::GTFNT_CR, encoding.bits() | width.bits())?;
+        //REG[CCh] bits 7-6 = 01, external CGROM
+        self.modify(regs::CCR0, 0b1100_0000, 0b0100_0000)?;
+        //REG[CDh] bit 7, full alignment off
+        self.modify(regs::CCR1, 0b1000_0000, 0)
+    }
+
+    /// Back to the internal CGROM for [`ER5517::write_text`].
+    pub fn select_internal_font(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::CCR0, 0b1100_0000, 0)
+    }
+
+    /// Write text already in the selected font's encoding at the text cursor, switching to text
+    /// mode if needed.
+    pub fn write_text_encoded(&mut self, text: &[u8]) -> Res<(), PinErr, SPIErr> {
+        if !self.is_text_mode() {
+            self.text_mode()?;
+        }
+        self.restore_fg()?;
+        self.cmd_write(regs::MRWDP)?;
+        for &b in text {
+            self.wait_wr_fifo_not_full()?;
+            self.data_write(b)?;
+            self.busy_draw()?;
+        }
+        Ok(())
+    }
+
+    /// Write `s` with `encode` converting each character to the font's encoding, it fills the
+    /// buffer and returns the length used.
+    pub fn write_text_with(
+        &mut self,
+        s: &str,
+        mut encode: impl FnMut(char, &mut [u8; 4]) -> usize,
+    ) -> Res<(), PinErr, SPIErr> {
+        let mut buf = [0; 4];
+        for c in s.chars() {
+            let n = encode(c, &mut buf).min(buf.len());
+            self.write_text_encoded(buf.get(..n).unwrap_or_default())?;
+        }
+        Ok(())
+    }
+
+    /// Enlarge glyphs `x` times horizontally and `y` times vertically, 1 to 4.
+    pub fn set_text_scale(&mut self, x: u8, y: u8) -> Res<(), PinErr, SPIErr> {
+        if !(1..=4).contains(&x) || !(1..=4).contains(&y) {
+            return Err(Error::InvalidParam);
+        }
+        //REG[CDh] bits 3-2 horizontal, bits 1-0 vertical
+        self.modify(
+            regs::CCR1,
+            0b0000_1111,
+            (x.saturating_sub(1) << 2) | y.saturating_sub(1),
+        )
+    }
+
+    /// Leave the pixels behind glyphs untouched instead of filling them with the bg color.
+    pub fn text_background_transparent(&mut self, on: bool) -> Res<(), PinErr, SPIErr> {
+        self.modify_bits(regs::CCR1, 0b0100_0000, on)
+    }
+
+    /// Extra pixels between text lines, up to 31.
+    ///
+    /// Text wraps to the next line at the active window's right edge, the controller has no
+    /// setting to turn that off.
+    pub fn set_text_line_spacing(&mut self, px: u8) -> Res<(), PinErr, SPIErr> {
+        if px > 0b1_1111 {
+            return Err(Error::InvalidParam);
+        }
+        self.modify(regs::FLDR, 0b0001_1111, px)
+    }
+
+    /// Extra pixels between characters, up to 63.
+    pub fn set_char_spacing(&mut self, px: u8) -> Res<(), PinErr, SPIErr> {
+        if px > 0b11_1111 {
+            return Err(Error::InvalidParam);
+        }
+        self.modify(regs::F2FSSR, 0b0011_1111, px)
+    }
+}
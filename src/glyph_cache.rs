@@ -0,0 +1,333 @@
+//! Text engine glyphs kept in SDRAM and copied with the BTE, for slow external font ROMs
+
+use crate::{regs, Error, Interface, Res, ER5517};
+
+//REG[CDh] bit 6, see ER5517::text_background_transparent
+const TEXT_TRANSPARENT: u8 = 0b0100_0000;
+
+//character and foreground color
+type Glyph = (char, (u8, u8, u8));
+
+/// Fixed size LRU atlas of `N` glyph cells in an SDRAM image, for
+/// [`ER5517::write_text_cached`]
+///
+/// Glyphs are keyed by character and foreground color, one cache holds a single font and
+/// scale, [`GlyphCache::clear`] it after changing them.
+pub struct GlyphCache<const N: usize> {
+    addr: u32,
+    image_w: u16,
+    cell: (u16, u16),
+    key: (u8, u8, u8),
+    slots: [Option<Glyph>; N],
+    //tick of the last use of each slot
+    used: [u32; N],
+    tick: u32,
+    hits: u32,
+    misses: u32,
+}
+
+impl<const N: usize> GlyphCache<N> {
+    /// A cache of `cell_w` x `cell_h` glyphs in rows across the image at `addr`, `image_w`
+    /// pixels wide, drawn transparent around pixels of the `key` color.
+    ///
+    /// `key` must not be a text color. None without room for a cell in a row or for `N` = 0,
+    /// the image has to fit the SDRAM in the color mode the cache is used with.
+    pub fn new(
+        addr: u32,
+        image_w: u16,
+        cell_w: u16,
+        cell_h: u16,
+        key: (u8, u8, u8),
+    ) -> Option<Self> {
+        if N == 0 || cell_w == 0 || cell_h == 0 || cell_w > image_w {
+            return None;
+        }
+        Some(Self {
+            addr,
+            image_w,
+            cell: (cell_w, cell_h),
+            key,
+            slots: [None; N],
+            used: [0; N],
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// Forget all glyphs.
+    pub fn clear(&mut self) {
+        self.slots = [None; N];
+    }
+
+    /// Draws copied from the atlas and draws that rendered the glyph first.
+    pub fn stats(&self) -> (u32, u32) {
+        (self.hits, self.misses)
+    }
+
+    //top left of `slot` in the atlas image, None past what u16 coordinates reach
+    fn slot_position(&self, slot: usize) -> Option<(u16, u16)> {
+        let cols = (self.image_w.checked_div(self.cell.0)?) as usize;
+        let col = u16::try_from(slot.checked_rem(cols)?).ok()?;
+        let row = u16::try_from(slot.checked_div(cols)?).ok()?;
+        Some((col.checked_mul(self.cell.0)?, row.checked_mul(self.cell.1)?))
+    }
+
+    //slot holding `glyph` and whether it was there, the least recently used one is taken over
+    //otherwise
+    fn lookup(&mut self, glyph: Glyph) -> (usize, bool) {
+        self.tick = self.tick.wrapping_add(1);
+        let slot = match self.slots.iter().position(|s| *s == Some(glyph)) {
+            Some(i) => {
+                self.hits = self.hits.wrapping_add(1);
+                if let Some(u) = self.used.get_mut(i) {
+                    *u = self.tick;
+                }
+                return (i, true);
+            }
+            None => match self.slots.iter().position(|s| s.is_none()) {
+                Some(i) => i,
+                //the smallest age, wrapping ticks included
+                None => self
+                    .used
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|&(_, &u)| self.tick.wrapping_sub(u))
+                    .map_or(0, |(i, _)| i),
+            },
+        };
+        self.misses = self.misses.wrapping_add(1);
+        if let (Some(s), Some(u)) = (self.slots.get_mut(slot), self.used.get_mut(slot)) {
+            *s = Some(glyph);
+            *u = self.tick;
+        }
+        (slot, false)
+    }
+
+    //forget what `slot` holds
+    fn evict(&mut self, slot: usize) {
+        if let Some(s) = self.slots.get_mut(slot) {
+            *s = None;
+        }
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Write `text` in `fg` on a transparent background from `x`, `y` on, one cache cell per
+    /// character, without wrapping.
+    ///
+    /// A glyph not in `cache` is drawn by the text engine into its cell of the atlas first,
+    /// then every use is a chroma keyed BTE copy. That moves more bytes over SPI than writing
+    /// the character, it pays off when the controller's font ROM fetches are the slow part.
+    /// Positions are in memory coordinates, like [`ER5517::set_text_cursor`].
+    pub fn write_text_cached<const N: usize>(
+        &mut self,
+        cache: &mut GlyphCache<N>,
+        x: u16,
+        y: u16,
+        text: &str,
+        fg: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        let (cw, ch) = cache.cell;
+        //the far corner of the last cell, all cells are inside the image when it is
+        let corner = cache.slot_position(N.saturating_sub(1)).and_then(|(x, y)| {
+            Some((
+                x.checked_add(cw)?.checked_sub(1)?,
+                y.checked_add(ch)?.checked_sub(1)?,
+            ))
+        });
+        let fits = corner.is_some_and(|(x, y)| {
+            self.pixel_address(cache.addr, cache.image_w, x, y)
+                .is_some()
+        });
+        if !fits {
+            return Err(Error::OutOfBounds);
+        }
+        //the copies and renders all key on the bg color, the previous one is put back once
+        self.with_bg(cache.key, |s| {
+            let mut x = x;
+            for c in text.chars() {
+                let (slot, hit) = cache.lookup((c, fg));
+                let src = cache.slot_position(slot).ok_or(Error::OutOfBounds)?;
+                if !hit {
+                    if let Err(e) = s.render_glyph(cache, src, c, fg) {
+                        //the slot may hold a partial glyph
+                        cache.evict(slot);
+                        return Err(e);
+                    }
+                }
+                s.bte_copy_chroma_from(cache.addr, cache.image_w, src, (x, y), cw, ch, cache.key)?;
+                x = x.checked_add(cw).ok_or(Error::OutOfBounds)?;
+            }
+            Ok(())
+        })
+    }
+
+    //draw `c` on the key color into the atlas cell at `pos`, then back to the previous canvas,
+    //fg color and text background
+    fn render_glyph<const N: usize>(
+        &mut self,
+        cache: &GlyphCache<N>,
+        pos: (u16, u16),
+        c: char,
+        fg: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        let prev_image = self.state.canvas_image;
+        let (prev_addr, prev_w) = prev_image.unwrap_or_else(|| self.canvas_target());
+        let prev_window = self.state.active_window;
+        let prev_fg = self.fg;
+        //a transparent background would leave what the slot held before around the glyph
+        let transparent = self.register_read(regs::CCR1)? & TEXT_TRANSPARENT != 0;
+        let r = self.draw_glyph(cache, pos, c, fg, transparent);
+        self.fg = prev_fg;
+        if transparent {
+            self.text_background_transparent(true)?;
+        }
+        self.canvas_image(prev_addr, prev_w)?;
+        self.state.canvas_image = prev_image;
+        let (x, y, w, h) = match prev_window {
+            Some(v) => v,
+            None => {
+                let (w, h) = self.dimensions();
+                (0, 0, w, h)
+            }
+        };
+        self.active_window_raw(x, y, w, h)?;
+        r
+    }
+
+    //render_glyph without the restoring, the bg registers already hold the key
+    fn draw_glyph<const N: usize>(
+        &mut self,
+        cache: &GlyphCache<N>,
+        pos: (u16, u16),
+        c: char,
+        fg: (u8, u8, u8),
+        transparent: bool,
+    ) -> Res<(), PinErr, SPIErr> {
+        //the whole cell, the glyph may not cover it
+        self.bte_solid_fill_at((cache.addr, cache.image_w), pos, cache.cell, cache.key)?;
+        if transparent {
+            self.text_background_transparent(false)?;
+        }
+        self.canvas_image(cache.addr, cache.image_w)?;
+        self.active_window_raw(pos.0, pos.1, cache.cell.0, cache.cell.1)?;
+        //write_text draws in the fg_color color
+        self.fg = Some(fg);
+        self.set_text_cursor(pos.0, pos.1)?;
+        let mut buf = [0; 4];
+        self.write_text(c.encode_utf8(&mut buf))?;
+        self.graphic_mode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{support, RecordingSpi};
+    use crate::ColorMode;
+    use std::vec::Vec;
+
+    const WHITE: (u8, u8, u8) = (0xff, 0xff, 0xff);
+    const KEY: (u8, u8, u8) = (0xff, 0, 0xff);
+
+    #[test]
+    fn lru_eviction() {
+        let mut cache = GlyphCache::<2>::new(0, 64, 16, 16, KEY).unwrap();
+        assert_eq!(cache.lookup(('a', WHITE)), (0, false));
+        assert_eq!(cache.lookup(('b', WHITE)), (1, false));
+        assert_eq!(cache.lookup(('a', WHITE)), (0, true));
+        //b is the least recently used
+        assert_eq!(cache.lookup(('c', WHITE)), (1, false));
+        assert_eq!(cache.lookup(('a', WHITE)), (0, true));
+        assert_eq!(cache.lookup(('b', WHITE)), (1, false));
+        //the color is part of the key
+        assert_eq!(cache.lookup(('b', (0, 0, 0))), (0, false));
+        assert_eq!(cache.stats(), (2, 5));
+
+        cache.clear();
+        assert_eq!(cache.lookup(('b', (0, 0, 0))), (0, false));
+        assert_eq!(cache.slot_position(0), Some((0, 0)));
+        let cache = GlyphCache::<8>::new(0, 64, 16, 16, KEY).unwrap();
+        assert_eq!(cache.slot_position(3), Some((48, 0)));
+        assert_eq!(cache.slot_position(5), Some((16, 16)));
+        assert!(GlyphCache::<0>::new(0, 64, 16, 16, KEY).is_none());
+        assert!(GlyphCache::<2>::new(0, 8, 16, 16, KEY).is_none());
+    }
+
+    //characters the text engine wrote
+    fn rendered(rec: &RecordingSpi) -> Vec<char> {
+        rec.register_writes()
+            .into_iter()
+            .filter(|&(r, _)| r == regs::MRWDP)
+            .map(|(_, c)| c as char)
+            .collect()
+    }
+
+    #[test]
+    fn slots_reused() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        let mut cache = GlyphCache::<2>::new(0x0010_0000, 64, 16, 16, KEY).unwrap();
+        display
+            .write_text_cached(&mut cache, 0, 0, "ab", WHITE)
+            .unwrap();
+        assert_eq!(rendered(&rec), ['a', 'b']);
+        rec.clear();
+        display
+            .write_text_cached(&mut cache, 0, 16, "baab", WHITE)
+            .unwrap();
+        assert!(rendered(&rec).is_empty());
+        //b was used last, a is evicted
+        display
+            .write_text_cached(&mut cache, 0, 32, "cba", WHITE)
+            .unwrap();
+        assert_eq!(rendered(&rec), ['c', 'a']);
+        assert_eq!(cache.stats(), (5, 4));
+    }
+
+    #[test]
+    fn render_restores_colors_and_background() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.fg_color(9, 9, 9).unwrap();
+        display.bg_color(1, 2, 3).unwrap();
+        display.text_background_transparent(true).unwrap();
+        let mut cache = GlyphCache::<2>::new(0x0010_0000, 64, 16, 16, KEY).unwrap();
+        rec.clear();
+        display
+            .write_text_cached(&mut cache, 0, 0, "a", WHITE)
+            .unwrap();
+
+        let w = rec.register_writes();
+        let at = |reg: u8, v: u8| w.iter().position(|&x| x == (reg, v)).unwrap();
+        let text = at(regs::MRWDP, b'a');
+        //the cell is filled with the key in the atlas first
+        let fill = at(regs::BTE_CTRL1, 0x0c);
+        assert!(fill < text);
+        let last = |reg: u8, end: usize| w[..end].iter().rev().find(|x| x.0 == reg).unwrap().1;
+        assert_eq!(
+            (last(regs::FGCR, fill), last(regs::FGCG, fill)),
+            (KEY.0, KEY.1)
+        );
+        assert_eq!(last(regs::DT_STR0 + 2, fill), 0x10);
+        //an opaque key background and the glyph color
+        assert_eq!(last(regs::CCR1, text) & TEXT_TRANSPARENT, 0);
+        assert_eq!(last(regs::BGCR, text), KEY.0);
+        assert_eq!(last(regs::FGCR, text), WHITE.0);
+
+        assert_ne!(rec.register(regs::CCR1) & TEXT_TRANSPARENT, 0);
+        let bg = (
+            rec.register(regs::BGCR),
+            rec.register(regs::BGCG),
+            rec.register(regs::BGCB),
+        );
+        assert_eq!(bg, (1, 2, 3));
+        assert_eq!(display.cached_fg_color(), Some((9, 9, 9)));
+        //the next draw puts the fg color back
+        rec.clear();
+        display.hline(0, 0, 10).unwrap();
+        assert!(rec.register_writes().contains(&(regs::FGCR, 9)));
+    }
+}
@@ -0,0 +1,103 @@
+//! General purpose I/O ports, REG[F0h]-REG[FAh]
+
+use crate::{regs, Error, Interface, Res, ER5517};
+use embedded_hal::digital::v2::OutputPin;
+
+/// Controller GPIO port, availability depends on the module's pin out
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GpioPort {
+    A,
+    /// shared with the key scan lines, input only
+    B,
+    /// GPIO-C7 doubles as the backlight PWM output
+    C,
+    D,
+    E,
+    F,
+}
+
+impl GpioPort {
+    //direction, 1 = input, and data registers
+    fn registers(self) -> (Option<u8>, u8) {
+        match self {
+            GpioPort::A => (Some(regs::GPIOAD), regs::GPIOA),
+            GpioPort::B => (None, regs::GPIOB),
+            GpioPort::C => (Some(regs::GPIOCD), regs::GPIOC),
+            GpioPort::D => (Some(regs::GPIODD), regs::GPIOD),
+            GpioPort::E => (Some(regs::GPIOED), regs::GPIOE),
+            GpioPort::F => (Some(regs::GPIOFD), regs::GPIOF),
+        }
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Set bits in `outputs` to output, the others to input.
+    pub fn gpio_set_direction(&mut self, port: GpioPort, outputs: u8) -> Res<(), PinErr, SPIErr> {
+        match port.registers() {
+            (Some(dir), _) => self.register_write(dir, !outputs),
+            (None, _) => Err(Error::InvalidParam),
+        }
+    }
+
+    pub fn gpio_write(&mut self, port: GpioPort, value: u8) -> Res<(), PinErr, SPIErr> {
+        match port.registers() {
+            (Some(_), data) => self.register_write(data, value),
+            (None, _) => Err(Error::InvalidParam),
+        }
+    }
+
+    pub fn gpio_read(&mut self, port: GpioPort) -> Res<u8, PinErr, SPIErr> {
+        if port == GpioPort::B {
+            //the pins only read as GPIO while the key scan is off
+            self.modify(regs::KSCR1, 0b1000_0000, 0)?;
+        }
+        let (_, data) = port.registers();
+        self.register_read_live(data)
+    }
+
+    /// Borrow `bit` of `port` as an output pin for other drivers, the display is unusable while
+    /// the pin is held.
+    pub fn gpio_pin(
+        &mut self,
+        port: GpioPort,
+        bit: u8,
+    ) -> Res<GpioPin<'_, SPI, CS>, PinErr, SPIErr> {
+        let dir = match port.registers() {
+            (Some(dir), _) if bit < 8 => dir,
+            _ => return Err(Error::InvalidParam),
+        };
+        self.modify(dir, 1 << bit, 0)?;
+        Ok(GpioPin {
+            driver: self,
+            port,
+            mask: 1 << bit,
+        })
+    }
+}
+
+/// A single controller GPIO output, from [`ER5517::gpio_pin`]
+pub struct GpioPin<'a, SPI, CS> {
+    driver: &'a mut ER5517<SPI, CS>,
+    port: GpioPort,
+    mask: u8,
+}
+
+impl<'a, SPI, CS, PinErr, SPIErr> OutputPin for GpioPin<'a, SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    type Error = Error<PinErr, SPIErr>;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let (_, data) = self.port.registers();
+        self.driver.modify(data, self.mask, 0)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let (_, data) = self.port.registers();
+        self.driver.modify(data, 0, self.mask)
+    }
+}
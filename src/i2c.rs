@@ -0,0 +1,125 @@
+//! I2C master, REG[E5h]-REG[EAh], and reading the touch controller behind it
+
+use crate::{regs, Error, Interface, Res, ER5517};
+
+//REG[E9h] command bits
+const CMD_START: u8 = 0b1000_0000;
+const CMD_STOP: u8 = 0b0100_0000;
+const CMD_READ: u8 = 0b0010_0000;
+const CMD_WRITE: u8 = 0b0001_0000;
+const CMD_NACK: u8 = 0b0000_1000;
+
+//REG[EAh] status bits
+const STATUS_NO_ACK: u8 = 0b1000_0000;
+const STATUS_TIP: u8 = 0b0000_0010;
+
+/// FT5x06 family touch controller address, the FT5316 on the ER-TFTMC043 touch panels
+pub const FT5X06_ADDR: u8 = 0x38;
+
+/// Maximum number of simultaneous touches reported by the FT5x06 family
+pub const MAX_TOUCH_POINTS: usize = 5;
+
+/// Touch positions in panel coordinates
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct TouchPoints {
+    len: u8,
+    points: [(u16, u16); MAX_TOUCH_POINTS],
+}
+
+impl TouchPoints {
+    pub fn points(&self) -> &[(u16, u16)] {
+        &self.points[..self.len as usize]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Set the I2C clock, SCL = core clock / (5 * (prescale + 1)).
+    pub fn i2c_init(&mut self, prescale: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u16(regs::I2CMCPR0, prescale)
+    }
+
+    /// Write `bytes` to the device at 7-bit address `addr`.
+    pub fn i2c_write(&mut self, addr: u8, bytes: &[u8]) -> Res<(), PinErr, SPIErr> {
+        if addr > 0x7f {
+            return Err(Error::InvalidParam);
+        }
+        let stop = if bytes.is_empty() { CMD_STOP } else { 0 };
+        self.i2c_transmit(addr << 1, CMD_START | stop)?;
+        for (i, b) in bytes.iter().enumerate() {
+            let stop = if i + 1 == bytes.len() { CMD_STOP } else { 0 };
+            self.i2c_transmit(*b, stop)?;
+        }
+        Ok(())
+    }
+
+    /// Fill `buf` from the device at 7-bit address `addr`.
+    pub fn i2c_read(&mut self, addr: u8, buf: &mut [u8]) -> Res<(), PinErr, SPIErr> {
+        if addr > 0x7f {
+            return Err(Error::InvalidParam);
+        }
+        let stop = if buf.is_empty() { CMD_STOP } else { 0 };
+        self.i2c_transmit((addr << 1) | 1, CMD_START | stop)?;
+        let len = buf.len();
+        for (i, b) in buf.iter_mut().enumerate() {
+            //the last byte is not acknowledged to end the read
+            let last = if i + 1 == len { CMD_NACK | CMD_STOP } else { 0 };
+            self.register_write(regs::I2CMCMDR, CMD_READ | last)?;
+            self.i2c_wait()?;
+            *b = self.register_read(regs::I2CMRXR)?;
+        }
+        Ok(())
+    }
+
+    /// Read the current touches from an FT5x06 family controller at [`FT5X06_ADDR`].
+    pub fn read_touch_points(&mut self) -> Res<TouchPoints, PinErr, SPIErr> {
+        //TD_STATUS followed by 6 bytes per point
+        let mut buf = [0u8; 1 + 6 * MAX_TOUCH_POINTS];
+        self.i2c_write(FT5X06_ADDR, &[0x02])?;
+        self.i2c_read(FT5X06_ADDR, &mut buf)?;
+        let mut t = TouchPoints {
+            len: (buf[0] & 0x0f).min(MAX_TOUCH_POINTS as u8),
+            ..Default::default()
+        };
+        for (i, p) in buf[1..].chunks(6).take(t.len as usize).enumerate() {
+            let x = ((p[0] as u16 & 0x0f) << 8) | p[1] as u16;
+            let y = ((p[2] as u16 & 0x0f) << 8) | p[3] as u16;
+            t.points[i] = (x, y);
+        }
+        Ok(t)
+    }
+
+    //send one byte with the WRITE command ORed with `cmd`, checking the acknowledge
+    fn i2c_transmit(&mut self, byte: u8, cmd: u8) -> Res<(), PinErr, SPIErr> {
+        self.register_write(regs::I2CMTXR, byte)?;
+        self.register_write(regs::I2CMCMDR, CMD_WRITE | cmd)?;
+        let status = self.i2c_wait()?;
+        if status & STATUS_NO_ACK != 0 {
+            //release the bus before reporting
+            if cmd & CMD_STOP == 0 {
+                self.register_write(regs::I2CMCMDR, CMD_STOP)?;
+                self.i2c_wait()?;
+            }
+            return Err(Error::I2cNack);
+        }
+        Ok(())
+    }
+
+    //wait for the transfer in progress flag to clear, returning the final status
+    fn i2c_wait(&mut self) -> Res<u8, PinErr, SPIErr> {
+        for _ in 0..self.busy_timeout() {
+            let s = self.register_read(regs::I2CMSTUR)?;
+            if s & STATUS_TIP == 0 {
+                return Ok(s);
+            }
+            self.poll_pause();
+        }
+        Err(Error::Timeout)
+    }
+}
@@ -0,0 +1,288 @@
+//! Resumable init, for schedulers that can't block for the whole start up
+
+use crate::{common, regs, Error, Interface, Res, ER5517};
+use core::fmt;
+
+/// What [`InitSequence::poll`] needs before it is called again
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum InitProgress {
+    /// call again once at least this many microseconds have passed
+    WaitUs(u32),
+    /// waiting on the controller, call again whenever convenient
+    Pending,
+    Done,
+}
+
+/// Part of init an [`InitError`] happened in
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum InitStage {
+    /// pulsing the reset pin and waiting for host access
+    Reset,
+    /// checking the panel config, waiting for host access and that a controller answers
+    Detect,
+    /// starting the PLL check loop on REG[01h]
+    TempCheck,
+    PllLock,
+    SdramReady,
+    /// bus, scan and panel timing registers
+    TimingConfig,
+    /// memory port, main and PIP window depths
+    ColorMode,
+    /// display and draw pages and the active window
+    WindowSetup,
+    /// turning the display on, the backlight is left as it is
+    DisplayOn,
+}
+
+impl fmt::Display for InitStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            InitStage::Reset => "reset",
+            InitStage::Detect => "detect",
+            InitStage::TempCheck => "PLL check",
+            InitStage::PllLock => "PLL lock",
+            InitStage::SdramReady => "SDRAM ready",
+            InitStage::TimingConfig => "timing config",
+            InitStage::ColorMode => "color mode",
+            InitStage::WindowSetup => "window setup",
+            InitStage::DisplayOn => "display on",
+        };
+        f.write_str(s)
+    }
+}
+
+/// An init error and the stage it happened in, converts into the plain [`Error`]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct InitError<P = (), S = ()> {
+    pub stage: InitStage,
+    pub error: Error<P, S>,
+}
+
+impl<P, S> InitError<P, S> {
+    //for map_err, tagging an error with `stage`
+    pub(crate) fn at(stage: InitStage) -> impl FnOnce(Error<P, S>) -> Self {
+        move |error| Self { stage, error }
+    }
+}
+
+impl<P, S> From<InitError<P, S>> for Error<P, S> {
+    fn from(e: InitError<P, S>) -> Self {
+        e.error
+    }
+}
+
+impl<P: fmt::Debug, S: fmt::Debug> fmt::Display for InitError<P, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "init failed at {}: {}", self.stage, self.error)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Stage {
+    Detect,
+    //REG[01h] bit 7 is set once the PLL has started, write it until it reads back set
+    TempStatus,
+    TempSelect,
+    TempRead,
+    TempReselect,
+    TempWrite,
+    Settle,
+    Pll,
+    PllStart,
+    Sdram,
+    SdramReady,
+    Configure,
+    Done,
+}
+
+/// Init split into short steps, from [`ER5517::start_init`].
+///
+/// Each waiting step is bounded by the poll config's `max_polls`, like the blocking waits, or
+/// 100000 polls if that is unlimited, so a dead controller fails with [`Error::Timeout`].
+#[derive(Copy, Clone, Debug)]
+pub struct InitSequence {
+    stage: Stage,
+    wait_us: u32,
+    polls: u32,
+}
+
+impl<SPI, CS> ER5517<SPI, CS> {
+    pub fn start_init(&self) -> InitSequence {
+        InitSequence {
+            stage: Stage::Detect,
+            wait_us: 0,
+            polls: 0,
+        }
+    }
+}
+
+impl Stage {
+    fn public(self) -> InitStage {
+        match self {
+            Stage::Detect => InitStage::Detect,
+            Stage::TempStatus
+            | Stage::TempSelect
+            | Stage::TempRead
+            | Stage::TempReselect
+            | Stage::TempWrite => InitStage::TempCheck,
+            Stage::Settle | Stage::Pll | Stage::PllStart => InitStage::PllLock,
+            Stage::Sdram | Stage::SdramReady => InitStage::SdramReady,
+            Stage::Configure => InitStage::TimingConfig,
+            Stage::Done => InitStage::DisplayOn,
+        }
+    }
+}
+
+impl InitSequence {
+    pub fn is_done(&self) -> bool {
+        self.stage == Stage::Done
+    }
+
+    /// Run the next step, `delay_elapsed_us` is the time since the previous call.
+    pub fn poll<SPI, CS, PinErr, SPIErr>(
+        &mut self,
+        display: &mut ER5517<SPI, CS>,
+        delay_elapsed_us: u32,
+    ) -> Result<InitProgress, InitError<PinErr, SPIErr>>
+    where
+        SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    {
+        let mut stage = self.stage.public();
+        self.step(display, delay_elapsed_us, &mut stage)
+            .map_err(InitError::at(stage))
+    }
+
+    //`stage` is moved along by the configure step
+    fn step<SPI, CS, PinErr, SPIErr>(
+        &mut self,
+        display: &mut ER5517<SPI, CS>,
+        delay_elapsed_us: u32,
+        stage: &mut InitStage,
+    ) -> Res<InitProgress, PinErr, SPIErr>
+    where
+        SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    {
+        let max_polls = match display.poll_config().max_polls {
+            u32::MAX => common::INIT_MAX_POLLS,
+            n => n,
+        };
+        if self.wait_us > delay_elapsed_us {
+            self.wait_us -= delay_elapsed_us;
+            return Ok(InitProgress::WaitUs(self.wait_us));
+        }
+        self.wait_us = 0;
+        match self.stage {
+            Stage::Detect => {
+                //fail before touching the clocks if the panel can't be configured
+                display.config().validate().map_err(Error::Config)?;
+                //writes are ignored until the inhibit bit clears, detect writes a scratch
+                //register and needs them to land
+                if display.status_read()? & common::STATUS_INHIBIT != 0 {
+                    return self.pending(max_polls);
+                }
+                if !display.chip_detect()? {
+                    return Err(Error::NotDetected);
+                }
+                self.next(Stage::TempStatus, 0)
+            }
+            Stage::TempStatus if !display.host_interface().readable => {
+                //the lock bit can't be read, start the PLL check once and give it the time
+                display.register_write(regs::CCR, 0x80)?;
+                self.next(Stage::Settle, common::PLL_LOCK_US)
+            }
+            Stage::TempStatus => {
+                if display.status_read()? & common::STATUS_INHIBIT == 0 {
+                    self.next(Stage::TempSelect, common::CHECK_STEP_US)
+                } else {
+                    self.pending(max_polls)
+                }
+            }
+            Stage::TempSelect => {
+                display.cmd_write(regs::CCR)?;
+                self.next(Stage::TempRead, common::CHECK_STEP_US)
+            }
+            Stage::TempRead => {
+                if display.data_read()? & 0x80 == 0x80 {
+                    self.next(Stage::Settle, common::SETTLE_US)
+                } else {
+                    self.next(Stage::TempReselect, common::CHECK_STEP_US)
+                }
+            }
+            Stage::TempReselect => {
+                display.cmd_write(regs::CCR)?;
+                self.next(Stage::TempWrite, common::CHECK_STEP_US)
+            }
+            Stage::TempWrite => {
+                display.data_write(0x80)?;
+                //counts as one pass of the check loop
+                self.stage = Stage::TempStatus;
+                self.pending(max_polls)
+            }
+            Stage::Settle => {
+                if display.status_read()? & common::STATUS_INHIBIT == 0 {
+                    self.next(Stage::Pll, 0)
+                } else {
+                    self.pending(max_polls)
+                }
+            }
+            Stage::Pll => {
+                display.register_write_all(&common::pll_registers(display.config()))?;
+                display.cmd_write(regs::SRR)?;
+                self.next(Stage::PllStart, common::PLL_START_US)
+            }
+            Stage::PllStart => {
+                display.data_write(0x80)?;
+                self.next(Stage::Sdram, common::PLL_START_US)
+            }
+            Stage::Sdram => {
+                for (reg, v) in common::PWM_INIT {
+                    display.register_write(reg, v)?;
+                }
+                for (reg, v) in common::SDRAM_INIT {
+                    display.register_write(reg, v)?;
+                }
+                self.next(Stage::SdramReady, 0)
+            }
+            Stage::SdramReady => {
+                if display.status_read()? & common::STATUS_SDRAM_READY != 0 {
+                    self.next(Stage::Configure, common::PLL_START_US)
+                } else {
+                    self.pending(max_polls)
+                }
+            }
+            Stage::Configure => {
+                display.init_configure(stage)?;
+                self.next(Stage::Done, 0)
+            }
+            Stage::Done => Ok(InitProgress::Done),
+        }
+    }
+
+    fn next<P, S>(&mut self, stage: Stage, wait_us: u32) -> Res<InitProgress, P, S> {
+        self.stage = stage;
+        //the check loop's passes count against one limit
+        if !matches!(
+            stage,
+            Stage::TempSelect | Stage::TempRead | Stage::TempReselect | Stage::TempWrite
+        ) {
+            self.polls = 0;
+        }
+        if stage == Stage::Done {
+            Ok(InitProgress::Done)
+        } else if wait_us > 0 {
+            self.wait_us = wait_us;
+            Ok(InitProgress::WaitUs(wait_us))
+        } else {
+            Ok(InitProgress::Pending)
+        }
+    }
+
+    fn pending<P, S>(&mut self, max_polls: u32) -> Res<InitProgress, P, S> {
+        self.polls += 1;
+        if self.polls >= max_polls {
+            Err(Error::Timeout)
+        } else {
+            Ok(InitProgress::Pending)
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! SPI transports for the controller
+
+use crate::{Error, Res};
+use embedded_hal::{
+    blocking::spi::{Transfer as SPITransfer, Write as SPIWrite},
+    digital::v2::OutputPin,
+};
+
+/// A transport able to frame a single SPI transaction to the controller.
+///
+/// `CS` is the chip select the transport needs, [`NoCs`] for transports that manage it themselves.
+pub trait Interface<CS> {
+    type PinError;
+    type SpiError;
+
+    fn write(&mut self, cs: &mut CS, bytes: &[u8]) -> Res<(), Self::PinError, Self::SpiError>;
+
+    fn transfer<'w>(
+        &mut self,
+        cs: &mut CS,
+        bytes: &'w mut [u8],
+    ) -> Res<&'w [u8], Self::PinError, Self::SpiError>;
+
+    /// Write `prefix` followed by all of `bytes` in one transaction.
+    ///
+    /// The default falls back to one transaction per byte.
+    fn write_burst(
+        &mut self,
+        cs: &mut CS,
+        prefix: u8,
+        bytes: &[u8],
+    ) -> Res<(), Self::PinError, Self::SpiError> {
+        for b in bytes {
+            self.write(cs, &[prefix, *b])?;
+        }
+        Ok(())
+    }
+
+    /// Write `prefix` and read `buf.len()` bytes after it in one transaction.
+    ///
+    /// The response follows the prefix directly, there is no dummy byte. The default falls back
+    /// to one transaction per byte.
+    fn read_burst(
+        &mut self,
+        cs: &mut CS,
+        prefix: u8,
+        buf: &mut [u8],
+    ) -> Res<(), Self::PinError, Self::SpiError> {
+        for b in buf.iter_mut() {
+            let mut d = [prefix, 0];
+            *b = self.transfer(cs, &mut d)?[1];
+        }
+        Ok(())
+    }
+}
+
+/// Placeholder chip select for transports that handle it themselves, like an embedded-hal 1.0 `SpiDevice`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NoCs;
+
+/// Placeholder for a driver without a reset pin.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NoPin;
+
+/// Controller reset line, implemented for every `OutputPin` with the chip select's error type.
+pub trait ResetPin<E> {
+    /// Drive the reset active (low) or release it, false if there is no pin.
+    fn set_reset(&mut self, active: bool) -> Result<bool, E>;
+}
+
+impl<E> ResetPin<E> for NoPin {
+    fn set_reset(&mut self, _active: bool) -> Result<bool, E> {
+        Ok(false)
+    }
+}
+
+impl<P, E> ResetPin<E> for P
+where
+    P: OutputPin<Error = E>,
+{
+    fn set_reset(&mut self, active: bool) -> Result<bool, E> {
+        if active {
+            self.set_low()?;
+        } else {
+            self.set_high()?;
+        }
+        Ok(true)
+    }
+}
+
+//chip select held low while alive, released on drop if `release` wasn't reached
+struct Selected<'a, CS: OutputPin>(Option<&'a mut CS>);
+
+impl<CS: OutputPin> Selected<'_, CS> {
+    fn release(mut self) -> Result<(), CS::Error> {
+        match self.0.take() {
+            Some(cs) => cs.set_high(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<CS: OutputPin> Drop for Selected<'_, CS> {
+    fn drop(&mut self) {
+        if let Some(cs) = self.0.take() {
+            let _ = cs.set_high();
+        }
+    }
+}
+
+//run `f` with the chip select low, it is released on every path and both errors are kept
+fn with_select<CS: OutputPin, T, E, F: FnOnce() -> Result<T, E>>(
+    cs: &mut CS,
+    f: F,
+) -> Res<T, CS::Error, E> {
+    cs.set_low().map_err(Error::Pin)?;
+    let selected = Selected(Some(cs));
+    let r = f();
+    match (r, selected.release()) {
+        (Ok(v), Ok(())) => Ok(v),
+        (Ok(_), Err(pin)) => Err(Error::Pin(pin)),
+        (Err(spi), Ok(())) => Err(Error::SPI(spi)),
+        (Err(spi), Err(pin)) => Err(Error::PinAfterSpi { pin, spi }),
+    }
+}
+
+/// embedded-hal 0.2 blocking SPI with a separate chip select pin
+impl<SPI, CS, PinErr, SPIErr> Interface<CS> for SPI
+where
+    SPI: SPIWrite<u8, Error = SPIErr> + SPITransfer<u8, Error = SPIErr>,
+    CS: OutputPin<Error = PinErr>,
+{
+    type PinError = PinErr;
+    type SpiError = SPIErr;
+
+    fn write(&mut self, cs: &mut CS, bytes: &[u8]) -> Res<(), PinErr, SPIErr> {
+        with_select(cs, || SPIWrite::write(self, bytes))
+    }
+
+    fn transfer<'w>(&mut self, cs: &mut CS, bytes: &'w mut [u8]) -> Res<&'w [u8], PinErr, SPIErr> {
+        with_select(cs, || SPITransfer::transfer(self, bytes))
+    }
+
+    fn write_burst(&mut self, cs: &mut CS, prefix: u8, bytes: &[u8]) -> Res<(), PinErr, SPIErr> {
+        with_select(cs, || {
+            SPIWrite::write(self, &[prefix])?;
+            SPIWrite::write(self, bytes)
+        })
+    }
+
+    fn read_burst(&mut self, cs: &mut CS, prefix: u8, buf: &mut [u8]) -> Res<(), PinErr, SPIErr> {
+        buf.fill(0);
+        with_select(cs, || {
+            SPIWrite::write(self, &[prefix])?;
+            SPITransfer::transfer(self, buf).map(|_| ())
+        })
+    }
+}
+
+/// embedded-hal 1.0 `SpiDevice`, chip select is handled by the device
+#[cfg(feature = "eh1")]
+impl<D> Interface<NoCs> for D
+where
+    D: embedded_hal_1::spi::SpiDevice<u8>,
+{
+    type PinError = core::convert::Infallible;
+    type SpiError = D::Error;
+
+    fn write(&mut self, _cs: &mut NoCs, bytes: &[u8]) -> Res<(), Self::PinError, D::Error> {
+        embedded_hal_1::spi::SpiDevice::write(self, bytes).map_err(Error::SPI)
+    }
+
+    fn transfer<'w>(
+        &mut self,
+        _cs: &mut NoCs,
+        bytes: &'w mut [u8],
+    ) -> Res<&'w [u8], Self::PinError, D::Error> {
+        embedded_hal_1::spi::SpiDevice::transfer_in_place(self, bytes).map_err(Error::SPI)?;
+        Ok(bytes)
+    }
+
+    fn write_burst(
+        &mut self,
+        _cs: &mut NoCs,
+        prefix: u8,
+        bytes: &[u8],
+    ) -> Res<(), Self::PinError, D::Error> {
+        use embedded_hal_1::spi::Operation;
+        embedded_hal_1::spi::SpiDevice::transaction(
+            self,
+            &mut [Operation::Write(&[prefix]), Operation::Write(bytes)],
+        )
+        .map_err(Error::SPI)
+    }
+
+    fn read_burst(
+        &mut self,
+        _cs: &mut NoCs,
+        prefix: u8,
+        buf: &mut [u8],
+    ) -> Res<(), Self::PinError, D::Error> {
+        use embedded_hal_1::spi::Operation;
+        embedded_hal_1::spi::SpiDevice::transaction(
+            self,
+            &mut [Operation::Write(&[prefix]), Operation::Read(buf)],
+        )
+        .map_err(Error::SPI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{panic, vec::Vec};
+
+    //records its levels, failing the `fail`th operation counting from 1
+    #[derive(Default)]
+    struct Pin {
+        ops: Vec<bool>,
+        fail: usize,
+    }
+
+    impl Pin {
+        fn failing(fail: usize) -> Self {
+            Self {
+                fail,
+                ..Self::default()
+            }
+        }
+
+        fn set(&mut self, high: bool) -> Result<(), &'static str> {
+            self.ops.push(high);
+            if self.ops.len() == self.fail {
+                Err("pin")
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl OutputPin for Pin {
+        type Error = &'static str;
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.set(false)
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.set(true)
+        }
+    }
+
+    //fails the `fail`th write counting from 1
+    struct Spi {
+        writes: usize,
+        fail: usize,
+    }
+
+    impl SPIWrite<u8> for Spi {
+        type Error = u8;
+
+        fn write(&mut self, _: &[u8]) -> Result<(), u8> {
+            self.writes += 1;
+            if self.writes == self.fail {
+                Err(0xee)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl SPITransfer<u8> for Spi {
+        type Error = u8;
+
+        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], u8> {
+            Ok(words)
+        }
+    }
+
+    #[test]
+    fn errors_compose() {
+        let mut cs = Pin::default();
+        assert_eq!(with_select(&mut cs, || Ok::<_, u8>(1)), Ok(1));
+        assert_eq!(cs.ops, [false, true]);
+
+        let mut cs = Pin::default();
+        assert_eq!(with_select(&mut cs, || Err::<(), _>(7u8)), Err(Error::SPI(7)));
+        assert_eq!(cs.ops, [false, true]);
+
+        let mut cs = Pin::failing(2);
+        assert_eq!(
+            with_select(&mut cs, || Err::<(), _>(7u8)),
+            Err(Error::PinAfterSpi { pin: "pin", spi: 7 })
+        );
+        assert_eq!(cs.ops, [false, true]);
+
+        let mut cs = Pin::failing(2);
+        assert_eq!(with_select(&mut cs, || Ok::<_, u8>(1)), Err(Error::Pin("pin")));
+
+        //never selected, so the transfer doesn't run
+        let mut cs = Pin::failing(1);
+        let mut ran = false;
+        let r = with_select(&mut cs, || {
+            ran = true;
+            Ok::<_, u8>(())
+        });
+        assert_eq!(r, Err(Error::Pin("pin")));
+        assert!(!ran);
+        assert_eq!(cs.ops, [false]);
+    }
+
+    #[test]
+    fn released_on_unwind() {
+        let mut cs = Pin::default();
+        let r = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _ = with_select(&mut cs, || -> Result<(), u8> { panic!("transfer") });
+        }));
+        assert!(r.is_err());
+        assert_eq!(cs.ops, [false, true]);
+    }
+
+    #[test]
+    fn burst_failing_midway() {
+        //the prefix goes out, the data write fails
+        let mut spi = Spi { writes: 0, fail: 2 };
+        let mut cs = Pin::failing(4);
+        assert_eq!(
+            spi.write_burst(&mut cs, 0x80, &[1, 2, 3]),
+            Err(Error::SPI(0xee))
+        );
+        assert_eq!(cs.ops, [false, true]);
+        //the next transaction selects again and its release failing is reported alone
+        assert_eq!(
+            spi.write_burst(&mut cs, 0x80, &[1, 2, 3]),
+            Err(Error::Pin("pin"))
+        );
+        assert_eq!(cs.ops, [false, true, false, true]);
+    }
+}
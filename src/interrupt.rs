@@ -0,0 +1,112 @@
+//! Controller interrupts, REG[0Bh]-REG[0Dh], the enabled events drive the XnINTR pin
+
+use core::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+use crate::{regs, Interface, Res, ER5517};
+
+/// Set of interrupt events, the same bit layout is used by the enable, flag and mask registers.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct InterruptMask(u8);
+
+impl InterruptMask {
+    pub const PWM0: Self = Self(0b0000_0001);
+    pub const PWM1: Self = Self(0b0000_0010);
+    /// BTE, DMA and shape drawing completion share one event
+    pub const DRAW_DONE: Self = Self(0b0000_0100);
+    pub const BTE: Self = Self::DRAW_DONE;
+    pub const DMA: Self = Self::DRAW_DONE;
+    pub const KEYSCAN: Self = Self(0b0000_1000);
+    pub const VSYNC: Self = Self(0b0001_0000);
+    pub const I2C: Self = Self(0b0010_0000);
+    /// external interrupt input pin
+    pub const EXTERNAL: Self = Self(0b0100_0000);
+    /// wake up from power saving
+    pub const WAKEUP: Self = Self(0b1000_0000);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn all() -> Self {
+        Self(0xff)
+    }
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// True if any event in `other` is set.
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for InterruptMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for InterruptMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for InterruptMask {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Not for InterruptMask {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Let the events in `mask` assert XnINTR, the others keep their setting.
+    pub fn enable_interrupts(&mut self, mask: InterruptMask) -> Res<(), PinErr, SPIErr> {
+        self.interrupt_enable(mask.bits(), true)
+    }
+
+    pub fn disable_interrupts(&mut self, mask: InterruptMask) -> Res<(), PinErr, SPIErr> {
+        self.interrupt_enable(mask.bits(), false)
+    }
+
+    pub fn enabled_interrupts(&mut self) -> Res<InterruptMask, PinErr, SPIErr> {
+        Ok(InterruptMask(self.register_read(regs::INTEN)?))
+    }
+
+    /// Events that occurred since they were last cleared, enabled or not.
+    pub fn pending_interrupts(&mut self) -> Res<InterruptMask, PinErr, SPIErr> {
+        Ok(InterruptMask(self.interrupt_flags()?))
+    }
+
+    /// Clear the events in `mask`, XnINTR is released once no enabled event is pending.
+    pub fn clear_interrupts(&mut self, mask: InterruptMask) -> Res<(), PinErr, SPIErr> {
+        self.interrupt_clear(mask.bits())
+    }
+}
@@ -0,0 +1,100 @@
+//! Key scan engine, REG[FBh]-REG[FFh]
+
+use crate::{regs, Error, Interface, InterruptMask, Res, ER5517};
+
+const INT_KEYSCAN: u8 = InterruptMask::KEYSCAN.bits();
+
+/// Frames a key has to be stable before it registers
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KeyDebounce {
+    Frames4,
+    Frames8,
+    Frames16,
+    Frames32,
+}
+
+/// Up to three keys registered by the last scan
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct KeyScanResult {
+    len: u8,
+    codes: [u8; 3],
+}
+
+impl KeyScanResult {
+    /// Key codes, the row in the high nibble and the column in the low nibble, long presses
+    /// have bit 7 set.
+    pub fn codes(&self) -> &[u8] {
+        &self.codes[..self.len as usize]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Start scanning a `rows` x `cols` matrix on KIN/KOUT, at most 5 x 4.
+    ///
+    /// The engine always scans the whole matrix, lines without keys never report a press.
+    pub fn keyscan_enable(
+        &mut self,
+        rows: u8,
+        cols: u8,
+        long_press: bool,
+    ) -> Res<(), PinErr, SPIErr> {
+        if rows == 0 || rows > 5 || cols == 0 || cols > 4 {
+            return Err(Error::InvalidParam);
+        }
+        let long = if long_press { 0b0100_0000 } else { 0 };
+        self.modify(regs::KSCR1, 0b1100_0000, 0b1000_0000 | long)?;
+        self.interrupt_clear(INT_KEYSCAN)?;
+        self.interrupt_enable(INT_KEYSCAN, true)
+    }
+
+    pub fn keyscan_disable(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.interrupt_enable(INT_KEYSCAN, false)?;
+        self.modify(regs::KSCR1, 0b1000_0000, 0)
+    }
+
+    /// Debounce and the long press time, 0..=7 in steps of the scan period.
+    pub fn set_keyscan_timing(
+        &mut self,
+        debounce: KeyDebounce,
+        long_press_time: u8,
+    ) -> Res<(), PinErr, SPIErr> {
+        if long_press_time > 7 {
+            return Err(Error::InvalidParam);
+        }
+        let d = match debounce {
+            KeyDebounce::Frames4 => 0b00,
+            KeyDebounce::Frames8 => 0b01,
+            KeyDebounce::Frames16 => 0b10,
+            KeyDebounce::Frames32 => 0b11,
+        };
+        self.modify(regs::KSCR1, 0b0011_0000, d << 4)?;
+        self.modify(regs::KSCR2, 0b0001_1100, long_press_time << 2)
+    }
+
+    /// The keys currently registered, simultaneous presses each get their own code.
+    pub fn keyscan_read(&mut self) -> Res<KeyScanResult, PinErr, SPIErr> {
+        let mut r = KeyScanResult {
+            len: self.register_read_live(regs::KSCR2)? & 0b11,
+            ..Default::default()
+        };
+        for i in 0..r.len as usize {
+            r.codes[i] = self.register_read(regs::KSDR0 + i as u8)?;
+        }
+        Ok(r)
+    }
+
+    pub fn keyscan_interrupt_pending(&mut self) -> Res<bool, PinErr, SPIErr> {
+        Ok(self.interrupt_flags()? & INT_KEYSCAN != 0)
+    }
+
+    pub fn keyscan_clear_interrupt(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.interrupt_clear(INT_KEYSCAN)
+    }
+}
@@ -1,656 +1,1275 @@
 #![no_std]
-
-use embedded_hal::{
-    blocking::{
-        delay::DelayMs,
-        spi::{Transfer as SPITransfer, Write as SPIWrite},
-    },
-    digital::v2::OutputPin,
+//with `panic-audit`, `cargo clippy` rejects what could panic in this file and the modules
+//outside of `unaudited!`, tests aside
+#![cfg_attr(
+    all(feature = "panic-audit", not(test)),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::indexing_slicing,
+        clippy::arithmetic_side_effects
+    )
+)]
+
+#[cfg(any(test, feature = "testing"))]
+extern crate std;
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
 };
-
-use core::convert::TryInto;
 use embedded_graphics_core::{
     pixelcolor::{Rgb565, Rgb888},
     prelude::*,
     primitives::Rectangle,
 };
-
-const LCD_HBPD: u16 = 140;
-const LCD_HFPD: u16 = 160;
-const LCD_HSPW: u16 = 20;
-
-const LCD_VBPD: u16 = 20;
-const LCD_VFPD: u16 = 12;
-const LCD_VSPW: u16 = 3;
-
-const HEIGHT: u32 = 272;
-const WIDTH: u32 = 480;
-
-#[derive(Copy, Clone, PartialEq, Eq)]
-pub enum ColorMode {
-    EightBit,
-    SixteenBit,
-    TwentyFourBit,
-}
-
-/*
-fn color_mode(mode: ColorMode, mut r: u8, mut g: u8, mut b: u8) -> (u8, u8, u8) {
-    match mode {
-        ColorMode::EightBit => {
-            r = r.clamp(0, 0b0000_0111) << 5;
-            g = g.clamp(0, 0b0000_0111) << 5;
-            b = b.clamp(0, 0b0000_0011) << 6;
-        }
-        ColorMode::SixteenBit => {
-            r = r.clamp(0, 0b0001_1111) << 3;
-            g = g.clamp(0, 0b0011_1111) << 2;
-            b = b.clamp(0, 0b0001_1111) << 3;
-        }
-        _ => (),
+use embedded_hal::blocking::delay::DelayUs;
+
+//modules outside of the drawing paths, left out of the `panic-audit` lints
+macro_rules! unaudited {
+    ($($(#[$m:meta])* $v:vis mod $name:ident;)*) => {
+        $(
+            $(#[$m])*
+            #[cfg_attr(
+                feature = "panic-audit",
+                allow(
+                    clippy::unwrap_used,
+                    clippy::expect_used,
+                    clippy::indexing_slicing,
+                    clippy::arithmetic_side_effects
+                )
+            )]
+            $v mod $name;
+        )*
     };
-    (r, g, b)
 }
-*/
 
-pub enum Error<P = (), S = ()> {
-    Pin(P),
-    SPI(S),
-}
-
-pub struct TFTMC043<SPI, CS> {
-    color_mode: ColorMode,
-    spi: SPI,
-    cs: CS, //chip select
-}
-
-/// NewType struct to implement DrawTarget
-pub struct TFTMC043Draw16Bit<SPI, CS> {
-    inner: TFTMC043<SPI, CS>,
+mod bte;
+#[cfg(feature = "buffered")]
+pub mod buffered;
+pub mod color;
+mod common;
+mod cursor;
+pub mod er5517;
+pub mod font;
+pub mod glyph_cache;
+pub mod mono;
+pub mod offscreen;
+pub mod pip;
+pub mod terminal;
+pub mod test_pattern;
+
+unaudited! {
+    #[cfg(feature = "async")]
+    pub mod asynch;
+    pub mod builder;
+    pub mod capabilities;
+    #[cfg(feature = "debug-strip")]
+    pub mod debug_strip;
+    pub mod delay;
+    pub mod flash;
+    pub mod gpio;
+    pub mod i2c;
+    pub mod init;
+    pub mod interface;
+    pub mod interrupt;
+    pub mod keyscan;
+    #[cfg(feature = "metrics")]
+    pub mod metrics;
+    pub mod panel;
+    pub mod power;
+    pub mod prelude;
+    pub mod pwm;
+    pub mod regs;
+    pub mod self_test;
+    #[cfg(any(test, feature = "testing"))]
+    pub mod testing;
+    #[cfg(feature = "trace")]
+    pub mod trace;
+    pub mod transaction;
 }
 
-/// NewType struct to implement DrawTarget
-pub struct TFTMC043Draw24Bit<SPI, CS> {
-    inner: TFTMC043<SPI, CS>,
+pub use bte::RopCode;
+#[cfg(feature = "buffered")]
+pub use buffered::BufferedDisplay;
+pub use builder::TFTMC043Builder;
+pub use capabilities::{capabilities, Capabilities};
+pub use color::Rgb332;
+#[cfg(feature = "debug-strip")]
+pub use debug_strip::{DebugStrip, DebugWriter};
+pub use delay::DelayMsShim;
+pub use er5517::{
+    ColorMode, DriverState, FontSize, HostInterface, Orientation, PollConfig, Quadrant, SpiTiming,
+    StatusFlags, WriteDirection, ER5517,
+};
+pub use flash::{FlashSelect, ImageInfo};
+pub use font::{FontEncoding, FontRom, FontWidth};
+pub use glyph_cache::GlyphCache;
+pub use gpio::GpioPort;
+pub use i2c::TouchPoints;
+pub use init::{InitError, InitProgress, InitSequence, InitStage};
+pub use interface::{Interface, NoCs, NoPin, ResetPin};
+pub use interrupt::InterruptMask;
+pub use keyscan::{KeyDebounce, KeyScanResult};
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+pub use offscreen::OffscreenCanvas;
+pub use panel::{Ertftmc050Panel, PanelTiming, Tftmc043Panel};
+pub use pip::PipWindow;
+pub use power::PowerMode;
+pub use pwm::PwmClockDivider;
+pub use self_test::SelfTestReport;
+pub use terminal::{Terminal, TerminalOverflow};
+pub use test_pattern::TestPattern;
+#[cfg(feature = "trace")]
+pub use trace::{Dir, RingTraceSink, TraceSink};
+pub use transaction::Transaction;
+
+/// Panel geometry and timing
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PanelConfig {
+    /// 8 to 2048, init fails with [`Error::InvalidParam`] otherwise
+    pub width: u16,
+    pub height: u16,
+    /// horizontal back porch, 8 to 2048
+    pub hbpd: u16,
+    /// horizontal front porch
+    pub hfpd: u16,
+    /// hsync pulse width
+    pub hspw: u16,
+    /// vertical back porch
+    pub vbpd: u16,
+    /// vertical front porch
+    pub vfpd: u16,
+    /// vsync pulse width
+    pub vspw: u16,
+    /// pixel clock in kHz
+    pub pclk_khz: u32,
 }
 
-type Res<T, P, S> = Result<T, Error<P, S>>;
-
-impl<SPI, CS, PinErr, SPIErr> TFTMC043<SPI, CS>
-where
-    SPI: SPIWrite<u8, Error = SPIErr> + SPITransfer<u8, Error = SPIErr>,
-    CS: OutputPin<Error = PinErr>,
-{
-    fn with_select<T, F: FnOnce(&mut SPI) -> T>(&mut self, f: F) -> Res<T, PinErr, SPIErr> {
-        self.cs.set_low().map_err(Error::Pin)?;
-        let r = f(&mut self.spi);
-        self.cs.set_high().map_err(Error::Pin)?;
-        Ok(r)
-    }
-
-    fn write(&mut self, bytes: &[u8]) -> Res<(), PinErr, SPIErr> {
-        let r = self.with_select(|spi| spi.write(bytes))?;
-        r.map_err(Error::SPI)
-    }
-
-    fn read<'w>(&mut self, bytes: &'w mut [u8]) -> Res<&'w [u8], PinErr, SPIErr> {
-        let r = self.with_select(|spi| spi.transfer(bytes))?;
-        r.map_err(Error::SPI)
-    }
-
-    fn cmd_write(&mut self, cmd: u8) -> Res<(), PinErr, SPIErr> {
-        self.write(&[0, cmd])
-    }
+impl PanelConfig {
+    /// ER-TFTMC043-3, 4.3" 480x272
+    pub const ER_TFTMC043: Self = Self {
+        width: 480,
+        height: 272,
+        hbpd: 140,
+        hfpd: 160,
+        hspw: 20,
+        vbpd: 20,
+        vfpd: 12,
+        vspw: 3,
+        pclk_khz: 7_500,
+    };
 
-    fn data_write(&mut self, data: u8) -> Res<(), PinErr, SPIErr> {
-        self.write(&[0x80, data])
-    }
+    /// ER-TFTMC050-3, 5.0" 800x480
+    pub const ER_TFTMC050: Self = Self {
+        width: 800,
+        height: 480,
+        hbpd: 140,
+        hfpd: 160,
+        hspw: 20,
+        vbpd: 20,
+        vfpd: 12,
+        vspw: 3,
+        pclk_khz: 32_500,
+    };
 
-    pub fn new(spi: SPI, cs: CS, color_mode: ColorMode) -> Self {
-        Self {
-            spi,
-            cs,
-            color_mode,
+    /// Check every field fits its timing register, init does the same.
+    pub const fn validate(&self) -> Result<(), ConfigError> {
+        //the horizontal values are written in 8 pixel units minus one, to a byte
+        const fn h(v: u16) -> bool {
+            v >= 8 && v <= 2048
+        }
+        if !h(self.width) {
+            Err(ConfigError::Width)
+        } else if self.height == 0 || self.height > 2048 {
+            Err(ConfigError::Height)
+        } else if !h(self.hbpd) {
+            Err(ConfigError::Hbpd)
+        } else if !h(self.hfpd) {
+            Err(ConfigError::Hfpd)
+        } else if !h(self.hspw) {
+            Err(ConfigError::Hspw)
+        } else if self.vbpd == 0 || self.vbpd > 1024 {
+            Err(ConfigError::Vbpd)
+        } else if self.vfpd == 0 || self.vfpd > 256 {
+            Err(ConfigError::Vfpd)
+        } else if self.vspw == 0 || self.vspw > 128 {
+            Err(ConfigError::Vspw)
+        } else if self.pclk_khz < 500 || self.pclk_khz > 127_500 {
+            Err(ConfigError::PixelClock)
+        } else {
+            Ok(())
         }
     }
 
-    pub fn status_read(&mut self) -> Res<u8, PinErr, SPIErr> {
-        let mut d: [u8; 2] = [0x40, 0x00];
-        let v = self.read(&mut d)?;
-        Ok(v[1])
-    }
-
-    pub fn data_read(&mut self) -> Res<u8, PinErr, SPIErr> {
-        let mut d: [u8; 2] = [0xc0, 0x00];
-        let v = self.read(&mut d)?;
-        Ok(v[1])
+    //sclk = 10MHz xtal / R(5) * N / 2^OD(2), so N = pclk / 500kHz
+    pub(crate) fn sclk_n(&self) -> u8 {
+        (self.pclk_khz.saturating_add(250) / 500).clamp(1, u8::MAX as u32) as u8
     }
+}
 
-    pub fn register_write(&mut self, cmd: u8, data: u8) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(cmd)?;
-        self.data_write(data)
+impl Default for PanelConfig {
+    fn default() -> Self {
+        Self::ER_TFTMC043
     }
+}
 
-    pub fn color_bars(&mut self, on: bool) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x12)?;
-        let mask = 0b0010_0000;
-
-        let mut s = self.data_read()?;
-        s = if on { s | mask } else { s & !mask };
+/// The [`PanelConfig`] field the timing registers can't hold, from [`PanelConfig::validate`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// 8 to 2048, REG[14h] holds width / 8 - 1
+    Width,
+    /// 1 to 2048, REG[1Ah]-REG[1Bh] hold 11 bits of height - 1
+    Height,
+    /// 8 to 2048, in 8 pixel units like the width
+    Hbpd,
+    /// 8 to 2048
+    Hfpd,
+    /// 8 to 2048
+    Hspw,
+    /// 1 to 1024, REG[1Ch]-REG[1Dh] hold 10 bits of vbpd - 1
+    Vbpd,
+    /// 1 to 256
+    Vfpd,
+    /// 1 to 128, REG[1Fh] holds 7 bits of vspw - 1
+    Vspw,
+    /// 500 to 127500 kHz, the PLL multiplier is a byte of 500 kHz steps
+    PixelClock,
+}
 
-        self.data_write(s)
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (field, range) = match self {
+            ConfigError::Width => ("width", "8 to 2048"),
+            ConfigError::Height => ("height", "1 to 2048"),
+            ConfigError::Hbpd => ("horizontal back porch", "8 to 2048"),
+            ConfigError::Hfpd => ("horizontal front porch", "8 to 2048"),
+            ConfigError::Hspw => ("hsync pulse width", "8 to 2048"),
+            ConfigError::Vbpd => ("vertical back porch", "1 to 1024"),
+            ConfigError::Vfpd => ("vertical front porch", "1 to 256"),
+            ConfigError::Vspw => ("vsync pulse width", "1 to 128"),
+            ConfigError::PixelClock => ("pixel clock", "500 to 127500 kHz"),
+        };
+        write!(f, "panel {} out of range, {}", field, range)
     }
+}
 
-    pub fn on(&mut self, on: bool) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x12)?;
-        let mask = 0b0100_0000u8;
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error<P = (), S = ()> {
+    Pin(P),
+    SPI(S),
+    /// a transfer failed and releasing the chip select afterwards did too
+    PinAfterSpi {
+        pin: P,
+        spi: S,
+    },
+    /// a parameter was outside of the range the controller supports
+    InvalidParam,
+    /// the controller did not become ready in time
+    Timeout,
+    /// the operation is only available in text mode
+    TextModeRequired,
+    /// the controller is in a power saving mode, see [`ER5517::wake`]
+    PowerSaving,
+    /// no controller answered, see [`ER5517::chip_detect`]
+    NotDetected,
+    /// the color mode is fixed while wrapped in a fixed depth draw target
+    ColorModeLocked,
+    /// an I2C device did not acknowledge
+    I2cNack,
+    /// a position or size is outside of the panel or memory
+    OutOfBounds,
+    /// a register read back differently after writing it, see [`ER5517::set_verify_writes`]
+    VerifyFailed {
+        reg: u8,
+        wrote: u8,
+        read: u8,
+    },
+    /// the host interface can't read, see [`HostInterface::SPI_3WIRE_WRITE_ONLY`]
+    ReadUnavailable,
+    /// the row source of [`ER5517::blit_rows`] failed
+    RowSource,
+    /// the panel timing can't be configured, see [`PanelConfig::validate`]
+    Config(ConfigError),
+    /// a flash image table entry doesn't describe an image that fits, see
+    /// [`ER5517::load_image_from_flash`]
+    BadImageHeader,
+}
 
-        let mut s = self.data_read()?;
-        s = if on { s | mask } else { s & !mask };
-        self.data_write(s)
+impl<P, S> Error<P, S> {
+    pub fn pin_err(&self) -> Option<&P> {
+        match self {
+            Error::Pin(e) | Error::PinAfterSpi { pin: e, .. } => Some(e),
+            _ => None,
+        }
     }
 
-    fn select_main_window_color_mode(&mut self, mode: ColorMode) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x10)?;
-        let v = (self.data_read()? & !0b1100)
-            | match mode {
-                ColorMode::EightBit => 0b0000,
-                ColorMode::SixteenBit => 0b0100,
-                ColorMode::TwentyFourBit => 0b1000,
-            };
-        self.data_write(v)
+    pub fn spi_err(&self) -> Option<&S> {
+        match self {
+            Error::SPI(e) | Error::PinAfterSpi { spi: e, .. } => Some(e),
+            _ => None,
+        }
     }
+}
 
-    pub fn init(&mut self, delay: &mut dyn DelayMs<u16>) -> Res<(), PinErr, SPIErr> {
-        self.system_check_temp(delay)?;
-        delay.delay_ms(100);
-        while self.status_read()? & 0x02 != 0 {
-            //loop
+impl<P: fmt::Debug, S: fmt::Debug> fmt::Display for Error<P, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Pin(e) => write!(f, "pin error: {:?}", e),
+            Error::SPI(e) => write!(f, "SPI error: {:?}", e),
+            Error::PinAfterSpi { pin, spi } => {
+                write!(f, "SPI error: {:?}, then pin error: {:?}", spi, pin)
+            }
+            Error::InvalidParam => write!(f, "invalid parameter"),
+            Error::Timeout => write!(f, "timed out waiting for the controller"),
+            Error::TextModeRequired => write!(f, "operation requires text mode"),
+            Error::PowerSaving => write!(f, "controller is in a power saving mode"),
+            Error::NotDetected => write!(f, "display controller not detected"),
+            Error::ColorModeLocked => write!(f, "color mode is fixed by the draw target"),
+            Error::I2cNack => write!(f, "I2C device did not acknowledge"),
+            Error::OutOfBounds => write!(f, "position or size out of bounds"),
+            Error::BadImageHeader => write!(f, "invalid flash image header"),
+            Error::Config(e) => write!(f, "{}", e),
+            Error::ReadUnavailable => write!(f, "host interface can't read"),
+            Error::RowSource => write!(f, "row source failed"),
+            Error::VerifyFailed { reg, wrote, read } => write!(
+                f,
+                "register {:#04x} read back {:#04x} after writing {:#04x}",
+                reg, read, wrote
+            ),
         }
+    }
+}
 
-        self.pll_init(delay)?;
-        self.sdram_init(delay)?;
-
-        self.tft_16bit()?;
-        self.host_16bit()?;
-
-        self.rgb_16bit_16bpp()?;
-        self.memwrite_left_right_top_down()?;
-
-        self.graphic_mode()?;
-        self.mem_select_sdram()?;
+/// ER-TFTMC043 panel driver, a thin wrapper supplying the panel timing to the [`ER5517`] controller
+///
+/// `P` names the panel for [`TFTMC043::new_for_panel`], the other constructors use
+/// [`Tftmc043Panel`].
+pub struct TFTMC043<SPI, CS, P = Tftmc043Panel, RST = NoPin> {
+    controller: ER5517<SPI, CS>,
+    //drawing limit set by the wrappers' with_clip
+    clip: Option<Rectangle>,
+    //screen area the wrappers' coordinates are relative to, from set_viewport
+    viewport: Option<Rectangle>,
+    //size of the canvas drawn to instead of the screen, while an OffscreenCanvas exists
+    surface: Option<Size>,
+    reset: RST,
+    panel: PhantomData<P>,
+}
 
-        self.hscan_l_to_r()?; //REG[12h]:from left to right
-        self.vscan_t_to_b()?; //REG[12h]:from top to bottom
-        self.pdata_set_rgb()?; //REG[12h]:Select RGB output
+/// Rgb888 DrawTarget following the controller's current color mode, colors are rounded to it
+pub struct TFTMC043Draw<SPI, CS, P = Tftmc043Panel, RST = NoPin> {
+    inner: TFTMC043<SPI, CS, P, RST>,
+}
 
-        self.pclk_falling()?;
-        self.hsync_low_active()?;
-        self.vsync_low_active()?;
-        self.de_high_active()?;
+/// NewType struct to implement DrawTarget
+pub struct TFTMC043Draw8Bit<SPI, CS, P = Tftmc043Panel, RST = NoPin> {
+    inner: TFTMC043<SPI, CS, P, RST>,
+}
 
-        self.set_width_height(WIDTH as _, HEIGHT as _)?;
-        self.set_horiz_non_display(LCD_HBPD)?;
-        self.set_horiz_start_pos(LCD_HFPD)?;
-        self.set_horiz_pulse_width(LCD_HSPW)?;
-        self.set_vert_non_display(LCD_VBPD)?;
-        self.set_vert_start_pos(LCD_VFPD)?;
-        self.set_vert_pulse_width(LCD_VSPW)?;
+/// NewType struct to implement DrawTarget
+pub struct TFTMC043Draw16Bit<SPI, CS, P = Tftmc043Panel, RST = NoPin> {
+    inner: TFTMC043<SPI, CS, P, RST>,
+}
 
-        self.select_main_window_color_mode(self.color_mode)?;
-        self.memory_xy_mode()?;
-        self.memory_color_mode(self.color_mode)?;
-        self.select_main_window_color_mode(self.color_mode)?;
+/// NewType struct to implement DrawTarget
+pub struct TFTMC043Draw24Bit<SPI, CS, P = Tftmc043Panel, RST = NoPin> {
+    inner: TFTMC043<SPI, CS, P, RST>,
+}
 
-        self.on(true)?;
+pub(crate) type Res<T, P, S> = Result<T, Error<P, S>>;
 
-        self.select_main_window_color_mode(self.color_mode)?;
-        self.main_image(0, 0, 0, WIDTH as _)?;
-        self.canvas_image(0, WIDTH as _)?;
-        self.active_window(0, 0, WIDTH as _, HEIGHT as _)?;
-        Ok(())
-    }
+//`p` moved by `d`, saturating far off screen instead of overflowing
+fn offset(p: Point, d: Point) -> Point {
+    Point::new(p.x.saturating_add(d.x), p.y.saturating_add(d.y))
+}
 
-    pub fn set_color_mode(&mut self, mode: ColorMode) -> Res<(), PinErr, SPIErr> {
-        if mode != self.color_mode {
-            self.memory_color_mode(mode)?;
-            self.select_main_window_color_mode(mode)?;
-            self.color_mode = mode;
-        }
-        Ok(())
-    }
+//`r` cut to a size and far edges that fit an i32, embedded-graphics' `intersection` overflows
+//past that
+fn bounded(r: &Rectangle) -> Rectangle {
+    let fit = |p: i32, len: u32| {
+        let room = i64::from(i32::MAX)
+            .saturating_sub(i64::from(p))
+            .min(i64::from(i32::MAX));
+        len.min(room as u32)
+    };
+    let Point { x, y } = r.top_left;
+    Rectangle::new(
+        r.top_left,
+        Size::new(fit(x, r.size.width), fit(y, r.size.height)),
+    )
+}
 
-    pub fn color_mode(&self) -> ColorMode {
-        self.color_mode
+impl<SPI, CS, P, RST> TFTMC043<SPI, CS, P, RST> {
+    pub fn controller(&self) -> &ER5517<SPI, CS> {
+        &self.controller
     }
 
-    //XXX expects 8-bit colors
-    pub fn fg_color(&mut self, r: u8, g: u8, b: u8) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0xD2, r)?;
-        self.register_write(0xD3, g)?;
-        self.register_write(0xD4, b)?;
-
-        Ok(())
+    pub fn controller_mut(&mut self) -> &mut ER5517<SPI, CS> {
+        &mut self.controller
     }
 
-    pub fn bg_color(&mut self, r: u8, g: u8, b: u8) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0xD5, r)?;
-        self.register_write(0xD6, g)?;
-        self.register_write(0xD7, b)?;
-        Ok(())
+    pub fn into_controller(self) -> ER5517<SPI, CS> {
+        self.controller
     }
 
-    pub fn active_window(&mut self, x: u16, y: u16, w: u16, h: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x56, x as u8)?;
-        self.register_write(0x57, (x >> 8) as u8)?;
-        self.register_write(0x58, y as u8)?;
-        self.register_write(0x59, (y >> 8) as u8)?;
-
-        self.register_write(0x5a, w as u8)?;
-        self.register_write(0x5b, (w >> 8) as u8)?;
-        self.register_write(0x5c, h as u8)?;
-        self.register_write(0x5d, (h >> 8) as u8)
+    /// Give back the bus and chip select, as `draw.release().destroy()` for the wrappers.
+    ///
+    /// The panel keeps showing its image, see [`TFTMC043::destroy_with_off`].
+    pub fn destroy(self) -> (SPI, CS) {
+        self.controller.release()
     }
 
-    pub fn line_start(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x68, x as u8)?;
-        self.register_write(0x69, (x >> 8) as u8)?;
-        self.register_write(0x6a, y as u8)?;
-        self.register_write(0x6b, (y >> 8) as u8)
+    /// [`TFTMC043::destroy`], also returning the reset pin.
+    pub fn destroy_with_reset(self) -> (SPI, CS, RST) {
+        let (spi, cs) = self.controller.release();
+        (spi, cs, self.reset)
     }
 
-    pub fn line_end(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x6c, x as u8)?;
-        self.register_write(0x6d, (x >> 8) as u8)?;
-        self.register_write(0x6e, y as u8)?;
-        self.register_write(0x6f, (y >> 8) as u8)
+    /// The area drawing is limited to in screen coordinates, the screen unless a wrapper set a
+    /// viewport or is inside `with_clip`.
+    pub fn clip_area(&self) -> Rectangle {
+        let screen = self.screen();
+        let area = match self.viewport {
+            Some(v) => v.intersection(&screen),
+            None => screen,
+        };
+        match self.clip {
+            Some(c) => c.intersection(&area),
+            None => area,
+        }
     }
 
-    pub fn rect_fill(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x76, 0xE0)?;
-        self.busy_draw()
+    fn screen(&self) -> Rectangle {
+        let (w, h) = self.dimensions();
+        let size = self.surface.unwrap_or(Size::new(w as _, h as _));
+        Rectangle::new(Point::zero(), size)
     }
 
-    pub fn main_image(&mut self, addr: u32, x: u16, y: u16, w: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x20, addr as _)?;
-        self.register_write(0x21, (addr >> 8) as _)?;
-        self.register_write(0x22, (addr >> 16) as _)?;
-        self.register_write(0x23, (addr >> 24) as _)?;
-
-        self.register_write(0x24, w as _)?;
-        self.register_write(0x25, (w >> 8) as _)?;
-
-        self.register_write(0x26, x as _)?;
-        self.register_write(0x27, (x >> 8) as _)?;
-
-        self.register_write(0x28, y as _)?;
-        self.register_write(0x29, (y >> 8) as _)
+    //the wrappers' size, the viewport's while it is set
+    fn draw_size(&self) -> Size {
+        self.viewport.unwrap_or_else(|| self.screen()).size
     }
 
-    pub fn canvas_image(&mut self, addr: u32, w: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x50, addr as _)?;
-        self.register_write(0x51, (addr >> 8) as _)?;
-        self.register_write(0x52, (addr >> 16) as _)?;
-        self.register_write(0x53, (addr >> 24) as _)?;
-
-        self.register_write(0x54, w as _)?;
-        self.register_write(0x55, (w >> 8) as _)
+    //screen coordinates for the wrappers' `p`
+    fn to_screen(&self, p: Point) -> Point {
+        match self.viewport {
+            Some(v) => offset(p, v.top_left),
+            None => p,
+        }
     }
 
-    pub fn busy_draw(&mut self) -> Res<(), PinErr, SPIErr> {
-        while self.status_read()? & 0x08 != 0 {
-            //busy loop
+    //the only conversion of screen points to register coordinates, None for points off the
+    //screen or surface so a negative value can't turn into a huge one
+    fn to_panel_coords(&self, p: Point) -> Option<(u16, u16)> {
+        if !self.screen().contains(p) {
+            return None;
         }
-        Ok(())
+        Some((u16::try_from(p.x).ok()?, u16::try_from(p.y).ok()?))
     }
 
-    pub fn check_mem_wr_fifo_ready(&mut self) -> Res<(), PinErr, SPIErr> {
-        while self.status_read()? & 0x80 != 0 {
-            //busy loop
+    //panel coordinates for `p`, or None if it is outside the clip area, negative coordinates included
+    fn clip_point(&self, p: Point) -> Option<(u16, u16)> {
+        let p = self.to_screen(p);
+        if !self.clip_area().contains(p) {
+            return None;
         }
-        Ok(())
+        self.to_panel_coords(p)
     }
+}
 
-    fn system_check_temp(&mut self, delay: &mut dyn DelayMs<u16>) -> Res<(), PinErr, SPIErr> {
-        loop {
-            if self.status_read()? & 0x02 == 0 {
-                delay.delay_ms(2);
-                self.cmd_write(0x01)?;
-                delay.delay_ms(2);
-                if self.data_read()? & 0x80 == 0x80 {
-                    return Ok(());
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    //the visible part of `area`, with the memory write position at its top left corner and
+    //writes wrapping inside it
+    fn begin_window(&mut self, area: &Rectangle) -> Res<Option<Rectangle>, PinErr, SPIErr> {
+        let visible = bounded(area).intersection(&self.clip_area());
+        if visible.size == Size::zero() {
+            return Ok(None);
+        }
+        if self.is_text_mode() {
+            self.graphic_mode()?;
+        }
+        let (x, y) = self
+            .to_panel_coords(visible.top_left)
+            .ok_or(Error::OutOfBounds)?;
+        self.active_window_raw(x, y, visible.size.width as _, visible.size.height as _)?;
+        self.goto_pixel(x, y)?;
+        Ok(Some(visible))
+    }
+
+    //a transaction writing to the memory port at `x`, `y`, skipping the position update if the
+    //previous pixel's write left it there
+    fn pixel_at(&mut self, x: u16, y: u16) -> Res<Transaction<'_, SPI, CS>, PinErr, SPIErr> {
+        if self.mem_cursor != Some((x, y)) {
+            self.goto_pixel(x, y)?;
+            self.cmd_write(regs::MRWDP)?;
+        }
+        Ok(self.begin())
+    }
+
+    //after writing the pixel at `x`, `y`, the position advanced to the right inside the window
+    fn pixel_done(&mut self, x: u16, y: u16) {
+        let area = self.clip_area();
+        let right = area.top_left.x.saturating_add(area.size.width as i32);
+        let next = x.checked_add(1).filter(|&n| (n as i32) < right);
+        //wrapping to the next row depends on the exact window, leave that to goto_pixel
+        self.mem_cursor = match next {
+            Some(n) if self.memwrite_direction() == self.row_direction() => Some((n, y)),
+            _ => None,
+        };
+    }
+
+    //back to the clip area
+    fn end_window(&mut self) -> Res<(), PinErr, SPIErr> {
+        //the last pixels would land in the new window otherwise
+        self.wait_wr_fifo_empty()?;
+        let area = self.clip_area();
+        if area.size == Size::zero() {
+            //nothing is drawn with an empty clip, no need for a window
+            return Ok(());
+        }
+        let (x, y) = self
+            .to_panel_coords(area.top_left)
+            .ok_or(Error::OutOfBounds)?;
+        self.active_window_raw(x, y, area.size.width as _, area.size.height as _)
+    }
+
+    //run `f` with drawing limited to `area` inside the current clip, the previous clip is
+    //restored even if `f` fails
+    fn with_clip<T, R, E>(
+        target: &mut T,
+        area: Rectangle,
+        inner: impl Fn(&mut T) -> &mut Self,
+        f: impl FnOnce(&mut T) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        E: From<Error<PinErr, SPIErr>>,
+    {
+        let this = inner(target);
+        let prev = this.clip;
+        let area = bounded(&Rectangle::new(this.to_screen(area.top_left), area.size));
+        this.clip = Some(area.intersection(&this.clip_area()));
+        this.end_window()?;
+        let r = f(target);
+        let this = inner(target);
+        this.clip = prev;
+        let restored = this.end_window();
+        let v = r?;
+        restored?;
+        Ok(v)
+    }
+
+    //replace the viewport, `area` has to be on screen
+    fn set_viewport(&mut self, area: Rectangle) -> Res<(), PinErr, SPIErr> {
+        if area.size == Size::zero() || bounded(&area).intersection(&self.screen()) != area {
+            return Err(Error::OutOfBounds);
+        }
+        self.viewport = Some(area);
+        self.end_window()
+    }
+
+    fn clear_viewport(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.viewport = None;
+        self.end_window()
+    }
+
+    //stream `colors` for the pixels of `area` in row order, dropping the ones off screen
+    fn fill_window<C, const N: usize>(
+        &mut self,
+        area: &Rectangle,
+        colors: impl IntoIterator<Item = C>,
+        bytes: impl Fn(C) -> [u8; N],
+    ) -> Res<(), PinErr, SPIErr> {
+        let area = &Rectangle::new(self.to_screen(area.top_left), area.size);
+        let visible = match self.begin_window(area)? {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        let mut t = self.begin();
+        t.cmd(regs::MRWDP)?;
+        for (p, c) in area.points().zip(colors) {
+            if visible.contains(p) {
+                for v in bytes(c) {
+                    t.data(v)?;
+                }
+            }
+        }
+        t.commit()?;
+        self.end_window()
+    }
+
+    //`data` is rows of `width` pixels, `bpp` bytes each, reversed per pixel if `reverse`
+    fn draw_raw(
+        &mut self,
+        top_left: Point,
+        width: u16,
+        data: &[u8],
+        bpp: usize,
+        reverse: bool,
+    ) -> Res<(), PinErr, SPIErr> {
+        let row_len = (width as usize).saturating_mul(bpp);
+        if row_len == 0 || !data.len().is_multiple_of(row_len) {
+            return Err(Error::InvalidParam);
+        }
+        let rows = data.len().checked_div(row_len).unwrap_or(0);
+        let top_left = self.to_screen(top_left);
+        let area = Rectangle::new(top_left, Size::new(width as _, rows as _));
+        let visible = match self.begin_window(&area)? {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        //the visible part is inside `area`, each of its rows inside a full `row_len` chunk
+        let skip_x = (visible.top_left.x.saturating_sub(top_left.x) as usize).saturating_mul(bpp);
+        let skip_y = visible.top_left.y.saturating_sub(top_left.y) as usize;
+        let len = (visible.size.width as usize).saturating_mul(bpp);
+        let rows = data
+            .chunks(row_len)
+            .skip(skip_y)
+            .take(visible.size.height as _)
+            .map(|row| {
+                row.get(skip_x..skip_x.saturating_add(len))
+                    .unwrap_or_default()
+            });
+        if !reverse {
+            //already in memory order, a burst per row
+            self.cmd_write(regs::MRWDP)?;
+            for row in rows {
+                self.data_write_buf(row)?;
+            }
+            return self.end_window();
+        }
+        let mut t = self.begin();
+        t.cmd(regs::MRWDP)?;
+        for row in rows {
+            for px in row.chunks(bpp) {
+                for v in px.iter().rev() {
+                    t.data(*v)?;
+                }
+            }
+        }
+        t.commit()?;
+        self.end_window()
+    }
+
+    //`bits` is `height` rows of 1 bit per pixel, most significant bit first, each row padded to
+    //a byte. Without `bg` only the runs of set bits are written, each from its own position
+    fn draw_mono<const N: usize>(
+        &mut self,
+        top_left: Point,
+        width: u16,
+        height: u16,
+        bits: &[u8],
+        fg: [u8; N],
+        bg: Option<[u8; N]>,
+    ) -> Res<(), PinErr, SPIErr> {
+        let stride = width.div_ceil(8) as usize;
+        if bits.len() < stride.saturating_mul(height as usize) {
+            return Err(Error::InvalidParam);
+        }
+        let top_left = self.to_screen(top_left);
+        let area = Rectangle::new(top_left, Size::new(width as _, height as _));
+        let visible = match self.begin_window(&area)? {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        //the visible part is inside `area`
+        let x0 = visible.top_left.x.saturating_sub(top_left.x) as usize;
+        let x1 = x0.saturating_add(visible.size.width as usize);
+        let y0 = visible.top_left.y.saturating_sub(top_left.y) as usize;
+        let set = |row: &[u8], x: usize| row.get(x / 8).is_some_and(|b| b & (0x80 >> (x % 8)) != 0);
+        let rows = bits
+            .chunks(stride)
+            .enumerate()
+            .skip(y0)
+            .take(visible.size.height as _);
+        if let Some(bg) = bg {
+            let mut t = self.begin();
+            t.cmd(regs::MRWDP)?;
+            for (_, row) in rows {
+                for x in x0..x1 {
+                    for v in if set(row, x) { fg } else { bg } {
+                        t.data(v)?;
+                    }
+                }
+            }
+            t.commit()?;
+            return self.end_window();
+        }
+        for (y, row) in rows {
+            let mut x = x0;
+            while x < x1 {
+                //x stays below x1
+                if !set(row, x) {
+                    x = x.saturating_add(1);
+                    continue;
+                }
+                let start = x;
+                while x < x1 && set(row, x) {
+                    x = x.saturating_add(1);
+                }
+                //pixels still in the write FIFO would land at the new position
+                self.wait_wr_fifo_empty()?;
+                let (px, py) = self
+                    .to_panel_coords(offset(top_left, Point::new(start as i32, y as i32)))
+                    .ok_or(Error::OutOfBounds)?;
+                self.goto_pixel(px, py)?;
+                let mut t = self.begin();
+                t.cmd(regs::MRWDP)?;
+                for _ in start..x {
+                    for v in fg {
+                        t.data(v)?;
+                    }
                 }
-                delay.delay_ms(2);
-                self.cmd_write(0x01)?;
-                delay.delay_ms(2);
-                self.data_write(0x80)?;
+                t.commit()?;
             }
         }
+        self.end_window()
     }
+}
 
-    fn pll_init(&mut self, delay: &mut dyn DelayMs<u16>) -> Res<(), PinErr, SPIErr> {
-        let lpll_od_sclk = 2u8;
-        let lpll_od_cclk = 2u8;
-        let lpll_od_mclk = 2u8;
-        let lpll_r_sclk = 5u8;
-        let lpll_r_cclk = 5u8;
-        let lpll_r_mclk = 5u8;
-        let lpll_n_sclk = 15u8; // TFT PCLK out put frequency:65
-        let lpll_n_cclk = 100u8; // Core CLK:100
-        let lpll_n_mclk = 100u8; // SRAM CLK:100
-                                 //
-        self.register_write(0x05, (lpll_od_sclk << 6) | (lpll_r_sclk << 1))?;
-        self.register_write(0x07, (lpll_od_mclk << 6) | (lpll_r_mclk << 1))?;
-        self.register_write(0x09, (lpll_od_cclk << 6) | (lpll_r_cclk << 1))?;
-
-        self.register_write(0x06, lpll_n_sclk)?;
-        self.register_write(0x08, lpll_n_mclk)?;
-        self.register_write(0x0a, lpll_n_cclk)?;
-
-        self.cmd_write(0x00)?;
-        delay.delay_ms(1);
-        self.data_write(0x80)?;
-        delay.delay_ms(1);
-
-        //set pwm0 pwm1 100%
-        self.register_write(0x85, 0x0a)?;
-        self.register_write(0x88, 0x64)?;
-        self.register_write(0x8a, 0x64)?;
-        self.register_write(0x8c, 0x64)?;
-        self.register_write(0x8e, 0x64)?;
-        self.register_write(0x86, 0x33)
-    }
-
-    fn sdram_init(&mut self, delay: &mut dyn DelayMs<u16>) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0xe0, 0x29)?;
-        self.register_write(0xe1, 0x03)?; //CAS:2=0x02�ACAS:3=0x03
-
-        let sdram_itv = 476u16; //(64000000 / 8192) / (1000/60) - 12
-        self.register_write(0xe2, sdram_itv as u8)?;
-        self.register_write(0xe3, (sdram_itv >> 8) as u8)?;
-        self.register_write(0xe4, 0x01)?;
-        self.sdram_check_ready()?;
-        delay.delay_ms(1);
-        Ok(())
+impl<SPI, CS, PinErr, SPIErr> TFTMC043<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    pub fn new(spi: SPI, cs: CS, color_mode: ColorMode) -> Self {
+        Self::new_with_config(spi, cs, color_mode, PanelConfig::default())
     }
 
-    fn sdram_check_ready(&mut self) -> Res<(), PinErr, SPIErr> {
-        while self.status_read()? & 0x04 == 0 {
-            //LOOP
+    /// Timing chosen at run time, for panels without a [`PanelTiming`] type.
+    ///
+    /// `init` rejects a `config` that doesn't pass [`PanelConfig::validate`], see
+    /// [`TFTMC043::try_new_with_config`] to check it here.
+    pub fn new_with_config(spi: SPI, cs: CS, color_mode: ColorMode, config: PanelConfig) -> Self {
+        Self {
+            controller: ER5517::new(spi, cs, color_mode, config),
+            clip: None,
+            viewport: None,
+            surface: None,
+            reset: NoPin,
+            panel: PhantomData,
         }
-        Ok(())
     }
 
-    fn tft_16bit(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x01)?;
-        let v = (self.data_read()? | 0b1_0000) & !0b1000;
-        self.data_write(v)
+    /// [`TFTMC043::new_with_config`], failing if the timing registers can't hold `config`.
+    pub fn try_new_with_config(
+        spi: SPI,
+        cs: CS,
+        color_mode: ColorMode,
+        config: PanelConfig,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Self::new_with_config(spi, cs, color_mode, config))
     }
+}
 
-    fn host_16bit(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x01)?;
-        let v = self.data_read()? | 0b0001;
-        self.data_write(v)
+impl<SPI, CS, P, PinErr, SPIErr> TFTMC043<SPI, CS, P>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    P: PanelTiming,
+{
+    /// Timing from `P`, as in `TFTMC043::<_, _, Ertftmc050Panel>::new_for_panel(spi, cs, mode)`.
+    pub fn new_for_panel(spi: SPI, cs: CS, color_mode: ColorMode) -> Self {
+        let () = P::VALID;
+        Self {
+            controller: ER5517::new(spi, cs, color_mode, P::CONFIG),
+            clip: None,
+            viewport: None,
+            surface: None,
+            reset: NoPin,
+            panel: PhantomData,
+        }
     }
+}
 
-    fn rgb_16bit_16bpp(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x02)?;
-        let v = (self.data_read()? | 0b0100_0000) & !0b1000_0000;
-        self.data_write(v)
+impl<SPI, CS, RST, PinErr, SPIErr> TFTMC043<SPI, CS, Tftmc043Panel, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    RST: ResetPin<PinErr>,
+{
+    /// Driver owning the controller's reset pin, `init` pulses it first.
+    pub fn with_reset(spi: SPI, cs: CS, reset: RST, color_mode: ColorMode) -> Self {
+        Self {
+            controller: ER5517::new(spi, cs, color_mode, PanelConfig::default()),
+            clip: None,
+            viewport: None,
+            surface: None,
+            reset,
+            panel: PhantomData,
+        }
     }
+}
 
-    fn memwrite_left_right_top_down(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x02)?;
-        let v = self.data_read()? & !0b0000_0110;
-        self.data_write(v)
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    RST: ResetPin<PinErr>,
+{
+    /// Pulse the reset pin and wait for the controller to come out of reset, does nothing
+    /// without a pin.
+    pub fn hard_reset<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        delay: &mut D,
+    ) -> Res<(), PinErr, SPIErr> {
+        if !self.reset.set_reset(true).map_err(Error::Pin)? {
+            return Ok(());
+        }
+        //the reset low time is a few microseconds, a millisecond covers slow pins too
+        delay.delay_us(1_000);
+        self.reset.set_reset(false).map_err(Error::Pin)?;
+        self.force_color_resync();
+        self.reset_shadows();
+        //host access stays inhibited until the internal reset is done
+        for _ in 0..self.poll_config().max_polls {
+            delay.delay_us(1_000);
+            if self.status_read()? & common::STATUS_INHIBIT == 0 {
+                self.power_mode = PowerMode::Normal;
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
     }
 
-    fn graphic_mode(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x03)?;
-        let v = self.data_read()? & !0b0000_0100;
-        self.data_write(v)
+    /// [`ER5517::init`], after a [`TFTMC043::hard_reset`] if there is a reset pin.
+    pub fn init<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(), InitError<PinErr, SPIErr>> {
+        self.hard_reset(delay)
+            .map_err(InitError::at(InitStage::Reset))?;
+        self.controller.init(delay)
     }
+}
 
-    fn mem_select_sdram(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x03)?;
-        let v = self.data_read()? & !0b0000_0011;
-        self.data_write(v)
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Turn the panel and backlight off, then [`TFTMC043::destroy`].
+    pub fn destroy_with_off(mut self) -> Res<(SPI, CS), PinErr, SPIErr> {
+        self.on(false)?;
+        self.backlight_on(false)?;
+        Ok(self.destroy())
     }
 
-    fn hscan_l_to_r(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x12)?;
-        let v = self.data_read()? & !0b0001_0000;
-        self.data_write(v)
+    /// Set the foreground color, replicating the top bits so full scale maps to 0xFF.
+    pub fn fg_color565(&mut self, c: Rgb565) -> Res<(), PinErr, SPIErr> {
+        self.fg_color888(color::expand565_to_888(c))
     }
 
-    fn vscan_t_to_b(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x12)?;
-        let v = self.data_read()? & !0b0000_1000;
-        self.data_write(v)
+    pub fn fg_color332(&mut self, c: Rgb332) -> Res<(), PinErr, SPIErr> {
+        self.fg_color888(color::expand332_to_888(c))
     }
 
-    fn pdata_set_rgb(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x12)?;
-        let v = self.data_read()? & !0b0000_0111;
-        self.data_write(v)
+    pub fn fg_color888(&mut self, c: Rgb888) -> Res<(), PinErr, SPIErr> {
+        self.fg_color(c.r(), c.g(), c.b())
     }
 
-    fn set_width_height(&mut self, w: u16, h: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x14, (w / 8 - 1) as _)?;
-        self.register_write(0x15, (w % 8) as _)?;
-        self.register_write(0x1A, (h - 1) as _)?;
-        self.register_write(0x1B, ((h - 1) >> 8) as _)?;
-        Ok(())
+    //fill with `c` through the fg registers, the fg_color color is restored when next used
+    fn fill_area(&mut self, area: &Rectangle, c: Rgb888) -> Res<(), PinErr, SPIErr> {
+        let area = bounded(&Rectangle::new(self.to_screen(area.top_left), area.size));
+        let drawable_area = area.intersection(&self.clip_area());
+        let Size { width, height } = drawable_area.size;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        //inclusive corners, the clip area keeps them on screen
+        let bottom_right = offset(
+            drawable_area.top_left,
+            Point::new(
+                (width as i32).saturating_sub(1),
+                (height as i32).saturating_sub(1),
+            ),
+        );
+        let corners = self
+            .to_panel_coords(drawable_area.top_left)
+            .zip(self.to_panel_coords(bottom_right));
+        let ((x1, y1), (x2, y2)) = match corners {
+            Some(c) => c,
+            None => return Err(Error::OutOfBounds),
+        };
+
+        self.check_awake()?;
+        self.write_fg((c.r(), c.g(), c.b()))?;
+        self.line_start(x1, y1)?;
+        self.line_end(x2, y2)?;
+        if width == 1 || height == 1 {
+            //some firmware leaves single row, column or pixel rectangles empty, the line engine
+            //draws them
+            self.register_write(regs::DCR0, 0x80)?;
+        } else {
+            self.rect_fill_raw()?;
+        }
+        self.busy_draw()
     }
+}
 
-    fn set_horiz_non_display(&mut self, w: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x16, (w / 8 - 1) as _)?;
-        self.register_write(0x17, (w % 8) as _)?;
-        Ok(())
+#[cfg(feature = "eh1")]
+impl<D> TFTMC043<D, NoCs>
+where
+    D: embedded_hal_1::spi::SpiDevice<u8>,
+{
+    /// Create a driver on an embedded-hal 1.0 `SpiDevice`, which handles the chip select itself.
+    pub fn new_with_device(dev: D, color_mode: ColorMode) -> Self {
+        Self::new(dev, NoCs, color_mode)
     }
 
-    fn set_horiz_start_pos(&mut self, w: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x18, (w / 8).saturating_sub(1) as _)?;
-        Ok(())
+    pub fn new_with_device_and_config(dev: D, color_mode: ColorMode, config: PanelConfig) -> Self {
+        Self::new_with_config(dev, NoCs, color_mode, config)
     }
+}
 
-    fn set_horiz_pulse_width(&mut self, w: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x19, (w / 8).saturating_sub(1) as _)?;
-        Ok(())
-    }
+impl<SPI, CS, P, RST> Deref for TFTMC043<SPI, CS, P, RST> {
+    type Target = ER5517<SPI, CS>;
 
-    fn set_vert_non_display(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
-        let v = v - 1;
-        self.register_write(0x1c, v as _)?;
-        self.register_write(0x1d, (v >> 8) as _)?;
-        Ok(())
+    fn deref(&self) -> &Self::Target {
+        &self.controller
     }
+}
 
-    fn set_vert_start_pos(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x1e, v.saturating_sub(1) as _)?;
-        Ok(())
+impl<SPI, CS, P, RST> DerefMut for TFTMC043<SPI, CS, P, RST> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.controller
     }
+}
 
-    fn set_vert_pulse_width(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x1f, v.saturating_sub(1) as _)?;
-        Ok(())
-    }
+//the methods every draw target passes on to the display
+macro_rules! draw_target_methods {
+    ($($target:ident),*) => {
+        $(
+            impl<SPI, CS, P, RST, PinErr, SPIErr> $target<SPI, CS, P, RST>
+            where
+                SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+            {
+                pub fn on(&mut self, on: bool) -> Res<(), PinErr, SPIErr> {
+                    self.inner.on(on)
+                }
 
-    fn memory_xy_mode(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x5e)?;
-        let v = self.data_read()? & !0b0000_0100;
-        self.data_write(v)?;
-        Ok(())
-    }
+                pub fn set_backlight_percent(&mut self, pct: u8) -> Res<(), PinErr, SPIErr> {
+                    self.inner.set_backlight_percent(pct)
+                }
 
-    fn memory_color_mode(&mut self, mode: ColorMode) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x5e)?;
-        let v = (self.data_read()? & !0b0011)
-            | match mode {
-                ColorMode::EightBit => 0b00,
-                ColorMode::SixteenBit => 0b01,
-                ColorMode::TwentyFourBit => 0b10,
-            };
+                pub fn wait_vsync(&mut self) -> Res<(), PinErr, SPIErr> {
+                    self.inner.wait_vsync()
+                }
 
-        self.data_write(v)?;
-        Ok(())
+                /// Run `f` with drawing limited to `area`, relative to the viewport if set, by
+                /// the active window.
+                ///
+                /// Nested calls clip to the intersection, the previous window is restored
+                /// afterwards even on error.
+                pub fn with_clip<R>(
+                    &mut self,
+                    area: Rectangle,
+                    f: impl FnOnce(&mut Self) -> Result<R, Error<PinErr, SPIErr>>,
+                ) -> Result<R, Error<PinErr, SPIErr>> {
+                    TFTMC043::with_clip(self, area, |s| &mut s.inner, f)
+                }
+
+                /// Limit drawing to `area` of the screen until
+                /// [`clear_viewport`](Self::clear_viewport), with coordinates and the size
+                /// relative to it.
+                ///
+                /// Replaces any previous viewport, `area` has to be on screen.
+                pub fn set_viewport(&mut self, area: Rectangle) -> Res<(), PinErr, SPIErr> {
+                    self.inner.set_viewport(area)
+                }
+
+                pub fn clear_viewport(&mut self) -> Res<(), PinErr, SPIErr> {
+                    self.inner.clear_viewport()
+                }
+            }
+        )*
+    };
+}
+
+draw_target_methods!(
+    TFTMC043Draw,
+    TFTMC043Draw8Bit,
+    TFTMC043Draw16Bit,
+    TFTMC043Draw24Bit
+);
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043Draw8Bit<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Draws in 8 bit through the memory port, what the main and PIP windows show is left to
+    /// [`ER5517::select_main_window_color_mode`] and [`ER5517::pip_color_mode`].
+    pub fn new(mut inner: TFTMC043<SPI, CS, P, RST>) -> Result<Self, Error<PinErr, SPIErr>> {
+        inner.memory_color_mode(ColorMode::EightBit)?;
+        inner.color_mode_locked = true;
+        Ok(Self { inner })
     }
 
-    fn pclk_falling(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x12)?;
-        let v = self.data_read()? | 0b1000_0000;
-        self.data_write(v)?;
-        Ok(())
+    pub fn release(mut self) -> TFTMC043<SPI, CS, P, RST> {
+        self.inner.color_mode_locked = false;
+        self.inner
     }
 
-    fn hsync_low_active(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x13)?;
-        let v = self.data_read()? & !0b1000_0000;
-        self.data_write(v)?;
-        Ok(())
+    pub fn inner(&self) -> &TFTMC043<SPI, CS, P, RST> {
+        &self.inner
     }
 
-    fn vsync_low_active(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x13)?;
-        let v = self.data_read()? & !0b0100_0000;
-        self.data_write(v)?;
-        Ok(())
+    /// The color mode can't be changed through the borrow, `memory_color_mode` fails with
+    /// [`Error::ColorModeLocked`].
+    pub fn inner_mut(&mut self) -> &mut TFTMC043<SPI, CS, P, RST> {
+        &mut self.inner
     }
+}
 
-    fn de_high_active(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x13)?;
-        let v = self.data_read()? & !0b0010_0000;
-        self.data_write(v)?;
-        Ok(())
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043Draw16Bit<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Draws in 16 bit through the memory port, what the main and PIP windows show is left to
+    /// [`ER5517::select_main_window_color_mode`] and [`ER5517::pip_color_mode`].
+    pub fn new(mut inner: TFTMC043<SPI, CS, P, RST>) -> Result<Self, Error<PinErr, SPIErr>> {
+        inner.memory_color_mode(ColorMode::SixteenBit)?;
+        inner.color_mode_locked = true;
+        Ok(Self { inner })
     }
 
-    fn set_pwm_prescaler_1_to_256(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x84, v.saturating_sub(1) as _)?;
-        Ok(())
+    pub fn release(mut self) -> TFTMC043<SPI, CS, P, RST> {
+        self.inner.color_mode_locked = false;
+        self.inner
     }
 
-    fn select_pwm1_clock_div_by_1(&mut self) -> Res<(), PinErr, SPIErr> {
-        /*
-        Select MUX input for PWM Timer 1.
-        00 = 1; 01 = 1/2; 10 = 1/4 ; 11 = 1/8;
-        */
-        self.cmd_write(0x85)?;
-        let v = self.data_read()? & !0b1100_0000;
-        self.data_write(v)?;
-        Ok(())
+    pub fn inner(&self) -> &TFTMC043<SPI, CS, P, RST> {
+        &self.inner
+    }
+
+    /// The color mode can't be changed through the borrow, `memory_color_mode` fails with
+    /// [`Error::ColorModeLocked`].
+    pub fn inner_mut(&mut self) -> &mut TFTMC043<SPI, CS, P, RST> {
+        &mut self.inner
+    }
+
+    /// Draw little endian Rgb565 image data, as in `ImageRawLE<Rgb565>`, `width` pixels per row.
+    ///
+    /// The bytes are streamed as is, the parts off screen are clipped.
+    pub fn draw_raw_image(
+        &mut self,
+        top_left: Point,
+        width: u16,
+        data: &[u8],
+    ) -> Res<(), PinErr, SPIErr> {
+        self.inner.draw_raw(top_left, width, data, 2, false)
+    }
+
+    /// Draw a 1 bit per pixel bitmap, rows most significant bit first and padded to a byte, set
+    /// bits in `fg` and the others in `bg` or left as they are.
+    ///
+    /// For text renderers handing over whole glyphs, the parts off screen are clipped.
+    pub fn draw_mono_bitmap(
+        &mut self,
+        top_left: Point,
+        width: u16,
+        height: u16,
+        bits: &[u8],
+        fg: Rgb565,
+        bg: Option<Rgb565>,
+    ) -> Res<(), PinErr, SPIErr> {
+        let bg = bg.map(color::rgb565_to_bytes);
+        self.inner.draw_mono(
+            top_left,
+            width,
+            height,
+            bits,
+            color::rgb565_to_bytes(fg),
+            bg,
+        )
     }
+}
 
-    fn select_pwm1(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x85)?;
-        let v = (self.data_read()? | 0b1000) & !0b0100;
-        self.data_write(v)?;
-        Ok(())
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043Draw24Bit<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Draws in 24 bit through the memory port, what the main and PIP windows show is left to
+    /// [`ER5517::select_main_window_color_mode`] and [`ER5517::pip_color_mode`].
+    pub fn new(mut inner: TFTMC043<SPI, CS, P, RST>) -> Result<Self, Error<PinErr, SPIErr>> {
+        inner.memory_color_mode(ColorMode::TwentyFourBit)?;
+        inner.color_mode_locked = true;
+        Ok(Self { inner })
     }
 
-    fn start_pwm1(&mut self) -> Res<(), PinErr, SPIErr> {
-        self.cmd_write(0x86)?;
-        let v = self.data_read()? | 0b1_0000;
-        self.data_write(v)?;
-        Ok(())
+    pub fn release(mut self) -> TFTMC043<SPI, CS, P, RST> {
+        self.inner.color_mode_locked = false;
+        self.inner
     }
 
-    fn set_timer1_count_buffer(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x8e, v as _)?;
-        self.register_write(0x8f, (v >> 8) as _)?;
-        Ok(())
+    pub fn inner(&self) -> &TFTMC043<SPI, CS, P, RST> {
+        &self.inner
+    }
+
+    /// The color mode can't be changed through the borrow, `memory_color_mode` fails with
+    /// [`Error::ColorModeLocked`].
+    pub fn inner_mut(&mut self) -> &mut TFTMC043<SPI, CS, P, RST> {
+        &mut self.inner
+    }
+
+    /// Draw Rgb888 image data, red first as in `ImageRaw<Rgb888>`, `width` pixels per row.
+    ///
+    /// The parts off screen are clipped.
+    pub fn draw_raw_image(
+        &mut self,
+        top_left: Point,
+        width: u16,
+        data: &[u8],
+    ) -> Res<(), PinErr, SPIErr> {
+        //memory order is blue first
+        self.inner.draw_raw(top_left, width, data, 3, true)
+    }
+
+    /// Draw a 1 bit per pixel bitmap, like [`TFTMC043Draw16Bit::draw_mono_bitmap`].
+    pub fn draw_mono_bitmap(
+        &mut self,
+        top_left: Point,
+        width: u16,
+        height: u16,
+        bits: &[u8],
+        fg: Rgb888,
+        bg: Option<Rgb888>,
+    ) -> Res<(), PinErr, SPIErr> {
+        let bg = bg.map(color::rgb888_to_bytes);
+        self.inner.draw_mono(
+            top_left,
+            width,
+            height,
+            bits,
+            color::rgb888_to_bytes(fg),
+            bg,
+        )
     }
+}
 
-    fn set_timer1_compare_buffer(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x8c, v as _)?;
-        self.register_write(0x8d, (v >> 8) as _)?;
-        Ok(())
+impl<SPI, CS, P, RST> OriginDimensions for TFTMC043Draw8Bit<SPI, CS, P, RST> {
+    fn size(&self) -> Size {
+        self.inner.draw_size()
     }
+}
 
-    pub fn set_brightness(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
-        self.select_pwm1()?;
-        self.set_pwm_prescaler_1_to_256(20)?;
-        self.select_pwm1_clock_div_by_1()?;
-        self.set_timer1_count_buffer(100)?;
-        self.set_timer1_compare_buffer(v)?;
-        self.start_pwm1()?;
-        Ok(())
+impl<SPI, CS, P, RST> OriginDimensions for TFTMC043Draw16Bit<SPI, CS, P, RST> {
+    fn size(&self) -> Size {
+        self.inner.draw_size()
     }
+}
 
-    pub fn goto_pixel(&mut self, x: u16, y: u16) -> Res<(), PinErr, SPIErr> {
-        self.register_write(0x5F, x as u8)?;
-        self.register_write(0x60, (x >> 8) as u8)?;
-        self.register_write(0x61, y as u8)?;
-        self.register_write(0x62, (y >> 8) as u8)?;
-        Ok(())
+impl<SPI, CS, P, RST> OriginDimensions for TFTMC043Draw24Bit<SPI, CS, P, RST> {
+    fn size(&self) -> Size {
+        self.inner.draw_size()
     }
 }
 
-impl<SPI, CS, PinErr, SPIErr> TFTMC043Draw16Bit<SPI, CS>
+impl<SPI, CS, P, RST, PinErr, SPIErr> DrawTarget for TFTMC043Draw8Bit<SPI, CS, P, RST>
 where
-    SPI: SPIWrite<u8, Error = SPIErr> + SPITransfer<u8, Error = SPIErr>,
-    CS: OutputPin<Error = PinErr>,
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
 {
-    pub fn new(mut inner: TFTMC043<SPI, CS>) -> Result<Self, Error<PinErr, SPIErr>> {
-        inner.set_color_mode(ColorMode::SixteenBit)?;
-        Ok(Self { inner })
+    type Color = Rgb332;
+    type Error = Error<PinErr, SPIErr>;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        if self.inner.is_text_mode() {
+            self.inner.graphic_mode()?;
+        }
+        for Pixel(coord, color) in pixels.into_iter() {
+            if let Some((x, y)) = self.inner.clip_point(coord) {
+                let mut t = self.inner.pixel_at(x, y)?;
+                t.data(color::rgb332_to_byte(color))?;
+                t.commit()?;
+                self.inner.pixel_done(x, y);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.inner.fill_area(area, color::expand332_to_888(color))
     }
 
-    pub fn release(self) -> TFTMC043<SPI, CS> {
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        //one window write instead of a position update per pixel
         self.inner
+            .fill_window(area, colors, |c: Rgb332| [color::rgb332_to_byte(c)])
     }
 }
 
-impl<SPI, CS, PinErr, SPIErr> TFTMC043Draw24Bit<SPI, CS>
+impl<SPI, CS, P, RST, PinErr, SPIErr> DrawTarget for TFTMC043Draw16Bit<SPI, CS, P, RST>
 where
-    SPI: SPIWrite<u8, Error = SPIErr> + SPITransfer<u8, Error = SPIErr>,
-    CS: OutputPin<Error = PinErr>,
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
 {
-    pub fn new(mut inner: TFTMC043<SPI, CS>) -> Result<Self, Error<PinErr, SPIErr>> {
-        inner.set_color_mode(ColorMode::TwentyFourBit)?;
-        Ok(Self { inner })
-    }
+    type Color = Rgb565;
+    type Error = Error<PinErr, SPIErr>;
 
-    pub fn release(self) -> TFTMC043<SPI, CS> {
-        self.inner
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        if self.inner.is_text_mode() {
+            self.inner.graphic_mode()?;
+        }
+        for Pixel(coord, color) in pixels.into_iter() {
+            if let Some((x, y)) = self.inner.clip_point(coord) {
+                let mut t = self.inner.pixel_at(x, y)?;
+                for v in color::rgb565_to_bytes(color) {
+                    t.data(v)?;
+                }
+                t.commit()?;
+                self.inner.pixel_done(x, y);
+            }
+        }
+
+        Ok(())
     }
-}
 
-impl<SPI, CS> OriginDimensions for TFTMC043Draw16Bit<SPI, CS> {
-    fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.inner.fill_area(area, color::expand565_to_888(color))
     }
-}
 
-impl<SPI, CS> OriginDimensions for TFTMC043Draw24Bit<SPI, CS> {
-    fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        //one window write instead of a position update per pixel
+        self.inner.fill_window(area, colors, color::rgb565_to_bytes)
     }
 }
 
-impl<SPI, CS, PinErr, SPIErr> DrawTarget for TFTMC043Draw16Bit<SPI, CS>
+impl<SPI, CS, P, RST, PinErr, SPIErr> DrawTarget for TFTMC043Draw24Bit<SPI, CS, P, RST>
 where
-    SPI: SPIWrite<u8, Error = SPIErr> + SPITransfer<u8, Error = SPIErr>,
-    CS: OutputPin<Error = PinErr>,
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
 {
-    type Color = Rgb565;
+    type Color = Rgb888;
     type Error = Error<PinErr, SPIErr>;
 
     fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
+        if self.inner.is_text_mode() {
+            self.inner.graphic_mode()?;
+        }
         for Pixel(coord, color) in pixels.into_iter() {
-            if let Ok((x @ 0..=WIDTH, y @ 0..=HEIGHT)) = coord.try_into() {
-                self.inner.goto_pixel(x as u16, y as u16)?;
-                self.inner.cmd_write(0x04)?;
-
-                let r = color.r();
-                let g = color.g();
-                let b = color.b();
-
-                for v in [b | (g << 5), (g >> 3) | (r << 3)] {
-                    self.inner.data_write(v)?;
-                    self.inner.check_mem_wr_fifo_ready()?;
+            if let Some((x, y)) = self.inner.clip_point(coord) {
+                let mut t = self.inner.pixel_at(x, y)?;
+                for v in color::rgb888_to_bytes(color) {
+                    t.data(v)?;
                 }
+                t.commit()?;
+                self.inner.pixel_done(x, y);
             }
         }
 
@@ -658,26 +1277,60 @@ where
     }
 
     fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
-        let drawable_area = area.intersection(&self.bounding_box());
+        self.inner.fill_area(area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        //one window write instead of a position update per pixel
+        self.inner.fill_window(area, colors, color::rgb888_to_bytes)
+    }
+}
 
-        if drawable_area.size != Size::zero() {
-            let Point { x: x1, y: y1 } = drawable_area.top_left;
-            let Point { x: x2, y: y2 } = drawable_area.bottom_right().unwrap();
+impl<SPI, CS, P, RST> TFTMC043Draw<SPI, CS, P, RST> {
+    /// Keeps the current color mode, change it with `set_color_mode` at any time.
+    pub fn new(inner: TFTMC043<SPI, CS, P, RST>) -> Self {
+        Self { inner }
+    }
 
-            self.inner
-                .fg_color(color.r() << 3, color.g() << 2, color.b() << 3)?;
-            self.inner.line_start(x1 as _, y1 as _)?;
-            self.inner.line_end(x2 as _, y2 as _)?;
-            self.inner.rect_fill()?;
-        }
-        Ok(())
+    pub fn release(self) -> TFTMC043<SPI, CS, P, RST> {
+        self.inner
+    }
+
+    pub fn inner(&self) -> &TFTMC043<SPI, CS, P, RST> {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut TFTMC043<SPI, CS, P, RST> {
+        &mut self.inner
     }
 }
 
-impl<SPI, CS, PinErr, SPIErr> DrawTarget for TFTMC043Draw24Bit<SPI, CS>
+impl<SPI, CS, P, RST> Deref for TFTMC043Draw<SPI, CS, P, RST> {
+    type Target = TFTMC043<SPI, CS, P, RST>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<SPI, CS, P, RST> DerefMut for TFTMC043Draw<SPI, CS, P, RST> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<SPI, CS, P, RST> OriginDimensions for TFTMC043Draw<SPI, CS, P, RST> {
+    fn size(&self) -> Size {
+        self.inner.draw_size()
+    }
+}
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> DrawTarget for TFTMC043Draw<SPI, CS, P, RST>
 where
-    SPI: SPIWrite<u8, Error = SPIErr> + SPITransfer<u8, Error = SPIErr>,
-    CS: OutputPin<Error = PinErr>,
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
 {
     type Color = Rgb888;
     type Error = Error<PinErr, SPIErr>;
@@ -686,15 +1339,28 @@ where
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
+        if self.inner.is_text_mode() {
+            self.inner.graphic_mode()?;
+        }
+        let mode = self.inner.color_mode();
         for Pixel(coord, color) in pixels.into_iter() {
-            if let Ok((x @ 0..=WIDTH, y @ 0..=HEIGHT)) = coord.try_into() {
-                self.inner.goto_pixel(x as u16, y as u16)?;
-                self.inner.cmd_write(0x04)?;
-
-                for v in [color.b(), color.g(), color.r()] {
-                    self.inner.data_write(v)?;
-                    self.inner.check_mem_wr_fifo_ready()?;
+            if let Some((x, y)) = self.inner.clip_point(coord) {
+                let mut t = self.inner.pixel_at(x, y)?;
+                match mode {
+                    ColorMode::EightBit => t.data(color::rgb332_to_byte(color.into()))?,
+                    ColorMode::SixteenBit => {
+                        for v in color::rgb565_to_bytes(color::rgb888_to_565(color)) {
+                            t.data(v)?;
+                        }
+                    }
+                    ColorMode::TwentyFourBit => {
+                        for v in color::rgb888_to_bytes(color) {
+                            t.data(v)?;
+                        }
+                    }
                 }
+                t.commit()?;
+                self.inner.pixel_done(x, y);
             }
         }
 
@@ -702,17 +1368,305 @@ where
     }
 
     fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
-        let drawable_area = area.intersection(&self.bounding_box());
+        //round like draw_iter so fills match pixels of the same color
+        let c = match self.inner.color_mode() {
+            ColorMode::EightBit => color::expand332_to_888(color.into()),
+            ColorMode::SixteenBit => color::expand565_to_888(color::rgb888_to_565(color)),
+            ColorMode::TwentyFourBit => color,
+        };
+        self.inner.fill_area(area, c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::support::{self, Panel};
+
+    #[test]
+    fn corner_pixels() {
+        let (display, rec) = support::initialized(ColorMode::SixteenBit);
+        let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+        let (c0, c1) = (Rgb565::new(31, 0, 0), Rgb565::new(0, 63, 31));
+        display
+            .draw_iter([Pixel(Point::new(0, 0), c0), Pixel(Point::new(479, 271), c1)])
+            .unwrap();
+        let mut panel = Panel::new(480, 272, ColorMode::SixteenBit);
+        panel.replay(&rec.frames());
+        let bytes = |c| {
+            let [b0, b1] = color::rgb565_to_bytes(c);
+            [b0, b1, 0]
+        };
+        assert_eq!(panel.pixel(0, 0), bytes(c0));
+        assert_eq!(panel.pixel(479, 271), bytes(c1));
+        assert_eq!(panel.pixel(1, 0), [0; 3]);
+        assert_eq!(panel.pixel(0, 271), [0; 3]);
+    }
+
+    #[test]
+    fn points_off_screen_write_nothing() {
+        let (display, rec) = support::initialized(ColorMode::SixteenBit);
+        let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+        rec.clear();
+        let off = [
+            (480, 0),
+            (0, 272),
+            (480, 272),
+            (-1, 0),
+            (0, -1),
+            (i32::MIN, i32::MAX),
+        ];
+        display
+            .draw_iter(off.map(|(x, y)| Pixel(Point::new(x, y), Rgb565::WHITE)))
+            .unwrap();
+        assert!(rec.frames().is_empty());
+
+        let (display, rec) = support::initialized(ColorMode::EightBit);
+        let mut display = TFTMC043Draw8Bit::new(display).unwrap();
+        rec.clear();
+        display
+            .draw_iter(off.map(|(x, y)| Pixel(Point::new(x, y), Rgb332::WHITE)))
+            .unwrap();
+        assert!(rec.frames().is_empty());
+    }
+
+    #[test]
+    fn fg_color_expansion() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        let fg = |rec: &testing::RecordingSpi| {
+            let w = rec.register_writes();
+            rec.clear();
+            w
+        };
+        for (c, rgb) in [
+            (Rgb565::WHITE, (0xff, 0xff, 0xff)),
+            (Rgb565::new(16, 32, 16), (0x84, 0x82, 0x84)),
+            (Rgb565::new(1, 1, 1), (0x08, 0x04, 0x08)),
+            (Rgb565::BLACK, (0, 0, 0)),
+        ] {
+            display.fg_color565(c).unwrap();
+            let (r, g, b) = rgb;
+            assert_eq!(
+                fg(&rec),
+                [(regs::FGCR, r), (regs::FGCG, g), (regs::FGCB, b)],
+                "{:?}",
+                c
+            );
+        }
+        display.fg_color888(Rgb888::new(0x12, 0x34, 0x56)).unwrap();
+        assert_eq!(
+            fg(&rec),
+            [(regs::FGCR, 0x12), (regs::FGCG, 0x34), (regs::FGCB, 0x56)]
+        );
+    }
+
+    #[test]
+    fn same_color_fills_write_fg_once() {
+        let (display, rec) = support::initialized(ColorMode::SixteenBit);
+        let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+        rec.clear();
+        let fg_writes = |rec: &testing::RecordingSpi| {
+            rec.register_writes()
+                .into_iter()
+                .filter(|(r, _)| (regs::FGCR..=regs::FGCB).contains(r))
+                .count()
+        };
+        let c = Rgb565::new(3, 40, 20);
+        let area = Rectangle::new(Point::new(10, 10), Size::new(20, 20));
+        display.fill_solid(&area, c).unwrap();
+        let next = Rectangle::new(Point::new(50, 10), area.size);
+        display.fill_solid(&next, c).unwrap();
+        assert_eq!(fg_writes(&rec), 3);
+
+        rec.clear();
+        display.inner_mut().force_color_resync();
+        display.fill_solid(&area, c).unwrap();
+        assert_eq!(fg_writes(&rec), 3);
+        rec.clear();
+        display.fill_solid(&area, Rgb565::BLUE).unwrap();
+        assert_eq!(fg_writes(&rec), 3);
+    }
+
+    //the fill of `area` as drawn through fill_solid, checked pixel by pixel against `expected`
+    fn check_fill(area: Rectangle, expected: Option<Rectangle>) {
+        let (display, rec) = support::initialized(ColorMode::SixteenBit);
+        let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+        rec.clear();
+        display.fill_solid(&area, Rgb565::WHITE).unwrap();
+        let expected = match expected {
+            Some(e) => e,
+            None => {
+                assert!(rec.frames().is_empty(), "{:?}", area);
+                return;
+            }
+        };
+        let mut panel = Panel::new(480, 272, ColorMode::SixteenBit);
+        panel.replay(&rec.frames());
+        for y in 0..272 {
+            for x in 0..480 {
+                let inside = expected.contains(Point::new(x as i32, y as i32));
+                let white = panel.pixel(x, y) == [0xff, 0xff, 0];
+                assert_eq!(white, inside, "{:?} at {x}, {y}", area);
+            }
+        }
+    }
 
-        if drawable_area.size != Size::zero() {
-            let Point { x: x1, y: y1 } = drawable_area.top_left;
-            let Point { x: x2, y: y2 } = drawable_area.bottom_right().unwrap();
+    fn rect(x: i32, y: i32, w: u32, h: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(w, h))
+    }
+
+    #[test]
+    fn fill_inside() {
+        check_fill(rect(10, 20, 30, 40), Some(rect(10, 20, 30, 40)));
+        check_fill(rect(0, 0, 480, 272), Some(rect(0, 0, 480, 272)));
+    }
+
+    #[test]
+    fn fill_clipped_on_each_edge() {
+        check_fill(rect(-5, 20, 30, 40), Some(rect(0, 20, 25, 40)));
+        check_fill(rect(10, -5, 30, 40), Some(rect(10, 0, 30, 35)));
+        check_fill(rect(470, 20, 30, 40), Some(rect(470, 20, 10, 40)));
+        check_fill(rect(10, 260, 30, 40), Some(rect(10, 260, 30, 12)));
+        check_fill(rect(-1, -1, 482, 274), Some(rect(0, 0, 480, 272)));
+    }
+
+    #[test]
+    fn fill_outside() {
+        check_fill(rect(480, 0, 10, 10), None);
+        check_fill(rect(0, 272, 10, 10), None);
+        check_fill(rect(-10, 0, 10, 10), None);
+        check_fill(rect(0, -10, 10, 10), None);
+        check_fill(rect(i32::MIN, i32::MIN, u32::MAX, 1), None);
+        check_fill(rect(i32::MAX, i32::MAX, 10, 10), None);
+    }
+
+    #[test]
+    fn fill_degenerate() {
+        check_fill(rect(10, 10, 0, 0), None);
+        check_fill(rect(10, 10, 5, 0), None);
+        check_fill(rect(10, 10, 0, 5), None);
+        check_fill(rect(10, 10, 1, 1), Some(rect(10, 10, 1, 1)));
+        check_fill(rect(10, 10, 1, 7), Some(rect(10, 10, 1, 7)));
+        check_fill(rect(10, 10, 7, 1), Some(rect(10, 10, 7, 1)));
+        check_fill(rect(479, 271, 1, 1), Some(rect(479, 271, 1, 1)));
+    }
+
+    #[test]
+    fn panel_config_boundaries() {
+        let base = PanelConfig::ER_TFTMC043;
+        assert_eq!(base.validate(), Ok(()));
+        assert_eq!(PanelConfig::ER_TFTMC050.validate(), Ok(()));
+        let () = <Tftmc043Panel as PanelTiming>::VALID;
+        type Set = fn(&mut PanelConfig, u32);
+        let fields: [(Set, u32, u32, ConfigError); 9] = [
+            (|c, v| c.width = v as u16, 8, 2048, ConfigError::Width),
+            (|c, v| c.height = v as u16, 1, 2048, ConfigError::Height),
+            (|c, v| c.hbpd = v as u16, 8, 2048, ConfigError::Hbpd),
+            (|c, v| c.hfpd = v as u16, 8, 2048, ConfigError::Hfpd),
+            (|c, v| c.hspw = v as u16, 8, 2048, ConfigError::Hspw),
+            (|c, v| c.vbpd = v as u16, 1, 1024, ConfigError::Vbpd),
+            (|c, v| c.vfpd = v as u16, 1, 256, ConfigError::Vfpd),
+            (|c, v| c.vspw = v as u16, 1, 128, ConfigError::Vspw),
+            (|c, v| c.pclk_khz = v, 500, 127_500, ConfigError::PixelClock),
+        ];
+        for (set, min, max, err) in fields {
+            let with = |v| {
+                let mut c = base;
+                set(&mut c, v);
+                c.validate()
+            };
+            assert_eq!(with(min), Ok(()), "{:?} at {min}", err);
+            assert_eq!(with(max), Ok(()), "{:?} at {max}", err);
+            assert_eq!(with(min - 1), Err(err), "{:?} at {}", err, min - 1);
+            assert_eq!(with(max + 1), Err(err), "{:?} at {}", err, max + 1);
+        }
+    }
 
-            self.inner.fg_color(color.r(), color.g(), color.b())?;
-            self.inner.line_start(x1 as _, y1 as _)?;
-            self.inner.line_end(x2 as _, y2 as _)?;
-            self.inner.rect_fill()?;
+    #[test]
+    fn invalid_config_fails_init() {
+        let config = PanelConfig {
+            hbpd: 7,
+            ..PanelConfig::ER_TFTMC043
+        };
+        let (mut display, rec) = support::display_with_config(ColorMode::SixteenBit, config);
+        let e = display.init(&mut support::NoDelay).unwrap_err();
+        assert_eq!(e.error, Error::Config(ConfigError::Hbpd));
+        //nothing written
+        assert!(rec.register_writes().is_empty());
+    }
+
+    #[test]
+    fn fill_corners_in_panel_coordinates() {
+        let corners = |area: Rectangle| {
+            let (display, rec) = support::initialized(ColorMode::SixteenBit);
+            let mut display = TFTMC043Draw16Bit::new(display).unwrap();
+            rec.clear();
+            display.fill_solid(&area, Rgb565::RED).unwrap();
+            let w = rec.register_writes();
+            let reg = |r: u8| {
+                let lo = w.iter().rev().find(|(x, _)| *x == r).map(|(_, v)| *v);
+                let hi = w.iter().rev().find(|(x, _)| *x == r + 1).map(|(_, v)| *v);
+                u16::from_le_bytes([lo.unwrap(), hi.unwrap()])
+            };
+            (
+                (reg(regs::DLHSR0), reg(regs::DLVSR0)),
+                (reg(regs::DLHER0), reg(regs::DLVER0)),
+            )
+        };
+        assert_eq!(corners(rect(-10, -10, 30, 20)), ((0, 0), (19, 9)));
+        //bottom right exactly at the panel corner
+        assert_eq!(corners(rect(460, 262, 20, 10)), ((460, 262), (479, 271)));
+        check_fill(rect(-10, -10, 30, 20), Some(rect(0, 0, 20, 10)));
+        check_fill(rect(460, 262, 20, 10), Some(rect(460, 262, 20, 10)));
+    }
+
+    #[test]
+    fn panel_coords_checked() {
+        let (display, _) = support::display(ColorMode::SixteenBit);
+        assert_eq!(display.to_panel_coords(Point::new(0, 0)), Some((0, 0)));
+        assert_eq!(
+            display.to_panel_coords(Point::new(479, 271)),
+            Some((479, 271))
+        );
+        for p in [
+            (-10, -10),
+            (-1, 0),
+            (0, -1),
+            (480, 0),
+            (0, 272),
+            (i32::MIN, 0),
+        ] {
+            assert_eq!(display.to_panel_coords(Point::new(p.0, p.1)), None, "{p:?}");
         }
-        Ok(())
+    }
+
+    #[test]
+    fn draw_targets_keep_window_depths() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        //an 8 bit background under 24 bit PIP content, drawn in other depths
+        display
+            .select_main_window_color_mode(ColorMode::EightBit)
+            .unwrap();
+        display
+            .pip_color_mode(PipWindow::Pip1, ColorMode::TwentyFourBit)
+            .unwrap();
+        let windows = |rec: &testing::RecordingSpi| {
+            (
+                rec.register(regs::MPWCTR) & 0b1100,
+                rec.register(regs::PIPCDEP),
+            )
+        };
+        let before = windows(&rec);
+        let memory = |rec: &testing::RecordingSpi| rec.register(regs::AW_COLOR) & 0b0011;
+        let display = TFTMC043Draw24Bit::new(display).unwrap();
+        assert_eq!((windows(&rec), memory(&rec)), (before, 0b10));
+        let display = TFTMC043Draw8Bit::new(display.release()).unwrap();
+        assert_eq!((windows(&rec), memory(&rec)), (before, 0b00));
+        let mut display = TFTMC043Draw16Bit::new(display.release()).unwrap();
+        assert_eq!((windows(&rec), memory(&rec)), (before, 0b01));
+        assert_eq!(
+            display.inner_mut().set_color_mode(ColorMode::EightBit),
+            Err(Error::ColorModeLocked)
+        );
     }
 }
@@ -0,0 +1,33 @@
+//! Bus traffic counters, for comparing the cost of drawing paths
+
+use crate::ER5517;
+
+/// Counts since the driver was created or [`ER5517::reset_metrics`], wrapping on overflow.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Metrics {
+    /// chip select assertions
+    pub transactions: u32,
+    /// bytes sent, prefixes and read dummies included
+    pub bytes_written: u32,
+    /// status reads spent waiting on the controller
+    pub busy_polls: u32,
+}
+
+impl<SPI, CS> ER5517<SPI, CS> {
+    pub fn metrics(&self) -> Metrics {
+        self.metrics
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+    }
+
+    pub(crate) fn count_transaction(&mut self, bytes: usize) {
+        self.metrics.transactions = self.metrics.transactions.wrapping_add(1);
+        self.metrics.bytes_written = self.metrics.bytes_written.wrapping_add(bytes as u32);
+    }
+
+    pub(crate) fn count_poll(&mut self) {
+        self.metrics.busy_polls = self.metrics.busy_polls.wrapping_add(1);
+    }
+}
@@ -0,0 +1,366 @@
+//! 1 bit per pixel frame buffers, expanded to the color mode on the way to the panel
+
+use crate::{color, ColorMode, Error, Interface, Res, Rgb332, TFTMC043};
+use embedded_graphics_core::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+
+//bytes expanded per burst, whole pixels in every color mode
+const CHUNK: usize = 192;
+//shortest run of 0x00 or 0xff bytes drawn as a fill, shorter ones cost less streamed than the
+//fill and the extra window around it
+const FILL_RUN: usize = 8;
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Draw the `w` x `h` 1 bit per pixel image `bits` at `x`, `y`, set bits in `fg` and the
+    /// others in `bg`.
+    ///
+    /// Rows are most significant bit first and padded to a byte, like
+    /// [`TFTMC043Draw16Bit::draw_mono_bitmap`](crate::TFTMC043Draw16Bit::draw_mono_bitmap).
+    /// Rows of one color and runs of at least 8 blank or set bytes become hardware fills, the
+    /// rest is expanded to the color mode in small bursts. The area has to be inside the
+    /// drawing area and clip rectangle.
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_mono(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        bits: &[u8],
+        fg: (u8, u8, u8),
+        bg: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        let stride = w.div_ceil(8) as usize;
+        if bits.len() < stride.saturating_mul(h as usize) {
+            return Err(Error::InvalidParam);
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+        let area = Rectangle::new(
+            self.to_screen(Point::new(x as i32, y as i32)),
+            Size::new(w as u32, h as u32),
+        );
+        if area.intersection(&self.clip_area()) != area {
+            return Err(Error::OutOfBounds);
+        }
+        let mode = self.color_mode();
+        let mono = Mono {
+            bits,
+            stride,
+            w,
+            colors: [pixel(mode, bg), pixel(mode, fg)],
+            bpp: mode.bytes_per_pixel() as usize,
+        };
+        let mut r = 0;
+        while r < h {
+            let row = mono.row(r);
+            //a stripe of one color rows, filled at once
+            if let Some(on) = mono.uniform(row) {
+                let mut end = r.saturating_add(1);
+                while end < h && mono.uniform(mono.row(end)) == Some(on) {
+                    end = end.saturating_add(1);
+                }
+                mono.fill(self, (x, y.saturating_add(r)), w, end.saturating_sub(r), on)?;
+                r = end;
+                continue;
+            }
+            //mixed rows without runs worth a fill, streamed in one window
+            if mono.next_run(row, 0).is_none() {
+                let mut end = r.saturating_add(1);
+                while end < h {
+                    let next = mono.row(end);
+                    if mono.uniform(next).is_some() || mono.next_run(next, 0).is_some() {
+                        break;
+                    }
+                    end = end.saturating_add(1);
+                }
+                mono.stream(self, (x, y), (0, w), (r, end))?;
+                r = end;
+                continue;
+            }
+            //a row with runs, filled and streamed in parts
+            let (mut px, mut byte) = (0, 0);
+            while let Some((start, end)) = mono.next_run(row, byte) {
+                let run_px = (start as u16).saturating_mul(8);
+                if run_px > px {
+                    mono.stream(self, (x, y), (px, run_px), (r, r.saturating_add(1)))?;
+                }
+                let on = mono.bit(row, run_px);
+                let mut end_px = (end as u16).saturating_mul(8);
+                //a uniform padded last byte joins the run
+                if (end_px..w).all(|i| mono.bit(row, i) == on) {
+                    end_px = w;
+                }
+                let len = end_px.saturating_sub(run_px);
+                mono.fill(
+                    self,
+                    (x.saturating_add(run_px), y.saturating_add(r)),
+                    len,
+                    1,
+                    on,
+                )?;
+                (px, byte) = (end_px, end);
+            }
+            if px < w {
+                mono.stream(self, (x, y), (px, w), (r, r.saturating_add(1)))?;
+            }
+            r = r.saturating_add(1);
+        }
+        Ok(())
+    }
+
+    /// [`TFTMC043::blit_mono`] over the whole drawing area, `bits` holding its rows.
+    pub fn present_mono_frame(
+        &mut self,
+        bits: &[u8],
+        fg: (u8, u8, u8),
+        bg: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        let size = self.draw_size();
+        self.blit_mono(0, 0, size.width as u16, size.height as u16, bits, fg, bg)
+    }
+}
+
+//the image of a blit_mono call and its two colors, off (bg) and on (fg)
+struct Mono<'a> {
+    bits: &'a [u8],
+    stride: usize,
+    w: u16,
+    colors: [([u8; 3], Rgb888); 2],
+    bpp: usize,
+}
+
+impl Mono<'_> {
+    fn row(&self, r: u16) -> &[u8] {
+        let start = (r as usize).saturating_mul(self.stride);
+        self.bits
+            .get(start..start.saturating_add(self.stride))
+            .unwrap_or_default()
+    }
+
+    fn bit(&self, row: &[u8], i: u16) -> bool {
+        let i = i as usize;
+        row.get(i / 8).is_some_and(|b| b & (0x80 >> (i % 8)) != 0)
+    }
+
+    //whether all `w` pixels of `row` are set or all clear, if they are the same
+    fn uniform(&self, row: &[u8]) -> Option<bool> {
+        let on = self.bit(row, 0);
+        let byte = if on { 0xff } else { 0 };
+        let full = self.w / 8;
+        let same = row.iter().take(full as usize).all(|&b| b == byte)
+            && (full.saturating_mul(8)..self.w).all(|i| self.bit(row, i) == on);
+        same.then_some(on)
+    }
+
+    //the first run of at least FILL_RUN equal 0x00 or 0xff bytes from byte `from` on, within
+    //the row's whole bytes
+    fn next_run(&self, row: &[u8], from: usize) -> Option<(usize, usize)> {
+        let full = (self.w / 8) as usize;
+        let row = row.get(..full).unwrap_or_default();
+        let mut i = from;
+        while let Some(&b) = row.get(i) {
+            let len = row
+                .iter()
+                .skip(i)
+                .take_while(|&&v| v == b && (b == 0 || b == 0xff))
+                .count();
+            if len >= FILL_RUN {
+                return Some((i, i.saturating_add(len)));
+            }
+            i = i.saturating_add(len.max(1));
+        }
+        None
+    }
+
+    //fill `w` x `h` at `x`, `y`, in drawing coordinates
+    fn fill<SPI, CS, P, RST, PinErr, SPIErr>(
+        &self,
+        display: &mut TFTMC043<SPI, CS, P, RST>,
+        (x, y): (u16, u16),
+        w: u16,
+        h: u16,
+        on: bool,
+    ) -> Res<(), PinErr, SPIErr>
+    where
+        SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    {
+        let (_, c) = self.color(on);
+        let area = Rectangle::new(
+            Point::new(x as i32, y as i32),
+            Size::new(w as u32, h as u32),
+        );
+        display.fill_area(&area, c)
+    }
+
+    //expand columns `px0..px1` of rows `r0..r1` into a window at image position `x`, `y`
+    fn stream<SPI, CS, P, RST, PinErr, SPIErr>(
+        &self,
+        display: &mut TFTMC043<SPI, CS, P, RST>,
+        (x, y): (u16, u16),
+        (px0, px1): (u16, u16),
+        (r0, r1): (u16, u16),
+    ) -> Res<(), PinErr, SPIErr>
+    where
+        SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+    {
+        let top_left = Point::new(x.saturating_add(px0) as i32, y.saturating_add(r0) as i32);
+        let size = Size::new(px1.saturating_sub(px0) as u32, r1.saturating_sub(r0) as u32);
+        let area = Rectangle::new(display.to_screen(top_left), size);
+        //blit_mono checked the whole image is visible
+        if display.begin_window(&area)?.is_none() {
+            return Ok(());
+        }
+        display.cmd_write(crate::regs::MRWDP)?;
+        let mut buf = [0; CHUNK];
+        let mut n: usize = 0;
+        for r in r0..r1 {
+            let row = self.row(r);
+            for i in px0..px1 {
+                let (bytes, _) = self.color(self.bit(row, i));
+                let end = n.saturating_add(self.bpp);
+                if let (Some(dst), Some(src)) = (buf.get_mut(n..end), bytes.get(..self.bpp)) {
+                    dst.copy_from_slice(src);
+                }
+                n = end;
+                if n.saturating_add(self.bpp) > CHUNK {
+                    display.data_write_buf(buf.get(..n).unwrap_or_default())?;
+                    n = 0;
+                }
+            }
+        }
+        display.data_write_buf(buf.get(..n).unwrap_or_default())?;
+        display.end_window()
+    }
+
+    fn color(&self, on: bool) -> ([u8; 3], Rgb888) {
+        let [off, on_color] = self.colors;
+        if on {
+            on_color
+        } else {
+            off
+        }
+    }
+}
+
+//`c` in the memory format of `mode`, and the color fills need to match it
+fn pixel(mode: ColorMode, (r, g, b): (u8, u8, u8)) -> ([u8; 3], Rgb888) {
+    let c = Rgb888::new(r, g, b);
+    match mode {
+        ColorMode::EightBit => {
+            let c = Rgb332::from(c);
+            ([color::rgb332_to_byte(c), 0, 0], color::expand332_to_888(c))
+        }
+        ColorMode::SixteenBit => {
+            let c = color::rgb888_to_565(c);
+            let [b0, b1] = color::rgb565_to_bytes(c);
+            ([b0, b1, 0], color::expand565_to_888(c))
+        }
+        ColorMode::TwentyFourBit => (color::rgb888_to_bytes(c), c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::support::{self, Panel};
+    use crate::{common, regs};
+    use std::{vec, vec::Vec};
+
+    const FG: (u8, u8, u8) = (0xff, 0x20, 0x00);
+    const BG: (u8, u8, u8) = (0x00, 0x40, 0xff);
+
+    //number of rectangle and line fills started in `frames`
+    fn fills(frames: &[Vec<u8>]) -> usize {
+        let mut selected = None;
+        let mut n = 0;
+        for f in frames {
+            match (f.as_slice(), selected) {
+                ([common::CMD_WRITE, r], _) => selected = Some(*r),
+                ([common::DATA_WRITE, 0xe0], Some(regs::DCR1))
+                | ([common::DATA_WRITE, 0x80], Some(regs::DCR0)) => n += 1,
+                _ => (),
+            }
+        }
+        n
+    }
+
+    //blit `bits` at `x`, `y` in `mode` and check every pixel of the image
+    fn check_blit(mode: ColorMode, (x, y): (u16, u16), w: u16, bits: &[u8]) -> Vec<Vec<u8>> {
+        let stride = w.div_ceil(8) as usize;
+        let h = (bits.len() / stride) as u16;
+        let (mut display, rec) = support::initialized(mode);
+        display.blit_mono(x, y, w, h, bits, FG, BG).unwrap();
+        let frames = rec.frames();
+        let mut panel = Panel::new(480, 272, mode);
+        panel.replay(&frames);
+        let (fg, bg) = (Rgb888::new(FG.0, FG.1, FG.2), Rgb888::new(BG.0, BG.1, BG.2));
+        for r in 0..h {
+            for i in 0..w {
+                let on = bits[r as usize * stride + i as usize / 8] & (0x80 >> (i % 8)) != 0;
+                let want = panel.bytes(if on { fg } else { bg });
+                assert_eq!(panel.pixel(x + i, y + r), want, "{mode:?} at {i}, {r}");
+            }
+        }
+        //nothing drawn around the image
+        for (px, py) in [(x - 1, y), (x + w, y), (x, y - 1), (x, y + h)] {
+            assert_eq!(panel.pixel(px, py), [0; 3], "{mode:?} at {px}, {py}");
+        }
+        frames
+    }
+
+    //rows mixing streamed bytes, a uniform row and, when they fit, runs, in a width that isn't a multiple of 8
+    fn image(w: u16) -> Vec<u8> {
+        let stride = w.div_ceil(8) as usize;
+        let mut bits = vec![0; stride * 4];
+        for (i, b) in bits[..stride].iter_mut().enumerate() {
+            *b = (i as u8).wrapping_mul(37) | 1;
+        }
+        bits[stride..2 * stride].fill(0xff);
+        if stride < FILL_RUN + 2 {
+            return bits;
+        }
+        let row = &mut bits[2 * stride..3 * stride];
+        row[0] = 0xa5;
+        row[1..1 + FILL_RUN].fill(0xff);
+        row[stride - 1] = 0x5a;
+        let row = &mut bits[3 * stride..];
+        row[..FILL_RUN].fill(0);
+        row[stride - 1] = 0x80;
+        bits
+    }
+
+    #[test]
+    fn expands_each_mode() {
+        for mode in [
+            ColorMode::EightBit,
+            ColorMode::SixteenBit,
+            ColorMode::TwentyFourBit,
+        ] {
+            for (w, at) in [(93, (3, 5)), (1, (1, 1)), (7, (471, 263)), (85, (17, 100))] {
+                check_blit(mode, at, w, &image(w));
+            }
+        }
+    }
+
+    #[test]
+    fn run_threshold() {
+        //one row, a mixed byte either side of a run of blank bytes
+        let row = |run: usize| {
+            let mut bits = vec![0xa5; run + 2];
+            bits[1..=run].fill(0);
+            bits
+        };
+        for mode in [ColorMode::EightBit, ColorMode::TwentyFourBit] {
+            let short = row(FILL_RUN - 1);
+            let frames = check_blit(mode, (3, 3), short.len() as u16 * 8 - 3, &short);
+            assert_eq!(fills(&frames), 0, "{mode:?}");
+            let long = row(FILL_RUN);
+            let frames = check_blit(mode, (3, 3), long.len() as u16 * 8 - 3, &long);
+            assert_eq!(fills(&frames), 1, "{mode:?}");
+        }
+    }
+}
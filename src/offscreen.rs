@@ -0,0 +1,154 @@
+//! Drawing into SDRAM outside of the displayed image, for pre-rendering and BTE copies
+
+use crate::{color, ColorMode, Error, Interface, NoPin, Orientation, Res, Tftmc043Panel, TFTMC043};
+use embedded_graphics_core::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+
+/// Rgb565 DrawTarget on a canvas at any SDRAM address, from [`TFTMC043::offscreen`]
+///
+/// The previous canvas, viewport and clip are restored by [`OffscreenCanvas::finish`], or on
+/// drop ignoring errors.
+pub struct OffscreenCanvas<'a, SPI, CS, P = Tftmc043Panel, RST = NoPin>
+where
+    SPI: Interface<CS>,
+{
+    inner: &'a mut TFTMC043<SPI, CS, P, RST>,
+    size: Size,
+    prev_canvas: (u32, u16),
+    prev_viewport: Option<Rectangle>,
+    prev_clip: Option<Rectangle>,
+    done: bool,
+}
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> TFTMC043<SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Draw to the `w` x `h` canvas at `addr` instead of the screen, sizes as drawn, rotated
+    /// orientations swap them in memory.
+    ///
+    /// Needs 16 bit color and the canvas inside the SDRAM, the active window is set relative to
+    /// the canvas.
+    pub fn offscreen(
+        &mut self,
+        addr: u32,
+        w: u16,
+        h: u16,
+    ) -> Res<OffscreenCanvas<'_, SPI, CS, P, RST>, PinErr, SPIErr> {
+        if self.color_mode() != ColorMode::SixteenBit {
+            return Err(Error::InvalidParam);
+        }
+        let (mem_w, mem_h) = match self.orientation() {
+            Orientation::Rot90 | Orientation::Rot270 => (h, w),
+            _ => (w, h),
+        };
+        if w == 0
+            || h == 0
+            || self
+                .pixel_address(
+                    addr,
+                    mem_w,
+                    mem_w.saturating_sub(1),
+                    mem_h.saturating_sub(1),
+                )
+                .is_none()
+        {
+            return Err(Error::OutOfBounds);
+        }
+        let prev_canvas = self
+            .state()
+            .canvas_image
+            .unwrap_or_else(|| self.canvas_target());
+        self.canvas_image(addr, mem_w)?;
+        let prev_viewport = self.viewport.take();
+        let prev_clip = self.clip.take();
+        self.surface = Some(Size::new(w as _, h as _));
+        self.end_window()?;
+        Ok(OffscreenCanvas {
+            inner: self,
+            size: Size::new(w as _, h as _),
+            prev_canvas,
+            prev_viewport,
+            prev_clip,
+            done: false,
+        })
+    }
+}
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> OffscreenCanvas<'_, SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Go back to drawing on the previous canvas.
+    pub fn finish(mut self) -> Res<(), PinErr, SPIErr> {
+        self.restore()
+    }
+
+    fn restore(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.done = true;
+        let inner = &mut *self.inner;
+        inner.surface = None;
+        inner.viewport = self.prev_viewport;
+        inner.clip = self.prev_clip;
+        inner.canvas_image(self.prev_canvas.0, self.prev_canvas.1)?;
+        inner.end_window()
+    }
+}
+
+impl<SPI, CS, P, RST> Drop for OffscreenCanvas<'_, SPI, CS, P, RST>
+where
+    SPI: Interface<CS>,
+{
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.restore();
+        }
+    }
+}
+
+impl<SPI, CS, P, RST> OriginDimensions for OffscreenCanvas<'_, SPI, CS, P, RST>
+where
+    SPI: Interface<CS>,
+{
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> DrawTarget for OffscreenCanvas<'_, SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    type Color = Rgb565;
+    type Error = Error<PinErr, SPIErr>;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        if self.inner.is_text_mode() {
+            self.inner.graphic_mode()?;
+        }
+        for Pixel(coord, color) in pixels.into_iter() {
+            if let Some((x, y)) = self.inner.clip_point(coord) {
+                let mut t = self.inner.pixel_at(x, y)?;
+                for v in color::rgb565_to_bytes(color) {
+                    t.data(v)?;
+                }
+                t.commit()?;
+                self.inner.pixel_done(x, y);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.inner.fill_area(area, color::expand565_to_888(color))
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.inner.fill_window(area, colors, color::rgb565_to_bytes)
+    }
+}
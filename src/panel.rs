@@ -0,0 +1,62 @@
+//! Panel timings known at compile time
+
+use crate::PanelConfig;
+
+/// Panel geometry and timing as associated consts, see [`PanelConfig`] for the meaning of each.
+pub trait PanelTiming {
+    const WIDTH: u16;
+    const HEIGHT: u16;
+    const HBPD: u16;
+    const HFPD: u16;
+    const HSPW: u16;
+    const VBPD: u16;
+    const VFPD: u16;
+    const VSPW: u16;
+    /// pixel clock PLL multiplier, the pixel clock is `SCLK_N` * 500kHz
+    const SCLK_N: u8;
+
+    const CONFIG: PanelConfig = PanelConfig {
+        width: Self::WIDTH,
+        height: Self::HEIGHT,
+        hbpd: Self::HBPD,
+        hfpd: Self::HFPD,
+        hspw: Self::HSPW,
+        vbpd: Self::VBPD,
+        vfpd: Self::VFPD,
+        vspw: Self::VSPW,
+        pclk_khz: Self::SCLK_N as u32 * 500,
+    };
+
+    /// Fails to compile for timings the registers can't hold, see [`PanelConfig::validate`].
+    const VALID: () = assert!(Self::CONFIG.validate().is_ok(), "invalid panel timing");
+}
+
+/// [`PanelConfig::ER_TFTMC043`]
+pub struct Tftmc043Panel;
+
+impl PanelTiming for Tftmc043Panel {
+    const WIDTH: u16 = 480;
+    const HEIGHT: u16 = 272;
+    const HBPD: u16 = 140;
+    const HFPD: u16 = 160;
+    const HSPW: u16 = 20;
+    const VBPD: u16 = 20;
+    const VFPD: u16 = 12;
+    const VSPW: u16 = 3;
+    const SCLK_N: u8 = 15;
+}
+
+/// [`PanelConfig::ER_TFTMC050`]
+pub struct Ertftmc050Panel;
+
+impl PanelTiming for Ertftmc050Panel {
+    const WIDTH: u16 = 800;
+    const HEIGHT: u16 = 480;
+    const HBPD: u16 = 140;
+    const HFPD: u16 = 160;
+    const HSPW: u16 = 20;
+    const VBPD: u16 = 20;
+    const VFPD: u16 = 12;
+    const VSPW: u16 = 3;
+    const SCLK_N: u8 = 65;
+}
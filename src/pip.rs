@@ -0,0 +1,99 @@
+//! Picture-in-picture windows overlaying the main image
+
+use crate::{common, regs, ColorMode, Error, Interface, Res, ER5517};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PipWindow {
+    Pip1,
+    Pip2,
+}
+
+impl PipWindow {
+    //REG[10h] enable bit
+    fn enable_bit(self) -> u8 {
+        match self {
+            PipWindow::Pip1 => 0b1000_0000,
+            PipWindow::Pip2 => 0b0100_0000,
+        }
+    }
+}
+
+//the chip misrenders PIP windows whose horizontal geometry isn't a multiple of 4
+fn check_aligned<P, S>(values: &[u16]) -> Res<(), P, S> {
+    if values.iter().all(|v| v % 4 == 0) {
+        Ok(())
+    } else {
+        Err(Error::InvalidParam)
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    //REG[10h] bit 4 selects which window REG[2Ah]-REG[3Bh] configure
+    fn pip_select(&mut self, which: PipWindow) -> Res<(), PinErr, SPIErr> {
+        match which {
+            PipWindow::Pip1 => self.modify(regs::MPWCTR, 0b0001_0000, 0),
+            PipWindow::Pip2 => self.modify(regs::MPWCTR, 0, 0b0001_0000),
+        }
+    }
+
+    /// Set the source of a PIP window, an `image_w` wide image at `addr` shown from `x`, `y`.
+    ///
+    /// `image_w` and `x` must be multiples of 4.
+    pub fn pip_image(
+        &mut self,
+        which: PipWindow,
+        addr: u32,
+        image_w: u16,
+        x: u16,
+        y: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        check_aligned(&[image_w, x])?;
+        self.pip_select(which)?;
+        self.register_write_u32(regs::PISA0, addr)?;
+        self.register_write_u16(regs::PIW0, image_w)?;
+        self.register_write_u16(regs::PWIULX0, x)?;
+        self.register_write_u16(regs::PWIULY0, y)
+    }
+
+    /// Place a `w` x `h` PIP window at `disp_x`, `disp_y` on the panel.
+    ///
+    /// `disp_x` and `w` must be multiples of 4.
+    pub fn pip_window(
+        &mut self,
+        which: PipWindow,
+        disp_x: u16,
+        disp_y: u16,
+        w: u16,
+        h: u16,
+    ) -> Res<(), PinErr, SPIErr> {
+        check_aligned(&[disp_x, w])?;
+        self.pip_select(which)?;
+        self.register_write_u16(regs::PWDULX0, disp_x)?;
+        self.register_write_u16(regs::PWDULY0, disp_y)?;
+        self.register_write_u16(regs::PWW0, w)?;
+        self.register_write_u16(regs::PWH0, h)
+    }
+
+    pub fn pip_enable(&mut self, which: PipWindow, on: bool) -> Res<(), PinErr, SPIErr> {
+        let bit = which.enable_bit();
+        self.modify_bits(regs::MPWCTR, bit, on)
+    }
+
+    /// PIP windows have their own color depth, REG[11h], independent of the main window.
+    ///
+    /// Kept through [`ER5517::set_color_mode`] and restored by init.
+    pub fn pip_color_mode(&mut self, which: PipWindow, mode: ColorMode) -> Res<(), PinErr, SPIErr> {
+        let bits = common::memory_color_bits(mode);
+        match which {
+            PipWindow::Pip1 => self.modify(regs::PIPCDEP, 0b1100, bits << 2)?,
+            PipWindow::Pip2 => self.modify(regs::PIPCDEP, 0b0011, bits)?,
+        }
+        if let Some(m) = self.state.pip_color_modes.get_mut(which as usize) {
+            *m = Some(mode);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! Power saving modes, REG[DFh]
+
+use crate::{common, regs, Error, Interface, Res, ER5517};
+use embedded_hal::blocking::delay::DelayUs;
+
+/// Controller power state, deeper modes save more power but take longer to wake
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PowerMode {
+    Normal,
+    /// core clock stopped, PLL running
+    Standby,
+    /// PLL stopped, SDRAM in self refresh
+    Suspend,
+    /// all clocks stopped
+    Sleep,
+}
+
+impl PowerMode {
+    fn bits(self) -> u8 {
+        match self {
+            PowerMode::Normal => 0b00,
+            PowerMode::Standby => 0b01,
+            PowerMode::Suspend => 0b10,
+            PowerMode::Sleep => 0b11,
+        }
+    }
+}
+
+//REG[DFh] bit 7, write 1 to enter the selected mode, reads 1 until awake again
+const PMU_ENTER: u8 = 0b1000_0000;
+
+impl<SPI, CS> ER5517<SPI, CS> {
+    pub fn power_state(&self) -> PowerMode {
+        self.power_mode
+    }
+
+    //drawing needs the core clock, fail instead of polling a stopped controller
+    pub(crate) fn check_awake<P, S>(&self) -> Res<(), P, S> {
+        if self.power_mode == PowerMode::Normal {
+            Ok(())
+        } else {
+            Err(Error::PowerSaving)
+        }
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Enter power saving `mode`, or wake up for [`PowerMode::Normal`].
+    ///
+    /// The panel is switched off in suspend and sleep as the pixel clock stops.
+    pub fn power_mode<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        mode: PowerMode,
+        delay: &mut D,
+    ) -> Res<(), PinErr, SPIErr> {
+        if mode == PowerMode::Normal {
+            return self.wake(delay);
+        }
+        if self.power_mode != PowerMode::Normal {
+            //the mode can only be changed while awake
+            self.wake(delay)?;
+        }
+        //let pending drawing finish before stopping the core
+        self.busy_draw()?;
+        if mode != PowerMode::Standby {
+            self.resume_display = self.register_read(regs::DPCR)? & 0b0100_0000 != 0;
+            self.on(false)?;
+        }
+        self.modify(regs::PMU, 0b0000_0011, mode.bits())?;
+        self.modify(regs::PMU, 0, PMU_ENTER)?;
+        self.wait_status(|s| s & common::STATUS_INHIBIT != 0)?;
+        self.power_mode = mode;
+        Ok(())
+    }
+
+    /// Return to normal operation, restarting the PLL and waiting for the SDRAM if they were stopped.
+    pub fn wake<D: DelayUs<u32> + ?Sized>(&mut self, delay: &mut D) -> Res<(), PinErr, SPIErr> {
+        let mode = self.power_mode;
+        if mode == PowerMode::Normal {
+            return Ok(());
+        }
+        self.register_write(regs::PMU, mode.bits())?;
+        self.wait_status(|s| s & common::STATUS_INHIBIT == 0)?;
+        self.power_mode = PowerMode::Normal;
+        if mode != PowerMode::Standby {
+            self.system_check_temp(delay)?;
+            self.sdram_check_ready()?;
+            if self.resume_display {
+                self.on(true)?;
+            }
+        }
+        Ok(())
+    }
+}
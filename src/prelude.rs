@@ -0,0 +1,6 @@
+//! The types most programs need, `use tftmc043::prelude::*;`
+
+pub use crate::{
+    ColorMode, Error, InitError, Interface, NoPin, Orientation, PanelConfig, Rgb332,
+    TFTMC043Builder, TFTMC043Draw16Bit, TFTMC043Draw24Bit, TFTMC043Draw8Bit, ER5517, TFTMC043,
+};
@@ -0,0 +1,219 @@
+//! PWM timers, PWM1 drives the backlight and PWM0 is free for other loads
+
+use crate::{regs, Error, Interface, Res, ER5517};
+use embedded_hal::blocking::delay::DelayUs;
+
+/// PWM timer clock divider after the shared prescaler
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PwmClockDivider {
+    Div1,
+    Div2,
+    Div4,
+    Div8,
+}
+
+impl PwmClockDivider {
+    fn bits(self) -> u8 {
+        match self {
+            PwmClockDivider::Div1 => 0b00,
+            PwmClockDivider::Div2 => 0b01,
+            PwmClockDivider::Div4 => 0b10,
+            PwmClockDivider::Div8 => 0b11,
+        }
+    }
+}
+
+//PWM1 counts to this, so the compare buffer is the duty in percent
+const BACKLIGHT_COUNT: u16 = 100;
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    fn set_pwm_prescaler_1_to_256(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write(regs::PSCLR, v.saturating_sub(1) as _)?;
+        Ok(())
+    }
+
+    fn select_pwm1_clock_div(&mut self, div: PwmClockDivider) -> Res<(), PinErr, SPIErr> {
+        /*
+        Select MUX input for PWM Timer 1.
+        00 = 1; 01 = 1/2; 10 = 1/4 ; 11 = 1/8;
+        */
+        self.modify(regs::PMUXR, 0b1100_0000, div.bits() << 6)
+    }
+
+    fn select_pwm1(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::PMUXR, 0b0100, 0b1000)
+    }
+
+    //route the PWM1 pin to GPIO-C7 and drive it low
+    fn force_pwm1_low(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::GPIOC, 0b1000_0000, 0)?; //GPIO-C7 low
+        self.modify(regs::GPIOCD, 0b1000_0000, 0)?; //GPIO-C7 output
+        self.modify(regs::PMUXR, 0b0000_1100, 0)
+    }
+
+    fn start_pwm1(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::PCFGR, 0, 0b1_0000)
+    }
+
+    fn stop_pwm1(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::PCFGR, 0b1_0000, 0)
+    }
+
+    fn set_timer1_count_buffer(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u16(regs::TCNTB1L, v)
+    }
+
+    fn set_timer1_compare_buffer(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u16(regs::TCMPB1L, v)
+    }
+
+    /// Set the PWM clock, `prescaler` 1..=256 is shared by both PWM timers.
+    ///
+    /// The backlight PWM frequency is core clock / prescaler / divider / 101, raise it above
+    /// the audible range if the backlight driver whines. Takes effect on the next backlight change.
+    pub fn set_backlight_pwm_clock(
+        &mut self,
+        prescaler: u16,
+        div: PwmClockDivider,
+    ) -> Res<(), PinErr, SPIErr> {
+        if !(1..=256).contains(&prescaler) {
+            return Err(Error::InvalidParam);
+        }
+        self.pwm_prescaler = prescaler;
+        self.pwm1_div = div;
+        Ok(())
+    }
+
+    /// Switch the backlight fully off, or back on at the last set percentage.
+    pub fn backlight_on(&mut self, on: bool) -> Res<(), PinErr, SPIErr> {
+        if on {
+            self.set_backlight_percent(self.state.backlight)
+        } else {
+            self.stop_pwm1()?;
+            self.force_pwm1_low()?;
+            self.state.backlight_on = false;
+            Ok(())
+        }
+    }
+
+    /// Backlight duty in percent, clamped to 0..=100, 0 turns it fully off.
+    pub fn set_backlight_percent(&mut self, pct: u8) -> Res<(), PinErr, SPIErr> {
+        let pct = pct.min(100);
+        if pct == 0 {
+            return self.backlight_on(false);
+        }
+        self.select_pwm1()?;
+        self.set_pwm_prescaler_1_to_256(self.pwm_prescaler)?;
+        self.select_pwm1_clock_div(self.pwm1_div)?;
+        self.set_timer1_count_buffer(BACKLIGHT_COUNT)?;
+        self.set_timer1_compare_buffer(pct as u16)?;
+        self.start_pwm1()?;
+        self.state.backlight = pct;
+        self.state.backlight_on = true;
+        Ok(())
+    }
+
+    /// Step the backlight from `from` to `to` percent over roughly `ms` milliseconds.
+    pub fn fade_backlight<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        from: u8,
+        to: u8,
+        ms: u16,
+        delay: &mut D,
+    ) -> Res<(), PinErr, SPIErr> {
+        let (from, to) = (from.clamp(1, 100), to.min(100));
+        self.set_backlight_percent(from)?;
+        let steps = (from as i16 - to as i16).unsigned_abs();
+        if steps == 0 {
+            return Ok(());
+        }
+        let step_us = (ms / steps) as u32 * 1_000;
+        let mut pct = from;
+        while pct != to {
+            delay.delay_us(step_us);
+            pct = if to > pct { pct + 1 } else { pct - 1 };
+            if pct == 0 {
+                return self.backlight_on(false);
+            }
+            //the timer is already running, only the duty changes
+            self.set_timer1_compare_buffer(pct as u16)?;
+            self.state.backlight = pct;
+        }
+        Ok(())
+    }
+
+    #[deprecated(note = "use set_backlight_percent")]
+    pub fn set_brightness(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
+        self.set_backlight_percent(v.min(100) as u8)
+    }
+
+    pub fn select_pwm0(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::PMUXR, 0b0000_0011, 0b0000_0010)
+    }
+
+    pub fn set_pwm0_clock_div(&mut self, div: PwmClockDivider) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::PMUXR, 0b0011_0000, div.bits() << 4)
+    }
+
+    pub fn start_pwm0(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::PCFGR, 0, 0b0000_0001)
+    }
+
+    pub fn stop_pwm0(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.modify(regs::PCFGR, 0b0000_0001, 0)
+    }
+
+    pub fn set_timer0_count_buffer(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u16(regs::TCNTB0L, v)
+    }
+
+    pub fn set_timer0_compare_buffer(&mut self, v: u16) -> Res<(), PinErr, SPIErr> {
+        self.register_write_u16(regs::TCMPB0L, v)
+    }
+
+    /// Invert the PWM0 output polarity.
+    pub fn pwm0_inverted(&mut self, inv: bool) -> Res<(), PinErr, SPIErr> {
+        self.modify_bits(regs::PCFGR, 0b0000_0100, inv)
+    }
+
+    /// Run PWM0 with a duty of `compare` / `count`, using the shared prescaler.
+    pub fn set_pwm0_duty(&mut self, count: u16, compare: u16) -> Res<(), PinErr, SPIErr> {
+        self.select_pwm0()?;
+        self.set_pwm_prescaler_1_to_256(self.pwm_prescaler)?;
+        self.set_timer0_count_buffer(count)?;
+        self.set_timer0_compare_buffer(compare)?;
+        self.start_pwm0()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::support::{self, NoDelay};
+    use crate::{ColorMode, DelayMsShim};
+    use embedded_hal::blocking::delay::DelayMs;
+
+    //the milliseconds asked for, through the shim
+    struct Total(u32);
+
+    impl DelayMs<u16> for Total {
+        fn delay_ms(&mut self, ms: u16) {
+            self.0 += ms as u32;
+        }
+    }
+
+    #[test]
+    fn fade_takes_microsecond_delays() {
+        let (mut display, _) = support::initialized(ColorMode::SixteenBit);
+        display.fade_backlight(10, 20, 100, &mut NoDelay).unwrap();
+        assert_eq!(display.state().backlight, 20);
+        let mut total = Total(0);
+        display
+            .fade_backlight(20, 10, 100, &mut DelayMsShim(&mut total))
+            .unwrap();
+        assert_eq!(total.0, 100);
+        assert_eq!(display.state().backlight, 10);
+    }
+}
@@ -0,0 +1,253 @@
+//! ER-5517 register addresses, named as in the datasheet
+//!
+//! Multi-byte values are little endian, the constant names the lowest byte's register.
+
+/// software reset
+pub const SRR: u8 = 0x00;
+/// chip configuration
+pub const CCR: u8 = 0x01;
+/// memory access control
+pub const MACR: u8 = 0x02;
+/// input control
+pub const ICR: u8 = 0x03;
+/// memory data read/write port
+pub const MRWDP: u8 = 0x04;
+
+/// SCLK, MCLK and CCLK PLL control
+pub const PPLLC1: u8 = 0x05;
+pub const PPLLC2: u8 = 0x06;
+pub const MPLLC1: u8 = 0x07;
+pub const MPLLC2: u8 = 0x08;
+pub const SPLLC1: u8 = 0x09;
+pub const SPLLC2: u8 = 0x0a;
+
+/// interrupt enable
+pub const INTEN: u8 = 0x0b;
+/// interrupt event flags
+pub const INTF: u8 = 0x0c;
+/// interrupt mask
+pub const MINTFR: u8 = 0x0d;
+
+/// main/PIP window control
+pub const MPWCTR: u8 = 0x10;
+/// PIP window color depths
+pub const PIPCDEP: u8 = 0x11;
+/// display configuration
+pub const DPCR: u8 = 0x12;
+/// panel scan clock and data settings
+pub const PCSR: u8 = 0x13;
+
+/// horizontal display width, in 8 pixel units, and fine tuning
+pub const HDWR: u8 = 0x14;
+pub const HDWFTR: u8 = 0x15;
+/// horizontal non-display period and fine tuning
+pub const HNDR: u8 = 0x16;
+pub const HNDFTR: u8 = 0x17;
+/// hsync start position
+pub const HSTR: u8 = 0x18;
+/// hsync pulse width
+pub const HPWR: u8 = 0x19;
+/// vertical display height
+pub const VDHR0: u8 = 0x1a;
+pub const VDHR1: u8 = 0x1b;
+/// vertical non-display period
+pub const VNDR0: u8 = 0x1c;
+pub const VNDR1: u8 = 0x1d;
+/// vsync start position
+pub const VSTR: u8 = 0x1e;
+/// vsync pulse width
+pub const VPWR: u8 = 0x1f;
+
+/// main image start address, 32 bit
+pub const MISA0: u8 = 0x20;
+/// main image width
+pub const MIW0: u8 = 0x24;
+/// main window upper left x
+pub const MWULX0: u8 = 0x26;
+/// main window upper left y
+pub const MWULY0: u8 = 0x28;
+
+/// PIP window display upper left x
+pub const PWDULX0: u8 = 0x2a;
+/// PIP window display upper left y
+pub const PWDULY0: u8 = 0x2c;
+/// PIP image start address, 32 bit
+pub const PISA0: u8 = 0x2e;
+/// PIP image width
+pub const PIW0: u8 = 0x32;
+/// PIP window image upper left x
+pub const PWIULX0: u8 = 0x34;
+/// PIP window image upper left y
+pub const PWIULY0: u8 = 0x36;
+/// PIP window width
+pub const PWW0: u8 = 0x38;
+/// PIP window height
+pub const PWH0: u8 = 0x3a;
+
+/// graphic and text cursor control
+pub const GTCCR: u8 = 0x3c;
+/// blink time
+pub const BTCR: u8 = 0x3d;
+/// text cursor horizontal size
+pub const CURHS: u8 = 0x3e;
+/// text cursor vertical size
+pub const CURVS: u8 = 0x3f;
+/// graphic cursor x
+pub const GCHP0: u8 = 0x40;
+/// graphic cursor y
+pub const GCVP0: u8 = 0x42;
+/// graphic cursor colors
+pub const GCC0: u8 = 0x44;
+pub const GCC1: u8 = 0x45;
+
+/// canvas start address, 32 bit
+pub const CVSSA0: u8 = 0x50;
+/// canvas image width
+pub const CVS_IMWTH0: u8 = 0x54;
+/// active window upper left x, y, width and height
+pub const AWUL_X0: u8 = 0x56;
+pub const AWUL_Y0: u8 = 0x58;
+pub const AW_WTH0: u8 = 0x5a;
+pub const AW_HT0: u8 = 0x5c;
+/// canvas addressing mode and color depth
+pub const AW_COLOR: u8 = 0x5e;
+/// graphic read/write position
+pub const CURH0: u8 = 0x5f;
+pub const CURV0: u8 = 0x61;
+/// text write position
+pub const F_CURX0: u8 = 0x63;
+pub const F_CURY0: u8 = 0x65;
+
+/// line and triangle draw control
+pub const DCR0: u8 = 0x67;
+/// line start / rectangle corner
+pub const DLHSR0: u8 = 0x68;
+pub const DLVSR0: u8 = 0x6a;
+/// line end / opposite rectangle corner
+pub const DLHER0: u8 = 0x6c;
+pub const DLVER0: u8 = 0x6e;
+/// triangle third point
+pub const DTPH0: u8 = 0x70;
+pub const DTPV0: u8 = 0x72;
+/// ellipse, curve and rectangle draw control
+pub const DCR1: u8 = 0x76;
+/// ellipse and rounded rectangle corner radii
+pub const ELL_A0: u8 = 0x77;
+pub const ELL_B0: u8 = 0x79;
+/// ellipse and curve center
+pub const DEHR0: u8 = 0x7b;
+pub const DEVR0: u8 = 0x7d;
+
+/// PWM prescaler
+pub const PSCLR: u8 = 0x84;
+/// PWM clock mux and pin function
+pub const PMUXR: u8 = 0x85;
+/// PWM configuration
+pub const PCFGR: u8 = 0x86;
+/// timer 0 compare and count buffers
+pub const TCMPB0L: u8 = 0x88;
+pub const TCNTB0L: u8 = 0x8a;
+/// timer 1 compare and count buffers
+pub const TCMPB1L: u8 = 0x8c;
+pub const TCNTB1L: u8 = 0x8e;
+
+/// BTE enable and status
+pub const BTE_CTRL0: u8 = 0x90;
+/// BTE raster and operation codes
+pub const BTE_CTRL1: u8 = 0x91;
+/// BTE source and destination color depths
+pub const BTE_COLR: u8 = 0x92;
+/// BTE source 0 address, width, x and y
+pub const S0_STR0: u8 = 0x93;
+pub const S0_WTH0: u8 = 0x97;
+pub const S0_X0: u8 = 0x99;
+pub const S0_Y0: u8 = 0x9b;
+/// BTE source 1 address, width, x and y
+pub const S1_STR0: u8 = 0x9d;
+pub const S1_WTH0: u8 = 0xa1;
+pub const S1_X0: u8 = 0xa3;
+pub const S1_Y0: u8 = 0xa5;
+/// BTE destination address, width, x and y
+pub const DT_STR0: u8 = 0xa7;
+pub const DT_WTH0: u8 = 0xab;
+pub const DT_X0: u8 = 0xad;
+pub const DT_Y0: u8 = 0xaf;
+/// BTE block width and height
+pub const BTE_WTH0: u8 = 0xb1;
+pub const BTE_HIG0: u8 = 0xb3;
+/// BTE alpha blending level
+pub const APB_CTRL: u8 = 0xb5;
+
+/// DMA control and status
+pub const DMA_CTRL: u8 = 0xb6;
+/// serial flash control
+pub const SFL_CTRL: u8 = 0xb7;
+/// serial flash clock divisor
+pub const SPI_DIVSOR: u8 = 0xbb;
+/// DMA source address, 32 bit
+pub const DMA_SSTR0: u8 = 0xbc;
+/// DMA destination x and y
+pub const DMA_DX0: u8 = 0xc0;
+pub const DMA_DY0: u8 = 0xc2;
+/// DMA block width and height
+pub const DMAW_WTH0: u8 = 0xc6;
+pub const DMAW_HIGH0: u8 = 0xc8;
+/// DMA source picture width
+pub const DMA_SWTH0: u8 = 0xca;
+
+/// character generator control
+pub const CCR0: u8 = 0xcc;
+pub const CCR1: u8 = 0xcd;
+/// external font ROM type and character encoding
+pub const GTFNT_SEL: u8 = 0xce;
+pub const GTFNT_CR: u8 = 0xcf;
+/// text line gap and character spacing
+pub const FLDR: u8 = 0xd0;
+pub const F2FSSR: u8 = 0xd1;
+/// foreground color
+pub const FGCR: u8 = 0xd2;
+pub const FGCG: u8 = 0xd3;
+pub const FGCB: u8 = 0xd4;
+/// background color
+pub const BGCR: u8 = 0xd5;
+pub const BGCG: u8 = 0xd6;
+pub const BGCB: u8 = 0xd7;
+
+/// power management
+pub const PMU: u8 = 0xdf;
+
+/// SDRAM attributes, mode, refresh interval and control
+pub const SDRAR: u8 = 0xe0;
+pub const SDRMD: u8 = 0xe1;
+pub const SDR_REF_ITVL0: u8 = 0xe2;
+pub const SDR_REF_ITVL1: u8 = 0xe3;
+pub const SDRCR: u8 = 0xe4;
+
+/// I2C master clock prescaler
+pub const I2CMCPR0: u8 = 0xe5;
+/// I2C master transmit and receive data
+pub const I2CMTXR: u8 = 0xe7;
+pub const I2CMRXR: u8 = 0xe8;
+/// I2C master command
+pub const I2CMCMDR: u8 = 0xe9;
+/// I2C master status
+pub const I2CMSTUR: u8 = 0xea;
+
+/// GPIO direction and data
+pub const GPIOAD: u8 = 0xf0;
+pub const GPIOA: u8 = 0xf1;
+pub const GPIOB: u8 = 0xf2;
+pub const GPIOCD: u8 = 0xf3;
+pub const GPIOC: u8 = 0xf4;
+pub const GPIODD: u8 = 0xf5;
+pub const GPIOD: u8 = 0xf6;
+pub const GPIOED: u8 = 0xf7;
+pub const GPIOE: u8 = 0xf8;
+pub const GPIOFD: u8 = 0xf9;
+pub const GPIOF: u8 = 0xfa;
+
+/// key scan control
+pub const KSCR1: u8 = 0xfb;
+pub const KSCR2: u8 = 0xfc;
+/// key scan data, three key codes
+pub const KSDR0: u8 = 0xfd;
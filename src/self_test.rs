@@ -0,0 +1,135 @@
+//! Controller self test, for checking boards at manufacturing
+
+use crate::{common, regs, Interface, Res, ER5517};
+use embedded_hal::blocking::delay::DelayUs;
+
+//pixels written and read back through the memory port
+const SDRAM_PIXELS: u16 = 4;
+
+/// What [`ER5517::self_test`] found, each check runs even if an earlier one failed
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct SelfTestReport {
+    /// scratch register write and read back
+    pub register_readback: bool,
+    /// pattern written through the memory port read back unchanged
+    pub sdram_readback: bool,
+    /// a small rectangle fill finished within the poll limit
+    pub draw_done: bool,
+    /// status reads until the fill finished
+    pub draw_polls: u32,
+    /// REG[01h] bit 7
+    pub pll_locked: bool,
+    pub sdram_ready: bool,
+    /// color bar bit read back set while enabled
+    pub color_bars: bool,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.register_readback
+            && self.sdram_readback
+            && self.draw_done
+            && self.pll_locked
+            && self.sdram_ready
+            && self.color_bars
+    }
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Exercise the bus, SDRAM, drawing engine and clocks of an initialized controller.
+    ///
+    /// Overwrites a few pixels in the top left corner of the canvas and shows the color bars
+    /// for half a second. Failed checks are reported as `false`, errors are only returned in
+    /// power saving or when the full window can't be restored. A millisecond only delay can be
+    /// wrapped in a [`DelayMsShim`](crate::DelayMsShim), like for [`ER5517::init`].
+    pub fn self_test<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        delay: &mut D,
+    ) -> Res<SelfTestReport, PinErr, SPIErr> {
+        self.check_awake()?;
+        let mut report = SelfTestReport {
+            register_readback: self.test_register().unwrap_or(false),
+            sdram_readback: self.test_sdram().unwrap_or(false),
+            ..Default::default()
+        };
+        //a failed read back can leave the small window behind
+        let (w, h) = self.dimensions();
+        self.active_window_raw(0, 0, w, h)?;
+        if let Ok(polls) = self.test_draw() {
+            report.draw_done = polls.is_some();
+            report.draw_polls = polls.unwrap_or(self.poll_config().max_polls);
+        }
+        report.pll_locked = self
+            .register_read_live(regs::CCR)
+            .map(|v| v & 0x80 != 0)
+            .unwrap_or(false);
+        report.sdram_ready = self
+            .status_read()
+            .map(|s| s & common::STATUS_SDRAM_READY != 0)
+            .unwrap_or(false);
+        report.color_bars = self.test_color_bars(delay).unwrap_or(false);
+        Ok(report)
+    }
+
+    //the BTE width is unused outside of BTE operations
+    fn test_register(&mut self) -> Res<bool, PinErr, SPIErr> {
+        let saved = self.register_read(regs::BTE_WTH0)?;
+        let mut ok = true;
+        for v in [0x55, 0xaa] {
+            self.register_write(regs::BTE_WTH0, v)?;
+            ok &= self.register_read(regs::BTE_WTH0)? == v;
+        }
+        self.register_write(regs::BTE_WTH0, saved)?;
+        Ok(ok)
+    }
+
+    fn test_sdram(&mut self) -> Res<bool, PinErr, SPIErr> {
+        let len = SDRAM_PIXELS as usize * self.color_mode().bytes_per_pixel() as usize;
+        let mut pattern = [0u8; 12];
+        for (i, b) in pattern[..len].iter_mut().enumerate() {
+            *b = 0xa5 ^ (i as u8).wrapping_mul(0x3b);
+        }
+        if self.is_text_mode() {
+            self.graphic_mode()?;
+        }
+        //the same window and position as read_pixels so both address the same pixels
+        self.active_window_raw(0, 0, SDRAM_PIXELS, 1)?;
+        self.goto_pixel(0, 0)?;
+        self.cmd_write(regs::MRWDP)?;
+        self.data_write_buf(&pattern[..len])?;
+        self.wait_wr_fifo_empty()?;
+        let mut read = [0u8; 12];
+        self.read_pixels(0, 0, SDRAM_PIXELS, 1, &mut read[..len])?;
+        Ok(read[..len] == pattern[..len])
+    }
+
+    //status reads until the fill finished, None if it didn't within max_polls
+    fn test_draw(&mut self) -> Res<Option<u32>, PinErr, SPIErr> {
+        self.line_start(0, 0)?;
+        self.line_end(SDRAM_PIXELS - 1, 0)?;
+        self.rect_fill_start()?;
+        let mut done = None;
+        for n in 1..=self.poll_config().max_polls {
+            if !self.draw_busy()? {
+                done = Some(n);
+                break;
+            }
+            self.poll_pause();
+        }
+        Ok(done)
+    }
+
+    fn test_color_bars<D: DelayUs<u32> + ?Sized>(
+        &mut self,
+        delay: &mut D,
+    ) -> Res<bool, PinErr, SPIErr> {
+        self.color_bars(true)?;
+        let ok = self.register_read_live(regs::DPCR)? & 0b0010_0000 != 0;
+        delay.delay_us(500_000);
+        self.color_bars(false)?;
+        Ok(ok)
+    }
+}
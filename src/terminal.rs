@@ -0,0 +1,212 @@
+//! Text console on the hardware text mode, for logging with `writeln!`
+
+use crate::{Error, Interface, NoPin, Res, Tftmc043Panel, TFTMC043};
+use core::fmt;
+use embedded_graphics_core::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+
+/// What a [`Terminal`] does once the last line is full
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TerminalOverflow {
+    /// continue on the first line
+    Wrap,
+    /// move the screen up a line with the BTE
+    Scroll,
+}
+
+/// `core::fmt::Write` console in character cells, each new line is cleared before it is written
+///
+/// Uses the selected font, the cell size has to match it. Positions are in memory coordinates,
+/// so it is meant for the normal orientation.
+pub struct Terminal<'a, SPI, CS, P = Tftmc043Panel, RST = NoPin>
+where
+    SPI: Interface<CS>,
+{
+    display: &'a mut TFTMC043<SPI, CS, P, RST>,
+    //top left of the first cell
+    origin: (u16, u16),
+    cell: (u16, u16),
+    cols: u16,
+    rows: u16,
+    col: u16,
+    row: u16,
+    overflow: TerminalOverflow,
+    fg: (u8, u8, u8),
+    bg: (u8, u8, u8),
+    error: Option<Error<SPI::PinError, SPI::SpiError>>,
+}
+
+impl<'a, SPI, CS, P, RST, PinErr, SPIErr> Terminal<'a, SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// A terminal over the whole screen in `cell_w` x `cell_h` cells, starting at the top left,
+    /// white on black.
+    pub fn new(
+        display: &'a mut TFTMC043<SPI, CS, P, RST>,
+        cell_w: u16,
+        cell_h: u16,
+        overflow: TerminalOverflow,
+    ) -> Res<Self, PinErr, SPIErr> {
+        let (w, h) = display.dimensions();
+        //no cells, zero sized or wider than the screen
+        let (cols, rows) = match (w.checked_div(cell_w), h.checked_div(cell_h)) {
+            (Some(cols), Some(rows)) if cols > 0 && rows > 0 => (cols, rows),
+            _ => return Err(Error::InvalidParam),
+        };
+        let mut t = Self {
+            display,
+            origin: (0, 0),
+            cell: (cell_w, cell_h),
+            cols,
+            rows,
+            col: 0,
+            row: 0,
+            overflow,
+            fg: (0xff, 0xff, 0xff),
+            bg: (0, 0, 0),
+            error: None,
+        };
+        t.clear_row()?;
+        Ok(t)
+    }
+
+    //a terminal of `cols` x `rows` cells at `origin` continuing at `pos`, without clearing
+    #[cfg(feature = "debug-strip")]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn resume(
+        display: &'a mut TFTMC043<SPI, CS, P, RST>,
+        origin: (u16, u16),
+        cell: (u16, u16),
+        (cols, rows): (u16, u16),
+        (col, row): (u16, u16),
+        overflow: TerminalOverflow,
+        fg: (u8, u8, u8),
+        bg: (u8, u8, u8),
+    ) -> Self {
+        Self {
+            display,
+            origin,
+            cell,
+            cols,
+            rows,
+            col,
+            row,
+            overflow,
+            fg,
+            bg,
+            error: None,
+        }
+    }
+
+    /// Colors for the following writes.
+    pub fn set_colors(&mut self, fg: (u8, u8, u8), bg: (u8, u8, u8)) {
+        self.fg = fg;
+        self.bg = bg;
+    }
+
+    /// Column and row of the next character.
+    pub fn position(&self) -> (u16, u16) {
+        (self.col, self.row)
+    }
+
+    /// The error behind the last `fmt::Error`, if any.
+    pub fn take_error(&mut self) -> Option<Error<PinErr, SPIErr>> {
+        self.error.take()
+    }
+
+    /// Write `s`, `\n` starts a new line and `\r` returns to the start of the current one.
+    pub fn write(&mut self, s: &str) -> Res<(), PinErr, SPIErr> {
+        let mut start = 0;
+        let mut n = 0;
+        for (i, c) in s.char_indices() {
+            if c == '\n' || c == '\r' {
+                self.write_run(s.get(start..i).unwrap_or_default())?;
+                if c == '\n' {
+                    self.newline()?;
+                } else {
+                    self.col = 0;
+                }
+                start = i.saturating_add(1);
+                n = 0;
+                continue;
+            }
+            if self.col.saturating_add(n) == self.cols {
+                self.write_run(s.get(start..i).unwrap_or_default())?;
+                self.newline()?;
+                start = i;
+                n = 0;
+            }
+            n = n.saturating_add(1);
+        }
+        self.write_run(s.get(start..).unwrap_or_default())
+    }
+
+    //characters that fit on the current line
+    fn write_run(&mut self, text: &str) -> Res<(), PinErr, SPIErr> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        let ((x, y), (cw, ch)) = (self.origin, self.cell);
+        self.display.set_font_colors(self.fg, self.bg)?;
+        //the cells are inside the screen, see Terminal::new
+        let at = (
+            x.saturating_add(self.col.saturating_mul(cw)),
+            y.saturating_add(self.row.saturating_mul(ch)),
+        );
+        self.display.set_text_cursor(at.0, at.1)?;
+        self.display.write_text(text)?;
+        self.col = self.col.saturating_add(text.chars().count() as u16);
+        Ok(())
+    }
+
+    fn newline(&mut self) -> Res<(), PinErr, SPIErr> {
+        self.col = 0;
+        if self.row.saturating_add(1) < self.rows {
+            self.row = self.row.saturating_add(1);
+        } else {
+            match self.overflow {
+                TerminalOverflow::Wrap => self.row = 0,
+                TerminalOverflow::Scroll if self.rows > 1 => {
+                    let (x, y) = self.origin;
+                    let (w, ch) = (self.cols.saturating_mul(self.cell.0), self.cell.1);
+                    if self.display.is_text_mode() {
+                        self.display.graphic_mode()?;
+                    }
+                    let h = self.rows.saturating_sub(1).saturating_mul(ch);
+                    self.display
+                        .bte_copy_within((x, y.saturating_add(ch)), (x, y), w, h)?;
+                }
+                TerminalOverflow::Scroll => (),
+            }
+        }
+        self.clear_row()
+    }
+
+    fn clear_row(&mut self) -> Res<(), PinErr, SPIErr> {
+        let ((x, y), (cw, ch)) = (self.origin, self.cell);
+        if self.display.is_text_mode() {
+            self.display.graphic_mode()?;
+        }
+        let row = Rectangle::new(
+            Point::new(
+                x as i32,
+                y.saturating_add(self.row.saturating_mul(ch)) as i32,
+            ),
+            Size::new(self.cols.saturating_mul(cw) as u32, ch as u32),
+        );
+        let (r, g, b) = self.bg;
+        self.display.fill_area(&row, Rgb888::new(r, g, b))
+    }
+}
+
+impl<SPI, CS, P, RST, PinErr, SPIErr> fmt::Write for Terminal<'_, SPI, CS, P, RST>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
@@ -0,0 +1,155 @@
+//! Full screen patterns for checking a panel without a host frame buffer
+
+use crate::{regs, Error, Interface, Res, ER5517};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TestPattern {
+    /// the controller's built-in color bars
+    ColorBars,
+    Red,
+    Green,
+    Blue,
+    White,
+    Black,
+    /// 1 pixel black and white checkerboard
+    Checkerboard,
+    /// 1 pixel white frame around the edge on black, for alignment checks
+    Border,
+}
+
+impl<SPI, CS, PinErr, SPIErr> ER5517<SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Show `pattern`, drawn to the canvas except for the color bars.
+    ///
+    /// The fg color set with [`ER5517::fg_color`] is kept for later drawing.
+    pub fn test_pattern(&mut self, pattern: TestPattern) -> Res<(), PinErr, SPIErr> {
+        self.color_bars(pattern == TestPattern::ColorBars)?;
+        let (w, h) = self.dimensions();
+        let white = (0xff, 0xff, 0xff);
+        match pattern {
+            TestPattern::ColorBars => Ok(()),
+            TestPattern::Red => self.pattern_fill(0, 0, w, h, (0xff, 0, 0)),
+            TestPattern::Green => self.pattern_fill(0, 0, w, h, (0, 0xff, 0)),
+            TestPattern::Blue => self.pattern_fill(0, 0, w, h, (0, 0, 0xff)),
+            TestPattern::White => self.pattern_fill(0, 0, w, h, white),
+            TestPattern::Black => self.pattern_fill(0, 0, w, h, (0, 0, 0)),
+            TestPattern::Checkerboard => self.checkerboard(),
+            TestPattern::Border => {
+                let (right, bottom) = w
+                    .checked_sub(1)
+                    .zip(h.checked_sub(1))
+                    .ok_or(Error::InvalidParam)?;
+                self.pattern_fill(0, 0, w, h, (0, 0, 0))?;
+                self.pattern_fill(0, 0, w, 1, white)?;
+                self.pattern_fill(0, bottom, w, 1, white)?;
+                self.pattern_fill(0, 0, 1, h, white)?;
+                self.pattern_fill(right, 0, 1, h, white)
+            }
+        }
+    }
+
+    fn pattern_fill(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        c: (u8, u8, u8),
+    ) -> Res<(), PinErr, SPIErr> {
+        let far = |v: u16, n: u16| v.checked_add(n)?.checked_sub(1);
+        let (x2, y2) = far(x, w).zip(far(y, h)).ok_or(Error::InvalidParam)?;
+        self.check_awake()?;
+        //the lazy restore puts the caller's fg color back before it draws with it
+        self.write_fg(c)?;
+        self.line_start(x, y)?;
+        self.line_end(x2, y2)?;
+        self.rect_fill_raw()?;
+        self.busy_draw()
+    }
+
+    //write the first two rows from the host, then let the BTE double them down the canvas
+    fn checkerboard(&mut self) -> Res<(), PinErr, SPIErr> {
+        //in memory coordinates, the pattern looks the same in any orientation
+        let (w, h) = (self.config().width, self.config().height);
+        let bpp = self.color_mode().bytes_per_pixel() as usize;
+        self.bte_write_start(0, 0, w, 2)?;
+        let mut t = self.begin();
+        t.cmd(regs::MRWDP)?;
+        for y in 0..2u16 {
+            for x in 0..w {
+                //all ones is white and all zeros black in every color mode
+                let v = if (x ^ y) & 1 == 0 { 0xff } else { 0x00 };
+                for _ in 0..bpp {
+                    t.data(v)?;
+                }
+            }
+        }
+        t.commit()?;
+        self.busy_draw()?;
+
+        let mut done = 2;
+        while done < h {
+            let n = done.min(h.saturating_sub(done));
+            self.bte_copy_within((0, 0), (0, done), w, n)?;
+            done = done.saturating_add(n);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::support::{self, Panel};
+    use crate::ColorMode;
+    use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+
+    #[test]
+    fn keeps_fg_color() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.fg_color(1, 2, 3).unwrap();
+        display.test_pattern(TestPattern::Red).unwrap();
+        assert_eq!(rec.register(regs::FGCR), 0xff);
+        display.line_start(0, 0).unwrap();
+        display.line_end(1, 1).unwrap();
+        display.rect_fill().unwrap();
+        let fg = [regs::FGCR, regs::FGCG, regs::FGCB].map(|r| rec.register(r));
+        assert_eq!(fg, [1, 2, 3]);
+    }
+
+    #[test]
+    fn border() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.test_pattern(TestPattern::Border).unwrap();
+        let mut panel = Panel::new(480, 272, ColorMode::SixteenBit);
+        panel.replay(&rec.frames());
+        let (white, black) = (panel.bytes(Rgb888::WHITE), panel.bytes(Rgb888::BLACK));
+        for (x, y) in [(0, 0), (479, 0), (0, 271), (479, 271), (240, 0), (0, 136)] {
+            assert_eq!(panel.pixel(x, y), white, "{x}, {y}");
+        }
+        for (x, y) in [(1, 1), (478, 270), (240, 136)] {
+            assert_eq!(panel.pixel(x, y), black, "{x}, {y}");
+        }
+    }
+
+    #[test]
+    fn fill_past_u16_rejected() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        let c = (0, 0, 0);
+        assert_eq!(
+            display.pattern_fill(u16::MAX, 0, 2, 1, c),
+            Err(Error::InvalidParam)
+        );
+        assert_eq!(
+            display.pattern_fill(0, 1, 1, u16::MAX, c),
+            Err(Error::InvalidParam)
+        );
+        assert_eq!(
+            display.pattern_fill(0, 0, 0, 1, c),
+            Err(Error::InvalidParam)
+        );
+        assert!(rec.frames().is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+//! Host side SPI recorder for checking register sequences without hardware.
+//!
+//! Needs `std`, so only enable the `testing` feature for host builds.
+
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+use std::{cell::RefCell, collections::VecDeque, convert::Infallible, rc::Rc, vec::Vec};
+
+use crate::common;
+
+#[derive(Default)]
+struct State {
+    selected: bool,
+    frames: Vec<Vec<u8>>,
+    current: Vec<u8>,
+    status: u8,
+    data: VecDeque<u8>,
+    data_default: u8,
+    //register memory for loopback reads, and the register the last command selected
+    loopback: bool,
+    regs: Vec<u8>,
+    reg: Option<u8>,
+}
+
+/// Records every byte written, one frame per chip select assertion, and answers reads from
+/// preloaded responses.
+#[derive(Clone, Default)]
+pub struct RecordingSpi {
+    state: Rc<RefCell<State>>,
+}
+
+/// Chip select paired with a [`RecordingSpi`], it delimits the recorded frames.
+pub struct RecordingCs {
+    state: Rc<RefCell<State>>,
+}
+
+impl RecordingSpi {
+    /// A recorder and its chip select, status reads return `0x44` (idle, SDRAM ready) by default.
+    pub fn new() -> (Self, RecordingCs) {
+        let spi = Self::default();
+        spi.set_status(common::STATUS_SDRAM_READY | common::STATUS_WR_FIFO_EMPTY);
+        let cs = RecordingCs {
+            state: spi.state.clone(),
+        };
+        (spi, cs)
+    }
+
+    /// A recorder answering like a powered controller, for running init and drawing on the
+    /// host: loopback reads with the registers at their reset values.
+    pub fn new_loopback() -> (Self, RecordingCs) {
+        let (spi, cs) = Self::new();
+        spi.set_loopback(true);
+        //the reset values the driver assumes
+        for (reg, v) in common::SHADOWED {
+            spi.set_register(reg, v);
+        }
+        (spi, cs)
+    }
+
+    /// Value returned by every status read.
+    pub fn set_status(&self, v: u8) {
+        self.state.borrow_mut().status = v;
+    }
+
+    /// Queue a response for the next data read.
+    pub fn push_data(&self, v: u8) {
+        self.state.borrow_mut().data.push_back(v);
+    }
+
+    /// Value returned by data reads once the queue is empty.
+    pub fn set_data_default(&self, v: u8) {
+        self.state.borrow_mut().data_default = v;
+    }
+
+    /// Answer data reads the queue doesn't with the last value written to the selected register,
+    /// like a controller whose registers all read back.
+    pub fn set_loopback(&self, on: bool) {
+        let mut s = self.state.borrow_mut();
+        s.loopback = on;
+        s.regs.resize(256, 0);
+    }
+
+    /// Preset what `reg` reads back in loopback.
+    pub fn set_register(&self, reg: u8, v: u8) {
+        let mut s = self.state.borrow_mut();
+        s.regs.resize(256, 0);
+        s.regs[reg as usize] = v;
+    }
+
+    /// The last value written to `reg` while in loopback, its preset or 0 if it never was.
+    pub fn register(&self, reg: u8) -> u8 {
+        let s = self.state.borrow();
+        s.regs.get(reg as usize).copied().unwrap_or(0)
+    }
+
+    /// All completed frames, including reads, in order.
+    pub fn frames(&self) -> Vec<Vec<u8>> {
+        self.state.borrow().frames.clone()
+    }
+
+    /// Register writes as `(register, value)`, a command frame followed by a data frame.
+    ///
+    /// Data writes after a read back of the same register, as done by read-modify-write, are
+    /// reported against that register too.
+    pub fn register_writes(&self) -> Vec<(u8, u8)> {
+        let mut out = Vec::new();
+        let mut reg = None;
+        for f in self.state.borrow().frames.iter() {
+            match f.as_slice() {
+                [common::CMD_WRITE, r] => reg = Some(*r),
+                [common::DATA_WRITE, v] => {
+                    if let Some(r) = reg {
+                        out.push((r, *v));
+                    }
+                }
+                _ => (),
+            }
+        }
+        out
+    }
+
+    /// Forget the recorded frames, responses are kept.
+    pub fn clear(&self) {
+        let mut s = self.state.borrow_mut();
+        s.frames.clear();
+        s.current.clear();
+    }
+}
+
+impl Write<u8> for RecordingSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.state.borrow_mut().current.extend_from_slice(words);
+        Ok(())
+    }
+}
+
+impl Transfer<u8> for RecordingSpi {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        let mut s = self.state.borrow_mut();
+        s.current.extend_from_slice(words);
+        //reads are a prefix byte followed by the response bytes, the prefix may have been
+        //written separately in the same frame
+        let prefix = match s.current.first() {
+            Some(p) => *p,
+            None => return Ok(words),
+        };
+        let skip = if s.current.len() == words.len() { 1 } else { 0 };
+        for b in words.iter_mut().skip(skip) {
+            *b = match prefix {
+                common::STATUS_READ => s.status,
+                common::DATA_READ => match s.data.pop_front() {
+                    Some(d) => d,
+                    None => match (s.loopback, s.reg) {
+                        (true, Some(r)) => s.regs[r as usize],
+                        _ => s.data_default,
+                    },
+                },
+                _ => 0,
+            };
+        }
+        Ok(words)
+    }
+}
+
+impl OutputPin for RecordingCs {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let mut s = self.state.borrow_mut();
+        s.selected = true;
+        s.current.clear();
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut s = self.state.borrow_mut();
+        if s.selected {
+            let f = core::mem::take(&mut s.current);
+            match f.as_slice() {
+                [common::CMD_WRITE, r] => s.reg = Some(*r),
+                [common::DATA_WRITE, .., v] if s.loopback => {
+                    if let Some(r) = s.reg {
+                        s.regs[r as usize] = *v;
+                    }
+                }
+                _ => (),
+            }
+            s.frames.push(f);
+        }
+        s.selected = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod support {
+    use super::{RecordingCs, RecordingSpi};
+    use crate::{color, common, regs, ColorMode, PanelConfig, TFTMC043};
+    use embedded_graphics_core::pixelcolor::Rgb888;
+    use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+    use std::{vec, vec::Vec};
+
+    pub(crate) struct NoDelay;
+
+    impl DelayMs<u16> for NoDelay {
+        fn delay_ms(&mut self, _: u16) {}
+    }
+
+    impl DelayUs<u32> for NoDelay {
+        fn delay_us(&mut self, _: u32) {}
+    }
+
+    pub(crate) type Display = TFTMC043<RecordingSpi, RecordingCs>;
+
+    /// A display over a loopback recorder, not initialized
+    pub(crate) fn display(mode: ColorMode) -> (Display, RecordingSpi) {
+        display_with_config(mode, PanelConfig::default())
+    }
+
+    pub(crate) fn display_with_config(
+        mode: ColorMode,
+        config: PanelConfig,
+    ) -> (Display, RecordingSpi) {
+        let (spi, cs) = RecordingSpi::new_loopback();
+        let rec = spi.clone();
+        (TFTMC043::new_with_config(spi, cs, mode, config), rec)
+    }
+
+    /// [`display`] after init, with the recorded frames cleared
+    pub(crate) fn initialized(mode: ColorMode) -> (Display, RecordingSpi) {
+        let (mut display, rec) = display(mode);
+        display.init(&mut NoDelay).unwrap();
+        rec.clear();
+        (display, rec)
+    }
+
+    /// Screen memory rebuilt from recorded frames, memory port writes inside the active window
+    /// and rectangle or line fills in the fg color, for the default orientation and page 0.
+    pub(crate) struct Panel {
+        w: u16,
+        h: u16,
+        mode: ColorMode,
+        px: Vec<[u8; 3]>,
+        regs: Vec<u8>,
+        selected: Option<u8>,
+        cursor: (u16, u16),
+    }
+
+    impl Panel {
+        pub(crate) fn new(w: u16, h: u16, mode: ColorMode) -> Self {
+            Self {
+                w,
+                h,
+                mode,
+                px: vec![[0; 3]; w as usize * h as usize],
+                regs: vec![0; 256],
+                selected: None,
+                cursor: (0, 0),
+            }
+        }
+
+        /// The memory bytes of the pixel at `x`, `y`, unused bytes 0.
+        pub(crate) fn pixel(&self, x: u16, y: u16) -> [u8; 3] {
+            self.px[y as usize * self.w as usize + x as usize]
+        }
+
+        /// `c` as the memory bytes [`Panel::pixel`] returns in this color mode.
+        pub(crate) fn bytes(&self, c: Rgb888) -> [u8; 3] {
+            match self.mode {
+                ColorMode::EightBit => [color::rgb332_to_byte(c.into()), 0, 0],
+                ColorMode::SixteenBit => {
+                    let [b0, b1] = color::rgb565_to_bytes(color::rgb888_to_565(c));
+                    [b0, b1, 0]
+                }
+                ColorMode::TwentyFourBit => color::rgb888_to_bytes(c),
+            }
+        }
+
+        pub(crate) fn replay(&mut self, frames: &[Vec<u8>]) {
+            for f in frames {
+                match f.as_slice() {
+                    [common::CMD_WRITE, r] => self.selected = Some(*r),
+                    [common::DATA_WRITE, data @ ..] => match self.selected {
+                        Some(regs::MRWDP) => self.write_pixels(data),
+                        Some(r) => {
+                            for &v in data {
+                                self.write_register(r, v);
+                            }
+                        }
+                        None => (),
+                    },
+                    _ => (),
+                }
+            }
+        }
+
+        fn reg16(&self, reg: u8) -> u16 {
+            u16::from_le_bytes([self.regs[reg as usize], self.regs[reg as usize + 1]])
+        }
+
+        fn write_register(&mut self, reg: u8, v: u8) {
+            self.regs[reg as usize] = v;
+            match reg {
+                regs::CURH0 | 0x60 => self.cursor.0 = self.reg16(regs::CURH0),
+                regs::CURV0 | 0x62 => self.cursor.1 = self.reg16(regs::CURV0),
+                regs::DCR1 if v == 0xe0 => self.fill(),
+                regs::DCR0 if v == 0x80 => self.fill(),
+                _ => (),
+            }
+        }
+
+        fn write_pixels(&mut self, data: &[u8]) {
+            let bpp = match self.mode {
+                ColorMode::EightBit => 1,
+                ColorMode::SixteenBit => 2,
+                ColorMode::TwentyFourBit => 3,
+            };
+            let (x0, w) = (self.reg16(regs::AWUL_X0), self.reg16(regs::AW_WTH0));
+            for p in data.chunks(bpp) {
+                let mut b = [0; 3];
+                b[..p.len()].copy_from_slice(p);
+                let (x, y) = self.cursor;
+                self.set(x, y, b);
+                self.cursor = if x + 1 >= x0 + w { (x0, y + 1) } else { (x + 1, y) };
+            }
+        }
+
+        fn fill(&mut self) {
+            let (x1, y1) = (self.reg16(regs::DLHSR0), self.reg16(regs::DLVSR0));
+            let (x2, y2) = (self.reg16(regs::DLHER0), self.reg16(regs::DLVER0));
+            let fg = Rgb888::new(
+                self.regs[regs::FGCR as usize],
+                self.regs[regs::FGCG as usize],
+                self.regs[regs::FGCB as usize],
+            );
+            let b = self.bytes(fg);
+            for y in y1..=y2 {
+                for x in x1..=x2 {
+                    self.set(x, y, b);
+                }
+            }
+        }
+
+        fn set(&mut self, x: u16, y: u16, b: [u8; 3]) {
+            assert!(x < self.w && y < self.h, "write off screen at {x}, {y}");
+            let i = y as usize * self.w as usize + x as usize;
+            self.px[i] = b;
+        }
+    }
+}
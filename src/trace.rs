@@ -0,0 +1,103 @@
+//! Recording the framed SPI transactions, for debugging the protocol without a logic analyzer
+
+use crate::ER5517;
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+/// Direction of the bytes after a transaction's prefix
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Dir {
+    Write,
+    Read,
+}
+
+/// Receives every transaction from [`ER5517::set_trace_sink`], called before writes are sent
+/// and after reads returned.
+///
+/// Runs in the middle of driver operations, keep it to copying the bytes.
+pub trait TraceSink {
+    /// One chip select assertion, `prefix` is the first byte sent and `bytes` the rest, sent
+    /// or received. Reads with a dummy byte include it.
+    fn record(&self, dir: Dir, prefix: u8, bytes: &[u8]);
+}
+
+/// The last `N` bytes of trace, each transaction as a `b'W'` or `b'R'` marker, the prefix,
+/// the length as a little endian u16 and the bytes
+///
+/// Meant to be a `static`, it doesn't lock, so only one driver should record into it.
+pub struct RingTraceSink<const N: usize> {
+    buf: [AtomicU8; N],
+    //bytes recorded since the last clear, the next one goes to written % N
+    written: AtomicUsize,
+}
+
+impl<const N: usize> RingTraceSink<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [const { AtomicU8::new(0) }; N],
+            written: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.written.store(0, Ordering::Relaxed);
+    }
+
+    /// Copy the stored bytes to `out`, oldest first, and return how many there were.
+    ///
+    /// Once more than `N` bytes were recorded the oldest transaction is cut off at the start.
+    pub fn dump(&self, out: &mut [u8]) -> usize {
+        let written = self.written.load(Ordering::Relaxed);
+        let len = written.min(N).min(out.len());
+        let start = written - len;
+        for (i, o) in out[..len].iter_mut().enumerate() {
+            *o = self.buf[(start + i) % N].load(Ordering::Relaxed);
+        }
+        len
+    }
+
+    fn push(&self, bytes: &[u8]) {
+        if N == 0 {
+            return;
+        }
+        let mut written = self.written.load(Ordering::Relaxed);
+        //only the last N bytes would survive anyway
+        let skip = bytes.len().saturating_sub(N);
+        written = written.wrapping_add(skip);
+        for b in &bytes[skip..] {
+            self.buf[written % N].store(*b, Ordering::Relaxed);
+            written = written.wrapping_add(1);
+        }
+        self.written.store(written, Ordering::Relaxed);
+    }
+}
+
+impl<const N: usize> Default for RingTraceSink<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> TraceSink for RingTraceSink<N> {
+    fn record(&self, dir: Dir, prefix: u8, bytes: &[u8]) {
+        let marker = match dir {
+            Dir::Write => b'W',
+            Dir::Read => b'R',
+        };
+        let len = (bytes.len().min(u16::MAX as usize) as u16).to_le_bytes();
+        self.push(&[marker, prefix, len[0], len[1]]);
+        self.push(bytes);
+    }
+}
+
+impl<SPI, CS> ER5517<SPI, CS> {
+    /// Pass every transaction to `sink`, None stops tracing.
+    pub fn set_trace_sink(&mut self, sink: Option<&'static dyn TraceSink>) {
+        self.trace = sink;
+    }
+
+    pub(crate) fn trace(&self, dir: Dir, prefix: u8, bytes: &[u8]) {
+        if let Some(sink) = self.trace {
+            sink.record(dir, prefix, bytes);
+        }
+    }
+}
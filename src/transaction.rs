@@ -0,0 +1,149 @@
+//! Batched data writes
+
+use crate::{Interface, Res, ER5517};
+
+//bytes per burst, a burst never overruns an empty write FIFO
+const BURST: usize = 16;
+
+/// Collects data writes into bursts sharing one chip select assertion, from [`ER5517::begin`].
+///
+/// Each command write flushes the pending data first, so register writes still cost a command
+/// and a data transaction each. Chip select is released between those and between bursts, so a
+/// transaction isn't atomic, another device on a shared bus can be accessed in between. Pending
+/// data is flushed on drop, call [`Transaction::commit`] to see the errors.
+pub struct Transaction<'a, SPI, CS>
+where
+    SPI: Interface<CS>,
+{
+    driver: &'a mut ER5517<SPI, CS>,
+    buf: [u8; BURST],
+    len: usize,
+}
+
+impl<SPI, CS> ER5517<SPI, CS>
+where
+    SPI: Interface<CS>,
+{
+    pub fn begin(&mut self) -> Transaction<'_, SPI, CS> {
+        Transaction {
+            driver: self,
+            buf: [0; BURST],
+            len: 0,
+        }
+    }
+}
+
+impl<'a, SPI, CS, PinErr, SPIErr> Transaction<'a, SPI, CS>
+where
+    SPI: Interface<CS, PinError = PinErr, SpiError = SPIErr>,
+{
+    /// Select `cmd`, skipped while it is still selected like [`ER5517::register_write`], the
+    /// following data then joins the pending burst.
+    pub fn cmd(&mut self, cmd: u8) -> Res<(), PinErr, SPIErr> {
+        if !self.driver.still_selected(cmd) {
+            self.flush()?;
+            self.driver.select(cmd)?;
+        }
+        Ok(())
+    }
+
+    pub fn data(&mut self, data: u8) -> Res<(), PinErr, SPIErr> {
+        if self.len == BURST {
+            self.flush()?;
+        }
+        self.buf[self.len] = data;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// [`ER5517::register_write`] after the pending data, shadowed and verified the same way.
+    pub fn register_write(&mut self, cmd: u8, data: u8) -> Res<(), PinErr, SPIErr> {
+        self.flush()?;
+        self.driver.register_write(cmd, data)
+    }
+
+    /// Flush the pending data.
+    pub fn commit(mut self) -> Res<(), PinErr, SPIErr> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Res<(), PinErr, SPIErr> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let len = self.len;
+        self.len = 0;
+        //register writes don't go through the write FIFO
+        if !self.driver.register_selected() {
+            self.driver.fifo_reserve(len as u16)?;
+        }
+        self.driver.data_write_burst(&self.buf[..len])
+    }
+}
+
+impl<'a, SPI, CS> Drop for Transaction<'a, SPI, CS>
+where
+    SPI: Interface<CS>,
+{
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::support;
+    use crate::{common, regs, ColorMode, Error};
+
+    #[test]
+    fn register_write_bookkeeping() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.enable_shadow_registers();
+        rec.clear();
+        let mut t = display.begin();
+        t.register_write(regs::MPWCTR, 0x40).unwrap();
+        //still selected
+        t.register_write(regs::MPWCTR, 0x44).unwrap();
+        t.commit().unwrap();
+        assert_eq!(
+            rec.frames(),
+            [
+                [common::CMD_WRITE, regs::MPWCTR],
+                [common::DATA_WRITE, 0x40],
+                [common::DATA_WRITE, 0x44],
+            ]
+        );
+        rec.clear();
+        assert_eq!(display.register_read(regs::MPWCTR), Ok(0x44));
+        assert!(rec.frames().is_empty());
+    }
+
+    #[test]
+    fn data_updates_shadow() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.enable_shadow_registers();
+        let mut t = display.begin();
+        t.cmd(regs::MPWCTR).unwrap();
+        t.data(0x05).unwrap();
+        t.commit().unwrap();
+        rec.clear();
+        assert_eq!(display.register_read(regs::MPWCTR), Ok(0x05));
+        assert!(rec.frames().is_empty());
+    }
+
+    #[test]
+    fn register_write_verified() {
+        let (mut display, rec) = support::initialized(ColorMode::SixteenBit);
+        display.set_verify_writes(true);
+        rec.push_data(0x11);
+        let mut t = display.begin();
+        assert_eq!(
+            t.register_write(regs::FGCR, 0x10),
+            Err(Error::VerifyFailed {
+                reg: regs::FGCR,
+                wrote: 0x10,
+                read: 0x11
+            })
+        );
+    }
+}